@@ -0,0 +1,496 @@
+//! An [`AsyncInterpreter`] running inference on dedicated worker threads, so that async
+//! executors are never blocked by [`Interpreter::invoke()`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use tflitec::async_interpreter::AsyncInterpreterBuilder;
+//! use tflitec::model::Model;
+//! # async fn run() -> tflitec::Result<()> {
+//! let model = Arc::new(Model::new("tests/add.bin")?);
+//! let interpreter = AsyncInterpreterBuilder::new(model)
+//!     .workers(2)
+//!     .queue_depth(16)
+//!     .build()?;
+//! let input: Vec<u8> = [1.0f32; 192].iter().flat_map(|x| x.to_ne_bytes()).collect();
+//! let outputs = interpreter.submit(vec![input])?.await?;
+//! println!("{:?}", outputs[0].data::<f32>());
+//! interpreter.shutdown();
+//! # Ok(())
+//! # }
+//! ```
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+
+use crate::interpreter::{Interpreter, InterpreterBuilder, SharedConfiguration};
+use crate::model::Model;
+use crate::tensor::{OwnedTensor, Shape};
+use crate::{Error, ErrorKind, Result};
+
+/// The result of a request shared by its [`InferenceFuture`] and its worker.
+#[derive(Default)]
+struct RequestState {
+    result: Option<Result<Vec<OwnedTensor>>>,
+    waker: Option<Waker>,
+}
+
+/// A request queued for the workers.
+struct Request {
+    inputs: Vec<Vec<u8>>,
+    state: Arc<Mutex<RequestState>>,
+}
+
+impl Request {
+    fn complete(&self, result: Result<Vec<OwnedTensor>>) {
+        let mut state = self.state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Request {
+    /// Resolves the future of a request which was never run, e.g. because its worker
+    /// panicked.
+    fn drop(&mut self) {
+        let is_pending = self
+            .state
+            .lock()
+            .map(|state| state.result.is_none())
+            .unwrap_or(false);
+        if is_pending {
+            self.complete(Err(Error::new(ErrorKind::InterpreterShutDown)));
+        }
+    }
+}
+
+/// A future resolving to the outputs of a request submitted to an [`AsyncInterpreter`].
+pub struct InferenceFuture {
+    state: Arc<Mutex<RequestState>>,
+}
+
+impl Debug for InferenceFuture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let is_ready = self.state.lock().unwrap().result.is_some();
+        f.debug_struct("InferenceFuture")
+            .field("is_ready", &is_ready)
+            .finish()
+    }
+}
+
+impl Future for InferenceFuture {
+    type Output = Result<Vec<OwnedTensor>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A builder of an [`AsyncInterpreter`].
+pub struct AsyncInterpreterBuilder {
+    model: Arc<Model<'static>>,
+    workers: usize,
+    queue_depth: usize,
+    configuration: Arc<SharedConfiguration>,
+    input_shapes: Vec<(usize, Shape)>,
+}
+
+impl Debug for AsyncInterpreterBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncInterpreterBuilder")
+            .field("workers", &self.workers)
+            .field("queue_depth", &self.queue_depth)
+            .field("input_shapes", &self.input_shapes)
+            .finish()
+    }
+}
+
+impl AsyncInterpreterBuilder {
+    /// Creates a builder of an [`AsyncInterpreter`] of `model`, with one worker and
+    /// a queue of 64 requests by default.
+    pub fn new(model: Arc<Model<'static>>) -> AsyncInterpreterBuilder {
+        AsyncInterpreterBuilder {
+            model,
+            workers: 1,
+            queue_depth: 64,
+            configuration: Arc::new(|builder| builder),
+            input_shapes: Vec::new(),
+        }
+    }
+
+    /// Sets the number of worker threads, each of which owns an interpreter.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the number of requests which can wait for a worker before
+    /// [`AsyncInterpreter::submit()`] fails with [`ErrorKind::QueueFull`].
+    pub fn queue_depth(mut self, queue_depth: usize) -> Self {
+        self.queue_depth = queue_depth;
+        self
+    }
+
+    /// Sets the configuration of the [`InterpreterBuilder`] of every worker.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: for<'m> Fn(InterpreterBuilder<'m>) -> InterpreterBuilder<'m> + Send + Sync + 'static,
+    {
+        self.configuration = Arc::new(configure);
+        self
+    }
+
+    /// Resizes the input at the given `index` of every interpreter to `shape` before
+    /// its tensors are allocated.
+    pub fn input_shape(mut self, index: usize, shape: Shape) -> Self {
+        self.input_shapes.push((index, shape));
+        self
+    }
+
+    /// Starts the workers and waits until their interpreters are ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidOptions`] if there are no workers or the queue depth
+    /// is zero. Returns error if an interpreter cannot be built, resized or allocated,
+    /// or a thread cannot be spawned.
+    pub fn build(self) -> Result<AsyncInterpreter> {
+        if self.workers == 0 {
+            return Err(Error::new(ErrorKind::InvalidOptions(
+                "async interpreter needs at least one worker",
+            )));
+        }
+        if self.queue_depth == 0 {
+            return Err(Error::new(ErrorKind::InvalidOptions(
+                "async interpreter queue depth must be positive",
+            )));
+        }
+        let (sender, receiver) = mpsc::sync_channel::<Request>(self.queue_depth);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready_sender, ready_receiver) = mpsc::channel::<Result<()>>();
+        let mut workers = Vec::with_capacity(self.workers);
+        for index in 0..self.workers {
+            let model = Arc::clone(&self.model);
+            let configuration = Arc::clone(&self.configuration);
+            let input_shapes = self.input_shapes.clone();
+            let receiver = Arc::clone(&receiver);
+            let ready_sender = ready_sender.clone();
+            let worker = std::thread::Builder::new()
+                .name(format!("tflitec-worker-{}", index))
+                .spawn(move || {
+                    let interpreter = match prepare(&model, configuration.as_ref(), &input_shapes) {
+                        Ok(interpreter) => interpreter,
+                        Err(error) => {
+                            let _ = ready_sender.send(Err(error));
+                            return;
+                        }
+                    };
+                    let _ = ready_sender.send(Ok(()));
+                    drop(ready_sender);
+                    serve(&interpreter, &receiver);
+                });
+            match worker {
+                Ok(worker) => workers.push(worker),
+                Err(_) => {
+                    // Workers which have started stop once the queue is closed.
+                    drop(sender);
+                    workers.into_iter().for_each(|worker| {
+                        let _ = worker.join();
+                    });
+                    return Err(Error::new(ErrorKind::FailedToCreateInterpreter));
+                }
+            }
+        }
+        drop(ready_sender);
+
+        let interpreter = AsyncInterpreter {
+            sender: Some(sender),
+            workers,
+        };
+        for _ in 0..interpreter.workers.len() {
+            // A worker which panicked before reporting closes its sender.
+            ready_receiver
+                .recv()
+                .unwrap_or_else(|_| Err(Error::new(ErrorKind::FailedToCreateInterpreter)))?;
+        }
+        Ok(interpreter)
+    }
+}
+
+/// Builds, resizes and allocates the interpreter of a worker.
+fn prepare<'m>(
+    model: &'m Model<'static>,
+    configuration: &SharedConfiguration,
+    input_shapes: &[(usize, Shape)],
+) -> Result<Interpreter<'m>> {
+    configuration(InterpreterBuilder::new(model)).build_allocated(input_shapes)
+}
+
+/// Runs queued requests until the queue is closed and drained.
+fn serve(interpreter: &Interpreter, receiver: &Mutex<Receiver<Request>>) {
+    loop {
+        // The lock is released before running the request, so other workers can receive.
+        let request = match receiver.lock().unwrap().recv() {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+        request.complete(run(interpreter, &request.inputs));
+    }
+}
+
+/// Copies `inputs` into `interpreter`, invokes it and copies its outputs.
+fn run(interpreter: &Interpreter, inputs: &[Vec<u8>]) -> Result<Vec<OwnedTensor>> {
+    let input_count = interpreter.input_tensor_count();
+    if inputs.len() != input_count {
+        return Err(Error::new(ErrorKind::InputCountMismatch(
+            inputs.len(),
+            input_count,
+        )));
+    }
+    for (index, input) in inputs.iter().enumerate() {
+        interpreter.copy(&input[..], index)?;
+    }
+    interpreter.invoke()?;
+    (0..interpreter.output_tensor_count())
+        .map(|index| Ok(interpreter.output(index)?.to_owned()))
+        .collect()
+}
+
+/// A handle to interpreters of one [`Model`] running on dedicated worker threads.
+///
+/// Requests are queued and taken by the first idle worker. Dropping the handle, or calling
+/// [`AsyncInterpreter::shutdown()`], closes the queue and waits until the workers have run
+/// every request submitted before.
+pub struct AsyncInterpreter {
+    sender: Option<SyncSender<Request>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Debug for AsyncInterpreter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncInterpreter")
+            .field("workers", &self.workers.len())
+            .finish()
+    }
+}
+
+impl AsyncInterpreter {
+    /// Queues a request and returns the future resolving to its outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: The data of every input tensor, in the order of the inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::QueueFull`] if the queue is full, so callers can shed load
+    /// or retry later, and [`ErrorKind::InterpreterShutDown`] if every worker has stopped.
+    /// The future resolves to an error if the inputs do not match the input tensors or
+    /// the invocation fails.
+    pub fn submit(&self, inputs: Vec<Vec<u8>>) -> Result<InferenceFuture> {
+        let sender = self
+            .sender
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::InterpreterShutDown))?;
+        let state = Arc::new(Mutex::new(RequestState::default()));
+        let request = Request {
+            inputs,
+            state: Arc::clone(&state),
+        };
+        match sender.try_send(request) {
+            Ok(()) => Ok(InferenceFuture { state }),
+            Err(TrySendError::Full(_)) => Err(Error::new(ErrorKind::QueueFull)),
+            Err(TrySendError::Disconnected(_)) => Err(Error::new(ErrorKind::InterpreterShutDown)),
+        }
+    }
+
+    /// Returns the number of worker threads.
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Closes the queue and blocks until the workers have run every queued request.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for AsyncInterpreter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::async_interpreter::AsyncInterpreterBuilder;
+    use crate::custom_op::{CustomOp, OpError, OpTensor};
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::model::Model;
+    use crate::tensor::Shape;
+    use crate::test_models::custom_op_model;
+    use crate::ErrorKind;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::Thread;
+
+    /// Wakes a thread blocked in [`block_on`].
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(mut future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    fn to_bytes(data: &[f32]) -> Vec<u8> {
+        data.iter().flat_map(|x| x.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_async_interpreter() {
+        let model = Arc::new(Model::new(MODEL_PATH).expect("Cannot load model from file!"));
+        let interpreter = AsyncInterpreterBuilder::new(model)
+            .workers(2)
+            .queue_depth(8)
+            .input_shape(0, Shape::new(vec![10, 8, 8, 3]))
+            .build()
+            .expect("Cannot build async interpreter!");
+        assert_eq!(2, interpreter.worker_count());
+
+        let data = (0..1920).map(|x| x as f32).collect::<Vec<f32>>();
+        let futures = (0..4)
+            .map(|_| interpreter.submit(vec![to_bytes(&data)]).unwrap())
+            .collect::<Vec<_>>();
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        for future in futures {
+            let outputs = block_on(future).expect("Cannot run inference!");
+            assert_eq!(1, outputs.len());
+            assert_eq!(&vec![10, 8, 8, 3], outputs[0].shape().dimensions());
+            assert_eq!(expected, outputs[0].data::<f32>());
+        }
+
+        let err = block_on(interpreter.submit(vec![vec![0u8; 4]]).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidTensorDataCount(4, 7680), err.kind());
+        let err = block_on(interpreter.submit(Vec::new()).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InputCountMismatch(0, 1), err.kind());
+    }
+
+    /// Copies its input to its output once the gate is open.
+    struct Gate(Arc<(Mutex<bool>, Condvar)>);
+
+    impl CustomOp for Gate {
+        type State = ();
+
+        fn init(&self, _: &[u8]) {}
+
+        fn prepare(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            outputs[0].resize(inputs[0].shape().clone())
+        }
+
+        fn invoke(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            let (is_open, opened) = &*self.0;
+            let _open = opened
+                .wait_while(is_open.lock().unwrap(), |is_open| !*is_open)
+                .unwrap();
+            outputs[0]
+                .data_mut::<f32>()
+                .copy_from_slice(inputs[0].data::<f32>());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_async_interpreter_backpressure_and_shutdown() {
+        let model_data = custom_op_model("Gate", &[]);
+        let model_data: &'static [u8] = Box::leak(model_data.into_boxed_slice());
+        let model = Arc::new(Model::from_bytes(model_data).expect("Cannot load model!"));
+        let err = AsyncInterpreterBuilder::new(model.clone())
+            .workers(0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+
+        let gate = Arc::new((Mutex::new(false), Condvar::new()));
+        let op_gate = gate.clone();
+        let interpreter = AsyncInterpreterBuilder::new(model)
+            .queue_depth(1)
+            .configure(move |builder| builder.add_custom_op("Gate", Gate(op_gate.clone())))
+            .build()
+            .expect("Cannot build async interpreter!");
+        let data = to_bytes(&[1.0, 2.0, 3.0, 4.0]);
+        // The worker is blocked by the first request, so the queue is full by the third.
+        let mut futures = Vec::new();
+        let mut is_full = false;
+        for _ in 0..3 {
+            match interpreter.submit(vec![data.clone()]) {
+                Ok(future) => futures.push(future),
+                Err(err) => {
+                    assert_eq!(ErrorKind::QueueFull, err.kind());
+                    is_full = true;
+                    break;
+                }
+            }
+        }
+        assert!(is_full);
+
+        // Queued requests are drained before the workers stop.
+        *gate.0.lock().unwrap() = true;
+        gate.1.notify_all();
+        interpreter.shutdown();
+        for future in futures {
+            let outputs = block_on(future).expect("Cannot run inference!");
+            assert_eq!(vec![1.0f32, 2.0, 3.0, 4.0], outputs[0].data::<f32>());
+        }
+    }
+}
@@ -0,0 +1,456 @@
+//! A [`Batcher`] combining single-sample requests into batches along dimension 0.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use tflitec::batching::BatcherBuilder;
+//! use tflitec::model::Model;
+//! let model = Arc::new(Model::new("tests/add.bin")?);
+//! let batcher = BatcherBuilder::new(model)
+//!     .max_batch_size(8)
+//!     .max_latency(Duration::from_millis(5))
+//!     .build()?;
+//! let sample: Vec<u8> = [1.0f32; 192].iter().flat_map(|x| x.to_ne_bytes()).collect();
+//! let outputs = batcher.run(vec![sample])?;
+//! println!("{:?}", outputs[0].data::<f32>());
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::fmt::{Debug, Formatter};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::interpreter::{Interpreter, InterpreterBuilder, SharedConfiguration};
+use crate::model::Model;
+use crate::tensor::{OwnedTensor, Shape};
+use crate::{Error, ErrorKind, Result};
+
+/// The counters of a [`Batcher`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct BatchStatistics {
+    /// The number of requests run in a batch.
+    pub requests: u64,
+    /// The number of invocations.
+    pub batches: u64,
+    /// The number of times the inputs were resized and the tensors allocated again,
+    /// because the batch size changed.
+    pub reallocations: u64,
+}
+
+impl BatchStatistics {
+    /// Returns the mean number of requests per batch.
+    pub fn mean_batch_size(&self) -> f64 {
+        match self.batches {
+            0 => 0.0,
+            batches => self.requests as f64 / batches as f64,
+        }
+    }
+}
+
+/// A request waiting for a batch.
+struct Request {
+    inputs: Vec<Vec<u8>>,
+    reply: Sender<Result<Vec<OwnedTensor>>>,
+}
+
+/// A builder of a [`Batcher`].
+pub struct BatcherBuilder {
+    model: Arc<Model<'static>>,
+    max_batch_size: usize,
+    max_latency: Duration,
+    configuration: Arc<SharedConfiguration>,
+}
+
+impl Debug for BatcherBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatcherBuilder")
+            .field("max_batch_size", &self.max_batch_size)
+            .field("max_latency", &self.max_latency)
+            .finish()
+    }
+}
+
+impl BatcherBuilder {
+    /// Creates a builder of a [`Batcher`] of `model`, whose inputs must have a resizable
+    /// batch dimension 0.
+    ///
+    /// Batches have at most 8 requests and wait at most 2 ms by default.
+    pub fn new(model: Arc<Model<'static>>) -> BatcherBuilder {
+        BatcherBuilder {
+            model,
+            max_batch_size: 8,
+            max_latency: Duration::from_millis(2),
+            configuration: Arc::new(|builder| builder),
+        }
+    }
+
+    /// Sets the largest number of requests run in one invocation.
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Sets how long the first request of a batch waits for more requests.
+    pub fn max_latency(mut self, max_latency: Duration) -> Self {
+        self.max_latency = max_latency;
+        self
+    }
+
+    /// Sets the configuration of the [`InterpreterBuilder`].
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: for<'m> Fn(InterpreterBuilder<'m>) -> InterpreterBuilder<'m> + Send + Sync + 'static,
+    {
+        self.configuration = Arc::new(configure);
+        self
+    }
+
+    /// Starts the thread running the batches and waits until its interpreter is ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidOptions`] if the maximum batch size is zero. Returns
+    /// error if the interpreter cannot be built or allocated, or an input has no
+    /// batch dimension.
+    pub fn build(self) -> Result<Batcher> {
+        if self.max_batch_size == 0 {
+            return Err(Error::new(ErrorKind::InvalidOptions(
+                "maximum batch size must be positive",
+            )));
+        }
+        let (sender, receiver) = mpsc::channel::<Request>();
+        let (ready_sender, ready_receiver) = mpsc::channel::<Result<()>>();
+        let statistics = Arc::new(Mutex::new(BatchStatistics::default()));
+        let worker_statistics = Arc::clone(&statistics);
+        let max_batch_size = self.max_batch_size;
+        let max_latency = self.max_latency;
+        let model = self.model;
+        let configuration = self.configuration;
+        let worker = std::thread::Builder::new()
+            .name("tflitec-batcher".to_string())
+            .spawn(move || {
+                let batches = configuration(InterpreterBuilder::new(&model))
+                    .build()
+                    .and_then(|interpreter| BatchRunner::new(interpreter, worker_statistics));
+                let mut runner = match batches {
+                    Ok(runner) => runner,
+                    Err(error) => {
+                        let _ = ready_sender.send(Err(error));
+                        return;
+                    }
+                };
+                let _ = ready_sender.send(Ok(()));
+                while let Some(batch) = collect(&receiver, max_batch_size, max_latency) {
+                    runner.run(batch);
+                }
+            })
+            .map_err(|_| Error::new(ErrorKind::FailedToCreateInterpreter))?;
+        let batcher = Batcher {
+            sender: Mutex::new(Some(sender)),
+            worker: Some(worker),
+            statistics,
+        };
+        ready_receiver
+            .recv()
+            .unwrap_or_else(|_| Err(Error::new(ErrorKind::FailedToCreateInterpreter)))?;
+        Ok(batcher)
+    }
+}
+
+/// Waits for a request and the requests following it within `max_latency`.
+///
+/// returns: [`None`] if the [`Batcher`] is dropped and every request has been run.
+fn collect(
+    receiver: &Receiver<Request>,
+    max_batch_size: usize,
+    max_latency: Duration,
+) -> Option<Vec<Request>> {
+    let first = receiver.recv().ok()?;
+    let deadline = Instant::now() + max_latency;
+    let mut batch = vec![first];
+    while batch.len() < max_batch_size {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok(request) => batch.push(request),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Some(batch)
+}
+
+/// The interpreter of a [`Batcher`] with the sizes of a single sample.
+struct BatchRunner<'m> {
+    interpreter: Interpreter<'m>,
+    /// The shape of every input without the batch dimension.
+    sample_shapes: Vec<Vec<usize>>,
+    /// The number of bytes of a single sample of every input.
+    sample_sizes: Vec<usize>,
+    /// The batch size the tensors are allocated for.
+    batch_size: usize,
+    statistics: Arc<Mutex<BatchStatistics>>,
+}
+
+impl<'m> BatchRunner<'m> {
+    fn new(
+        interpreter: Interpreter<'m>,
+        statistics: Arc<Mutex<BatchStatistics>>,
+    ) -> Result<BatchRunner<'m>> {
+        interpreter.allocate_tensors()?;
+        let mut sample_shapes = Vec::new();
+        let mut sample_sizes = Vec::new();
+        let mut batch_size = None;
+        for index in 0..interpreter.input_tensor_count() {
+            let input = interpreter.input(index)?;
+            let dimensions = input.shape().dimensions();
+            let input_batch_size = match dimensions.first() {
+                Some(size) if *size > 0 => *size,
+                _ => return Err(Error::new(ErrorKind::FailedToResizeInputTensor(index))),
+            };
+            if *batch_size.get_or_insert(input_batch_size) != input_batch_size {
+                return Err(Error::new(ErrorKind::FailedToResizeInputTensor(index)));
+            }
+            sample_shapes.push(dimensions[1..].to_vec());
+            sample_sizes.push(input.data::<u8>().len() / input_batch_size);
+        }
+        Ok(BatchRunner {
+            interpreter,
+            sample_shapes,
+            sample_sizes,
+            batch_size: batch_size.unwrap_or(1),
+            statistics,
+        })
+    }
+
+    /// Runs the valid requests of `batch` in one invocation and replies to every request.
+    fn run(&mut self, batch: Vec<Request>) {
+        let mut requests = Vec::with_capacity(batch.len());
+        for request in batch {
+            match self.validate(&request.inputs) {
+                Ok(()) => requests.push(request),
+                Err(error) => {
+                    let _ = request.reply.send(Err(error));
+                }
+            }
+        }
+        if requests.is_empty() {
+            return;
+        }
+        match self.invoke(&requests) {
+            Ok(outputs) => {
+                for (request, outputs) in requests.iter().zip(outputs) {
+                    let _ = request.reply.send(Ok(outputs));
+                }
+            }
+            Err(error) => {
+                for request in &requests {
+                    let _ = request.reply.send(Err(error));
+                }
+            }
+        }
+    }
+
+    /// Checks that `inputs` hold a single sample of every input.
+    fn validate(&self, inputs: &[Vec<u8>]) -> Result<()> {
+        if inputs.len() != self.sample_sizes.len() {
+            return Err(Error::new(ErrorKind::InputCountMismatch(
+                inputs.len(),
+                self.sample_sizes.len(),
+            )));
+        }
+        for (input, sample_size) in inputs.iter().zip(&self.sample_sizes) {
+            if input.len() != *sample_size {
+                return Err(Error::new(ErrorKind::InvalidTensorDataCount(
+                    input.len(),
+                    *sample_size,
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Concatenates the inputs of `requests`, invokes the interpreter and splits
+    /// the outputs back to each request.
+    fn invoke(&mut self, requests: &[Request]) -> Result<Vec<Vec<OwnedTensor>>> {
+        let batch_size = requests.len();
+        if batch_size != self.batch_size {
+            for (index, sample_shape) in self.sample_shapes.iter().enumerate() {
+                let mut dimensions = vec![batch_size];
+                dimensions.extend_from_slice(sample_shape);
+                self.interpreter
+                    .resize_input(index, Shape::new(dimensions))?;
+            }
+            self.interpreter.allocate_tensors()?;
+            self.batch_size = batch_size;
+            self.statistics.lock().unwrap().reallocations += 1;
+        }
+        for (index, sample_size) in self.sample_sizes.iter().enumerate() {
+            let mut data = Vec::with_capacity(sample_size * batch_size);
+            for request in requests {
+                data.extend_from_slice(&request.inputs[index]);
+            }
+            self.interpreter.copy(&data[..], index)?;
+        }
+        self.interpreter.invoke()?;
+        {
+            let mut statistics = self.statistics.lock().unwrap();
+            statistics.batches += 1;
+            statistics.requests += batch_size as u64;
+        }
+
+        let mut outputs = vec![Vec::new(); batch_size];
+        for index in 0..self.interpreter.output_tensor_count() {
+            let output = self.interpreter.output(index)?;
+            let dimensions = output.shape().dimensions();
+            if dimensions.first() != Some(&batch_size) {
+                return Err(Error::new(ErrorKind::InvalidTensorDataCount(
+                    dimensions.first().copied().unwrap_or(0),
+                    batch_size,
+                )));
+            }
+            let mut sample_dimensions = dimensions.clone();
+            sample_dimensions[0] = 1;
+            let data = output.data::<u8>();
+            let sample_size = data.len() / batch_size;
+            for (sample, request_outputs) in data.chunks(sample_size.max(1)).zip(&mut outputs) {
+                request_outputs.push(OwnedTensor::from_parts(
+                    output.name(),
+                    output.data_type(),
+                    Shape::new(sample_dimensions.clone()),
+                    output.quantization_parameters(),
+                    sample,
+                ));
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+/// Runs single-sample requests from many threads in batches on one interpreter.
+///
+/// The first request of a batch waits up to the maximum latency for more requests, then
+/// their inputs are concatenated along dimension 0 and run in one invocation. The inputs
+/// are only resized, and the tensors allocated again, when the batch size changes.
+/// Dropping the batcher waits until every submitted request has been run.
+pub struct Batcher {
+    sender: Mutex<Option<Sender<Request>>>,
+    worker: Option<JoinHandle<()>>,
+    statistics: Arc<Mutex<BatchStatistics>>,
+}
+
+impl Debug for Batcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batcher")
+            .field("statistics", &self.statistics())
+            .finish()
+    }
+}
+
+impl Batcher {
+    /// Runs a request in the next batch and blocks until its outputs are ready.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: The data of a single sample of every input tensor, in the order of
+    ///   the inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InputCountMismatch`] if the number of inputs does not match the
+    /// number of input tensors and [`ErrorKind::InvalidTensorDataCount`] if an input is not
+    /// a single sample. Returns error if the batch fails or an output has no batch dimension
+    /// to split.
+    pub fn run(&self, inputs: Vec<Vec<u8>>) -> Result<Vec<OwnedTensor>> {
+        let (reply, result) = mpsc::channel();
+        self.sender
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|sender| sender.send(Request { inputs, reply }).ok())
+            .ok_or_else(|| Error::new(ErrorKind::InterpreterShutDown))?;
+        result
+            .recv()
+            .unwrap_or_else(|_| Err(Error::new(ErrorKind::InterpreterShutDown)))
+    }
+
+    /// Returns the counters of the batches run so far.
+    pub fn statistics(&self) -> BatchStatistics {
+        *self.statistics.lock().unwrap()
+    }
+}
+
+impl Drop for Batcher {
+    fn drop(&mut self) {
+        drop(self.sender.lock().unwrap().take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batching::BatcherBuilder;
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::interpreter::Interpreter;
+    use crate::model::Model;
+    use crate::ErrorKind;
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    fn to_bytes(data: &[f32]) -> Vec<u8> {
+        data.iter().flat_map(|x| x.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_batcher() {
+        let model = Arc::new(Model::new(MODEL_PATH).expect("Cannot load model from file!"));
+        let batcher = BatcherBuilder::new(Arc::clone(&model))
+            .max_batch_size(4)
+            .max_latency(Duration::from_secs(2))
+            .build()
+            .expect("Cannot build batcher!");
+
+        let inputs = (0..4)
+            .map(|request| (0..192).map(|x| (x + request) as f32).collect())
+            .collect::<Vec<Vec<f32>>>();
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter.allocate_tensors().unwrap();
+        let unbatched_outputs = inputs
+            .iter()
+            .map(|data| {
+                interpreter.copy(&data[..], 0).unwrap();
+                interpreter.invoke().unwrap();
+                interpreter.output(0).unwrap().data::<f32>().to_vec()
+            })
+            .collect::<Vec<Vec<f32>>>();
+
+        // All requests are submitted together, well within the latency budget.
+        let barrier = Barrier::new(inputs.len());
+        std::thread::scope(|scope| {
+            for (data, expected) in inputs.iter().zip(&unbatched_outputs) {
+                let batcher = &batcher;
+                let barrier = &barrier;
+                scope.spawn(move || {
+                    barrier.wait();
+                    let outputs = batcher.run(vec![to_bytes(data)]).unwrap();
+                    assert_eq!(1, outputs.len());
+                    assert_eq!(&vec![1, 8, 8, 3], outputs[0].shape().dimensions());
+                    assert_eq!(&expected[..], outputs[0].data::<f32>());
+                });
+            }
+        });
+        let statistics = batcher.statistics();
+        assert_eq!(4, statistics.requests);
+        assert!(statistics.batches < statistics.requests);
+        assert!(statistics.mean_batch_size() > 1.0);
+
+        let err = batcher.run(vec![vec![0u8; 4]]).err().unwrap();
+        assert_eq!(ErrorKind::InvalidTensorDataCount(4, 768), err.kind());
+        let err = batcher.run(Vec::new()).err().unwrap();
+        assert_eq!(ErrorKind::InputCountMismatch(0, 1), err.kind());
+        assert_eq!(4, batcher.statistics().requests);
+    }
+}
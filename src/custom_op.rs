@@ -0,0 +1,631 @@
+//! Custom operators implemented in Rust with the [`CustomOp`] trait.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::custom_op::{CustomOp, OpError, OpTensor};
+//! use tflitec::interpreter::InterpreterBuilder;
+//! use tflitec::model::Model;
+//!
+//! /// Multiplies its input by the factor in the first byte of the custom options.
+//! struct Scale;
+//!
+//! impl CustomOp for Scale {
+//!     type State = f32;
+//!
+//!     fn init(&self, options: &[u8]) -> f32 {
+//!         options.first().copied().unwrap_or(1) as f32
+//!     }
+//!
+//!     fn prepare(
+//!         &self,
+//!         _: &mut f32,
+//!         inputs: &[OpTensor],
+//!         outputs: &mut [OpTensor],
+//!     ) -> Result<(), OpError> {
+//!         outputs[0].resize(inputs[0].shape().clone())
+//!     }
+//!
+//!     fn invoke(
+//!         &self,
+//!         factor: &mut f32,
+//!         inputs: &[OpTensor],
+//!         outputs: &mut [OpTensor],
+//!     ) -> Result<(), OpError> {
+//!         let input = inputs[0].data::<f32>();
+//!         for (y, x) in outputs[0].data_mut::<f32>().iter_mut().zip(input) {
+//!             *y = *x * *factor;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let model = Model::new("model_with_scale.tflite")?;
+//! let interpreter = InterpreterBuilder::new(&model)
+//!     .add_custom_op("Scale", Scale)
+//!     .build()?;
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::ffi::{CStr, CString};
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::minimal_bindings::*;
+use crate::tensor::{DataType, Shape};
+
+/// The error of a [`CustomOp`], reported to TensorFlow Lite with its message.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpError {
+    message: String,
+}
+
+impl OpError {
+    /// Creates a new error with the given `message`.
+    pub fn new<M: Into<String>>(message: M) -> OpError {
+        OpError {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the message of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for OpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for OpError {}
+
+impl From<&str> for OpError {
+    fn from(message: &str) -> Self {
+        OpError::new(message)
+    }
+}
+
+impl From<String> for OpError {
+    fn from(message: String) -> Self {
+        OpError::new(message)
+    }
+}
+
+/// A custom operator of a TensorFlow Lite graph implemented in Rust.
+///
+/// Operators are registered with
+/// [`InterpreterBuilder::add_custom_op()`][crate::interpreter::InterpreterBuilder::add_custom_op()]
+/// under the custom code used in the model. Every node of the operator has its own
+/// [`CustomOp::State`], created by [`CustomOp::init()`] when the graph is loaded and
+/// passed to [`CustomOp::free()`] when the interpreter is dropped.
+///
+/// Errors returned by [`CustomOp::prepare()`] and [`CustomOp::invoke()`] are reported
+/// to the error reporter of the interpreter, and fail the interpreter call in progress.
+/// Panics are caught and reported the same way.
+pub trait CustomOp: Send {
+    /// The state of a node of the operator.
+    type State: Send;
+
+    /// Creates the state of a node from the custom options of the node in the model.
+    fn init(&self, options: &[u8]) -> Self::State;
+
+    /// Checks the inputs of a node and resizes its outputs.
+    ///
+    /// Called when tensors are allocated, so tensor data may not be available.
+    fn prepare(
+        &self,
+        state: &mut Self::State,
+        inputs: &[OpTensor<'_>],
+        outputs: &mut [OpTensor<'_>],
+    ) -> Result<(), OpError> {
+        let _ = (state, inputs, outputs);
+        Ok(())
+    }
+
+    /// Computes the outputs of a node from its inputs.
+    fn invoke(
+        &self,
+        state: &mut Self::State,
+        inputs: &[OpTensor<'_>],
+        outputs: &mut [OpTensor<'_>],
+    ) -> Result<(), OpError>;
+
+    /// Frees the state of a node.
+    fn free(&self, state: Self::State) {
+        drop(state);
+    }
+}
+
+/// An input or output tensor of a node of a [`CustomOp`].
+pub struct OpTensor<'a> {
+    /// The context of the graph, used to resize the tensor.
+    context: *mut TfLiteOpaqueContext,
+
+    /// The underlying [`TfLiteOpaqueTensor`] C pointer.
+    tensor_ptr: *mut TfLiteOpaqueTensor,
+
+    /// The data type of the tensor.
+    data_type: DataType,
+
+    /// The shape of the tensor.
+    shape: Shape,
+
+    // To set lifetime of the OpTensor
+    phantom: PhantomData<&'a TfLiteOpaqueTensor>,
+}
+
+impl Debug for OpTensor<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpTensor")
+            .field("name", &self.name())
+            .field("shape", &self.shape)
+            .field("data_type", &self.data_type)
+            .finish()
+    }
+}
+
+impl<'a> OpTensor<'a> {
+    unsafe fn from_raw(
+        context: *mut TfLiteOpaqueContext,
+        tensor_ptr: *mut TfLiteOpaqueTensor,
+    ) -> Result<OpTensor<'a>, OpError> {
+        let data_type = DataType::new(TfLiteOpaqueTensorType(tensor_ptr))
+            .ok_or_else(|| OpError::new("unsupported tensor data type"))?;
+        let rank = TfLiteOpaqueTensorNumDims(tensor_ptr);
+        let dimensions = (0..rank)
+            .map(|i| TfLiteOpaqueTensorDim(tensor_ptr, i).max(0) as usize)
+            .collect();
+        Ok(OpTensor {
+            context,
+            tensor_ptr,
+            data_type,
+            shape: Shape::new(dimensions),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns name of the tensor.
+    pub fn name(&self) -> &str {
+        let name_ptr = unsafe { TfLiteOpaqueTensorName(self.tensor_ptr) };
+        if name_ptr.is_null() {
+            return "";
+        }
+        unsafe { CStr::from_ptr(name_ptr) }.to_str().unwrap_or("")
+    }
+
+    /// Returns [data type][`DataType`] of the tensor.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Returns [`Shape`] of the tensor.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// Returns data of the tensor as a slice of given type `T`.
+    ///
+    /// The slice is empty if the data of the tensor is not allocated yet.
+    ///
+    /// # Panics
+    ///
+    /// * If number of bytes in buffer of the tensor is not integer
+    ///   multiple of byte count of a single `T` (see [`std::mem::size_of`])
+    pub fn data<T>(&self) -> &[T] {
+        let (data_ptr, length) = self.raw_data::<T>();
+        if data_ptr.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(data_ptr as *const T, length) }
+    }
+
+    /// Returns data of the output tensor as a mutable slice of given type `T`.
+    ///
+    /// The slice is empty if the data of the tensor is not allocated yet.
+    ///
+    /// # Panics
+    ///
+    /// * If number of bytes in buffer of the tensor is not integer
+    ///   multiple of byte count of a single `T` (see [`std::mem::size_of`])
+    pub fn data_mut<T>(&mut self) -> &mut [T] {
+        let (data_ptr, length) = self.raw_data::<T>();
+        if data_ptr.is_null() {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(data_ptr as *mut T, length) }
+    }
+
+    /// Resizes the output tensor to the given `shape`.
+    ///
+    /// Outputs are resized in [`CustomOp::prepare()`], before their data is allocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a dimension exceeds `i32::MAX` or TensorFlow Lite C fails internally.
+    pub fn resize(&mut self, shape: Shape) -> Result<(), OpError> {
+        if shape.dimensions().iter().any(|d| *d > c_int::MAX as usize) {
+            return Err(OpError::new("tensor dimension exceeds i32::MAX"));
+        }
+        let status = unsafe {
+            let new_size = TfLiteIntArrayCreate(shape.rank() as c_int);
+            if new_size.is_null() {
+                return Err(OpError::new("failed to allocate the tensor shape"));
+            }
+            let data = std::ptr::addr_of_mut!((*new_size).data) as *mut c_int;
+            for (i, dimension) in shape.dimensions().iter().enumerate() {
+                *data.add(i) = *dimension as c_int;
+            }
+            // The context takes the ownership of `new_size`.
+            TfLiteOpaqueContextResizeTensor(self.context, self.tensor_ptr, new_size)
+        };
+        if status.is_error() {
+            return Err(OpError::new(format!(
+                "failed to resize tensor {}",
+                self.name()
+            )));
+        }
+        self.shape = shape;
+        Ok(())
+    }
+
+    fn raw_data<T>(&self) -> (*mut c_void, usize) {
+        let element_size = std::mem::size_of::<T>();
+        let (data_ptr, data_length) = unsafe {
+            (
+                TfLiteOpaqueTensorData(self.tensor_ptr),
+                TfLiteOpaqueTensorByteSize(self.tensor_ptr),
+            )
+        };
+        if data_length % element_size != 0 {
+            panic!(
+                "data length {} should be divisible by size of type {}",
+                data_length, element_size
+            )
+        }
+        (data_ptr, data_length / element_size)
+    }
+}
+
+/// A [`CustomOp`] registered on an interpreter, with the [`TfLiteOperator`] calling it.
+pub(crate) struct CustomOpRegistration<'a> {
+    /// The custom code of the operator in the model.
+    name: String,
+
+    /// The underlying [`TfLiteOperator`] C pointer, null if it cannot be created.
+    operator_ptr: *mut TfLiteOperator,
+
+    /// The custom code referred to by the operator.
+    #[allow(dead_code)]
+    custom_name: Option<CString>,
+
+    /// The operator, which is boxed since its address is the user data of the callbacks.
+    #[allow(dead_code)]
+    op: Box<dyn Send + 'a>,
+}
+
+unsafe impl Send for CustomOpRegistration<'_> {}
+
+impl Debug for CustomOpRegistration<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomOpRegistration")
+            .field(&self.name)
+            .finish()
+    }
+}
+
+impl<'a> CustomOpRegistration<'a> {
+    pub(crate) fn new<O: CustomOp + 'a>(name: &str, op: O) -> CustomOpRegistration<'a> {
+        let op = Box::new(op);
+        let user_data = op.as_ref() as *const O as *mut c_void;
+        let custom_name = CString::new(name).ok();
+        let operator_ptr = custom_name
+            .as_ref()
+            .map(|custom_name| unsafe { create_operator::<O>(custom_name, user_data) })
+            .unwrap_or(std::ptr::null_mut());
+        CustomOpRegistration {
+            name: name.to_string(),
+            operator_ptr,
+            custom_name,
+            op,
+        }
+    }
+
+    /// Returns `false` if the name contains a nul byte.
+    pub(crate) fn has_valid_name(&self) -> bool {
+        self.custom_name.is_some()
+    }
+
+    /// Returns the underlying [`TfLiteOperator`] C pointer, or null if it cannot be created.
+    pub(crate) fn as_ptr(&self) -> *mut TfLiteOperator {
+        self.operator_ptr
+    }
+}
+
+impl Drop for CustomOpRegistration<'_> {
+    fn drop(&mut self) {
+        if !self.operator_ptr.is_null() {
+            unsafe { TfLiteOperatorDelete(self.operator_ptr) }
+        }
+    }
+}
+
+/// Creates the [`TfLiteOperator`] calling the [`CustomOp`] at `user_data`.
+///
+/// returns: A null pointer if TensorFlow Lite C fails to create the operator.
+unsafe fn create_operator<O: CustomOp>(
+    custom_name: &CStr,
+    user_data: *mut c_void,
+) -> *mut TfLiteOperator {
+    let operator_ptr =
+        TfLiteOperatorCreate(TFLITE_BUILTIN_CUSTOM, custom_name.as_ptr(), 1, user_data);
+    if operator_ptr.is_null() {
+        return operator_ptr;
+    }
+    let statuses = [
+        TfLiteOperatorSetInitWithData(operator_ptr, Some(init_node::<O>)),
+        TfLiteOperatorSetFreeWithData(operator_ptr, Some(free_node::<O>)),
+        TfLiteOperatorSetPrepareWithData(operator_ptr, Some(prepare_node::<O>)),
+        TfLiteOperatorSetInvokeWithData(operator_ptr, Some(invoke_node::<O>)),
+    ];
+    if statuses.iter().any(TfLiteStatus::is_error) {
+        TfLiteOperatorDelete(operator_ptr);
+        return std::ptr::null_mut();
+    }
+    operator_ptr
+}
+
+/// Creates the state of a node, which is [`None`] if [`CustomOp::init()`] panicked.
+unsafe extern "C" fn init_node<O: CustomOp>(
+    user_data: *mut c_void,
+    _context: *mut TfLiteOpaqueContext,
+    buffer: *const c_char,
+    length: usize,
+) -> *mut c_void {
+    let op = &*(user_data as *const O);
+    let options: &[u8] = if buffer.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffer as *const u8, length)
+    };
+    let state = catch_unwind(AssertUnwindSafe(|| op.init(options))).ok();
+    Box::into_raw(Box::new(state)) as *mut c_void
+}
+
+unsafe extern "C" fn free_node<O: CustomOp>(
+    user_data: *mut c_void,
+    _context: *mut TfLiteOpaqueContext,
+    data: *mut c_void,
+) {
+    if data.is_null() {
+        return;
+    }
+    let op = &*(user_data as *const O);
+    if let Some(state) = *Box::from_raw(data as *mut Option<O::State>) {
+        // A panic must not unwind into TensorFlow Lite, and there is no one to report it to.
+        let _ = catch_unwind(AssertUnwindSafe(|| op.free(state)));
+    }
+}
+
+unsafe extern "C" fn prepare_node<O: CustomOp>(
+    user_data: *mut c_void,
+    context: *mut TfLiteOpaqueContext,
+    node: *mut TfLiteOpaqueNode,
+) -> TfLiteStatus {
+    run_node::<O>(user_data, context, node, O::prepare)
+}
+
+unsafe extern "C" fn invoke_node<O: CustomOp>(
+    user_data: *mut c_void,
+    context: *mut TfLiteOpaqueContext,
+    node: *mut TfLiteOpaqueNode,
+) -> TfLiteStatus {
+    run_node::<O>(user_data, context, node, O::invoke)
+}
+
+/// The signature of [`CustomOp::prepare()`] and [`CustomOp::invoke()`].
+type NodeFn<O> = fn(
+    &O,
+    &mut <O as CustomOp>::State,
+    &[OpTensor<'_>],
+    &mut [OpTensor<'_>],
+) -> Result<(), OpError>;
+
+/// Calls `f` with the state and tensors of `node`, and reports its error to `context`.
+unsafe fn run_node<O: CustomOp>(
+    user_data: *mut c_void,
+    context: *mut TfLiteOpaqueContext,
+    node: *mut TfLiteOpaqueNode,
+    f: NodeFn<O>,
+) -> TfLiteStatus {
+    let op = &*(user_data as *const O);
+    let state = (TfLiteOpaqueNodeGetUserData(node) as *mut Option<O::State>).as_mut();
+    let result = match state.and_then(Option::as_mut) {
+        Some(state) => node_tensors(context, node).and_then(|(inputs, mut outputs)| {
+            catch_unwind(AssertUnwindSafe(|| f(op, state, &inputs, &mut outputs)))
+                .unwrap_or_else(|_| Err(OpError::new("the custom operator panicked")))
+        }),
+        None => Err(OpError::new("the custom operator failed to initialize")),
+    };
+    match result {
+        Ok(()) => TfLiteStatus::kTfLiteOk,
+        Err(error) => {
+            let message = CString::new(error.message().replace('\0', " ")).unwrap();
+            TfLiteOpaqueContextReportError(
+                context,
+                b"%s\0".as_ptr() as *const c_char,
+                message.as_ptr(),
+            );
+            TfLiteStatus::kTfLiteError
+        }
+    }
+}
+
+/// Returns the input and output tensors of `node`.
+unsafe fn node_tensors<'a>(
+    context: *mut TfLiteOpaqueContext,
+    node: *mut TfLiteOpaqueNode,
+) -> Result<(Vec<OpTensor<'a>>, Vec<OpTensor<'a>>), OpError> {
+    let input_count = TfLiteOpaqueNodeNumberOfInputs(node);
+    let output_count = TfLiteOpaqueNodeNumberOfOutputs(node);
+    let mut inputs = Vec::with_capacity(input_count.max(0) as usize);
+    for index in 0..input_count {
+        let tensor_ptr = TfLiteOpaqueNodeGetInput(context, node, index);
+        if tensor_ptr.is_null() {
+            return Err(OpError::new(format!("input {} is missing", index)));
+        }
+        inputs.push(OpTensor::from_raw(context, tensor_ptr as *mut _)?);
+    }
+    let mut outputs = Vec::with_capacity(output_count.max(0) as usize);
+    for index in 0..output_count {
+        let tensor_ptr = TfLiteOpaqueNodeGetOutput(context, node, index);
+        if tensor_ptr.is_null() {
+            return Err(OpError::new(format!("output {} is missing", index)));
+        }
+        outputs.push(OpTensor::from_raw(context, tensor_ptr)?);
+    }
+    Ok((inputs, outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::custom_op::{CustomOp, OpError, OpTensor};
+    use crate::interpreter::{InterpreterBuilder, OpResolver};
+    use crate::model::Model;
+    use crate::test_models::custom_op_model;
+    use crate::ErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Multiplies its input by the factor in the first byte of the custom options.
+    struct Scale {
+        freed: Arc<AtomicUsize>,
+    }
+
+    impl CustomOp for Scale {
+        type State = f32;
+
+        fn init(&self, options: &[u8]) -> f32 {
+            options.first().copied().unwrap_or(1) as f32
+        }
+
+        fn prepare(
+            &self,
+            _: &mut f32,
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            if inputs.len() != 1 || outputs.len() != 1 {
+                return Err("Scale has one input and one output".into());
+            }
+            outputs[0].resize(inputs[0].shape().clone())
+        }
+
+        fn invoke(
+            &self,
+            factor: &mut f32,
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            let input = inputs[0].data::<f32>();
+            for (y, x) in outputs[0].data_mut::<f32>().iter_mut().zip(input) {
+                *y = *x * *factor;
+            }
+            Ok(())
+        }
+
+        fn free(&self, _: f32) {
+            self.freed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_custom_op() {
+        let model_data = custom_op_model("RustScale", &[3]);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+
+        let err = InterpreterBuilder::new(&model)
+            .error_reporter(|_| {})
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::FailedToCreateInterpreter, err.kind());
+
+        for op_resolver in [OpResolver::Builtin, OpResolver::Selected] {
+            let freed = Arc::new(AtomicUsize::new(0));
+            let interpreter = InterpreterBuilder::new(&model)
+                .op_resolver(op_resolver)
+                .add_custom_op(
+                    "RustScale",
+                    Scale {
+                        freed: freed.clone(),
+                    },
+                )
+                .build()
+                .expect("Cannot create interpreter!");
+            interpreter
+                .allocate_tensors()
+                .expect("Cannot allocate tensors");
+            let data = [1.0f32, 2.0, 3.0, 4.0];
+            assert!(interpreter.copy(&data[..], 0).is_ok());
+            assert!(interpreter.invoke().is_ok());
+            let output_tensor = interpreter.output(0).unwrap();
+            assert_eq!(output_tensor.shape().dimensions(), &vec![1, 4]);
+            assert_eq!(&[3.0f32, 6.0, 9.0, 12.0], output_tensor.data::<f32>());
+
+            drop(interpreter);
+            assert_eq!(1, freed.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_custom_op_error() {
+        struct Fail;
+
+        impl CustomOp for Fail {
+            type State = ();
+
+            fn init(&self, _: &[u8]) {}
+
+            fn invoke(
+                &self,
+                _: &mut (),
+                _: &[OpTensor],
+                _: &mut [OpTensor],
+            ) -> Result<(), OpError> {
+                Err("custom failure".into())
+            }
+        }
+
+        let model_data = custom_op_model("RustFail", &[]);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let reported = messages.clone();
+        let interpreter = InterpreterBuilder::new(&model)
+            .add_custom_op("RustFail", Fail)
+            .error_reporter(move |message| reported.lock().unwrap().push(message.to_string()))
+            .build()
+            .expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        assert_eq!(
+            ErrorKind::FailedToInvokeInterpreter,
+            interpreter.invoke().err().unwrap().kind()
+        );
+        let messages = messages.lock().unwrap();
+        assert!(messages
+            .iter()
+            .any(|message| message.contains("custom failure")));
+
+        let err = InterpreterBuilder::new(&model)
+            .add_custom_op("Rust\0Fail", Fail)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+    }
+}
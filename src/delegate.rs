@@ -0,0 +1,409 @@
+//! TensorFlow Lite [`Delegate`]s which run parts of a graph on other backends.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::delegate::ExternalDelegate;
+//! use tflitec::interpreter::InterpreterBuilder;
+//! use tflitec::model::Model;
+//! let delegate = ExternalDelegate::load("libmy_delegate.so", &[("precision", "fp16")])?;
+//! let model = Model::new("tests/add.bin")?;
+//! let interpreter = InterpreterBuilder::new(&model)
+//!     .add_delegate(delegate)
+//!     .build()?;
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::ffi::{CStr, CString};
+use std::fmt::{Debug, Formatter};
+use std::os::raw::{c_char, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+
+use crate::minimal_bindings::*;
+use crate::{Error, ErrorKind, Result};
+
+/// A delegate which owns a [`TfLiteDelegate`].
+///
+/// Delegates are added to an [`Interpreter`][crate::interpreter::Interpreter] with
+/// [`InterpreterBuilder::add_delegate()`][crate::interpreter::InterpreterBuilder::add_delegate()].
+/// The interpreter owns its delegates and drops them after the underlying interpreter is
+/// deleted, so implementations can free the [`TfLiteDelegate`] when they are dropped.
+///
+/// # Safety
+///
+/// [`Delegate::as_ptr()`] must return a valid [`TfLiteDelegate`] which stays valid and
+/// unchanged until the delegate is dropped, and which is not used by another interpreter.
+pub unsafe trait Delegate: Send {
+    /// Returns the underlying [`TfLiteDelegate`] C pointer.
+    fn as_ptr(&self) -> *mut TfLiteDelegate;
+
+    /// Returns the name of the delegate used in [`DelegateReport`]s and log messages.
+    fn name(&self) -> &str {
+        "delegate"
+    }
+}
+
+/// What an [`Interpreter`][crate::interpreter::Interpreter] does when a delegate fails.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum DelegateFailurePolicy {
+    /// Fails creating the interpreter if a delegate cannot be created or applied to
+    /// the graph.
+    Fail,
+    /// Retries creating the interpreter without a delegate which cannot be created or
+    /// applied to the graph, so its nodes run on the CPU kernels of TensorFlow Lite.
+    /// The failure is logged as a warning and reported by
+    /// [`Interpreter::delegate_status()`][crate::interpreter::Interpreter::delegate_status()].
+    #[default]
+    RetryWithoutDelegate,
+    /// Retries without failing delegates like [`DelegateFailurePolicy::RetryWithoutDelegate`],
+    /// and also falls back to CPU kernels when an invocation with delegates fails.
+    ///
+    /// When an invocation fails, the delegates are undone and the invocation is retried
+    /// without them. Tensor data addresses may change afterwards, and the state of stateful
+    /// models is lost, so only use this for stateless models.
+    FallBackToCpu,
+}
+
+/// The outcome of applying a delegate to the graph of an interpreter.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum DelegateStatus {
+    /// The delegate runs every node of the graph.
+    Applied,
+    /// The delegate runs some nodes of the graph, and the remaining nodes run on other
+    /// delegates or the CPU kernels.
+    PartiallyApplied {
+        /// The number of nodes of the graph the delegate runs.
+        delegated_nodes: usize,
+        /// The number of nodes of the graph before delegates were applied.
+        total_nodes: usize,
+    },
+    /// The delegate was applied but claimed no node of the graph, e.g. because it supports
+    /// none of the operators.
+    NotApplied,
+    /// The delegate could not be created or applied, and the interpreter was created
+    /// without it.
+    Rejected {
+        /// The reason, e.g. the last error reported by TensorFlow Lite C.
+        reason: String,
+    },
+}
+
+/// Describes a delegate of an [`Interpreter`][crate::interpreter::Interpreter].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct DelegateReport {
+    /// The [name][Delegate::name()] of the delegate.
+    pub name: String,
+    /// The outcome of applying the delegate.
+    pub status: DelegateStatus,
+}
+
+/// A dynamic library which is closed when dropped.
+struct Library {
+    handle: NonNull<c_void>,
+}
+
+impl Library {
+    fn open(path: &Path) -> Result<Library> {
+        #[cfg(unix)]
+        let handle = {
+            use std::os::unix::ffi::OsStrExt;
+            let path = CString::new(path.as_os_str().as_bytes())
+                .map_err(|_| Error::new(ErrorKind::FailedToLoadDelegate))?;
+            unsafe { dlopen(path.as_ptr(), RTLD_NOW) }
+        };
+        #[cfg(windows)]
+        let handle = {
+            use std::os::windows::ffi::OsStrExt;
+            let path = path
+                .as_os_str()
+                .encode_wide()
+                .chain(std::iter::once(0))
+                .collect::<Vec<u16>>();
+            unsafe { LoadLibraryW(path.as_ptr()) }
+        };
+        NonNull::new(handle)
+            .map(|handle| Library { handle })
+            .ok_or_else(|| Error::new(ErrorKind::FailedToLoadDelegate))
+    }
+
+    /// Returns the address of the exported `symbol`, or null if it is not exported.
+    fn symbol(&self, symbol: &CStr) -> *mut c_void {
+        #[cfg(unix)]
+        unsafe {
+            dlsym(self.handle.as_ptr(), symbol.as_ptr())
+        }
+        #[cfg(windows)]
+        unsafe {
+            GetProcAddress(self.handle.as_ptr(), symbol.as_ptr())
+        }
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            dlclose(self.handle.as_ptr());
+        }
+        #[cfg(windows)]
+        unsafe {
+            FreeLibrary(self.handle.as_ptr());
+        }
+    }
+}
+
+/// Forwards error messages of external delegate libraries to the log.
+unsafe extern "C" fn report_external_delegate_error(message: *const c_char) {
+    if !message.is_null() {
+        log::warn!(
+            "External delegate error: {}",
+            CStr::from_ptr(message).to_string_lossy()
+        );
+    }
+}
+
+/// A delegate loaded from a shared library implementing the TensorFlow Lite
+/// external delegate interface.
+///
+/// The library must export `tflite_plugin_create_delegate` and
+/// `tflite_plugin_destroy_delegate`. See
+/// [external_delegate_interface.h](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/lite/delegates/external/external_delegate_interface.h)
+/// for details.
+pub struct ExternalDelegate {
+    /// The underlying [`TfLiteDelegate`] C pointer.
+    delegate_ptr: NonNull<TfLiteDelegate>,
+
+    /// `tflite_plugin_destroy_delegate` of the library.
+    destroy: TfLiteExternalDelegateDestroy,
+
+    /// The path of the library.
+    path: PathBuf,
+
+    /// The file stem of the library.
+    name: String,
+
+    /// The library, which is closed after the delegate is destroyed.
+    #[allow(dead_code)]
+    library: Library,
+}
+
+unsafe impl Send for ExternalDelegate {}
+
+impl Debug for ExternalDelegate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExternalDelegate")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl ExternalDelegate {
+    /// Loads the delegate library at `path` and creates a delegate with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the delegate shared library.
+    /// * `options`: Key/value options passed to the library, as documented by the delegate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::FailedToLoadDelegate`] if the library cannot be loaded or does not
+    /// export the external delegate interface, or [`ErrorKind::FailedToCreateDelegate`] if the
+    /// library fails to create the delegate, e.g. because of invalid options.
+    pub fn load<P: AsRef<Path>>(path: P, options: &[(&str, &str)]) -> Result<ExternalDelegate> {
+        let path = path.as_ref();
+        let library = Library::open(path)?;
+        let create =
+            library.symbol(CStr::from_bytes_with_nul(b"tflite_plugin_create_delegate\0").unwrap());
+        let destroy =
+            library.symbol(CStr::from_bytes_with_nul(b"tflite_plugin_destroy_delegate\0").unwrap());
+        if create.is_null() || destroy.is_null() {
+            return Err(Error::new(ErrorKind::FailedToLoadDelegate));
+        }
+        let (create, destroy) = unsafe {
+            (
+                std::mem::transmute::<*mut c_void, TfLiteExternalDelegateCreate>(create),
+                std::mem::transmute::<*mut c_void, TfLiteExternalDelegateDestroy>(destroy),
+            )
+        };
+
+        let to_c_string = |value: &str| {
+            CString::new(value).map_err(|_| Error::new(ErrorKind::FailedToCreateDelegate))
+        };
+        let keys = options
+            .iter()
+            .map(|(key, _)| to_c_string(key))
+            .collect::<Result<Vec<CString>>>()?;
+        let values = options
+            .iter()
+            .map(|(_, value)| to_c_string(value))
+            .collect::<Result<Vec<CString>>>()?;
+        let key_ptrs = keys.iter().map(|key| key.as_ptr()).collect::<Vec<_>>();
+        let value_ptrs = values
+            .iter()
+            .map(|value| value.as_ptr())
+            .collect::<Vec<_>>();
+
+        let delegate_ptr = unsafe {
+            create(
+                key_ptrs.as_ptr(),
+                value_ptrs.as_ptr(),
+                options.len(),
+                Some(report_external_delegate_error),
+            )
+        };
+        NonNull::new(delegate_ptr)
+            .map(|delegate_ptr| ExternalDelegate {
+                delegate_ptr,
+                destroy,
+                path: path.to_path_buf(),
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "external delegate".to_string()),
+                library,
+            })
+            .ok_or_else(|| Error::new(ErrorKind::FailedToCreateDelegate))
+    }
+
+    /// Returns the path of the delegate library.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+unsafe impl Delegate for ExternalDelegate {
+    fn as_ptr(&self) -> *mut TfLiteDelegate {
+        self.delegate_ptr.as_ptr()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for ExternalDelegate {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.delegate_ptr.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delegate::{DelegateFailurePolicy, DelegateStatus, ExternalDelegate, Library};
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::interpreter::InterpreterBuilder;
+    use crate::model::Model;
+    use crate::ErrorKind;
+    use std::ffi::CStr;
+    use std::os::raw::c_void;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// Compiles `tests/stub_delegate.c` into a shared library named after the test,
+    /// since tests run concurrently.
+    #[cfg(unix)]
+    fn build_stub_delegate(test_name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("tflitec_{}_{}.so", test_name, std::process::id()));
+        let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let status = Command::new(compiler)
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&path)
+            .arg("tests/stub_delegate.c")
+            .status()
+            .expect("Cannot run the C compiler!");
+        assert!(status.success(), "Cannot compile the stub delegate!");
+        path
+    }
+
+    #[test]
+    fn test_external_delegate_load_failure() {
+        let err = ExternalDelegate::load("tests/missing_delegate.so", &[])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::FailedToLoadDelegate, err.kind());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_delegate() {
+        let path = build_stub_delegate("external_delegate");
+        // Keeps the library loaded to inspect its counters after the delegates are dropped.
+        let library = Library::open(&path).expect("Cannot load the stub delegate!");
+        let counter = |name: &[u8]| unsafe {
+            let symbol = library.symbol(CStr::from_bytes_with_nul(name).unwrap());
+            assert!(!symbol.is_null());
+            std::mem::transmute::<*mut c_void, unsafe extern "C" fn() -> i32>(symbol)()
+        };
+
+        let err = ExternalDelegate::load(&path, &[("fail", "true")])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::FailedToCreateDelegate, err.kind());
+        assert_eq!(0, counter(b"stub_delegate_live_count\0"));
+
+        let delegate = ExternalDelegate::load(&path, &[("name", "stub"), ("fail", "false")])
+            .expect("Cannot create the stub delegate!");
+        assert_eq!(path, delegate.path());
+        assert_eq!(1, counter(b"stub_delegate_live_count\0"));
+        assert_eq!(2, counter(b"stub_delegate_option_count\0"));
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = InterpreterBuilder::new(&model)
+            .add_delegate(delegate)
+            .build()
+            .expect("Cannot create interpreter!");
+        assert_eq!(1, counter(b"stub_delegate_prepare_count\0"));
+        let report = &interpreter.delegate_status()[0];
+        assert_eq!(path.file_stem().unwrap().to_str().unwrap(), report.name);
+        assert_eq!(DelegateStatus::NotApplied, report.status);
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        assert!(interpreter.copy(&data[..], 0).is_ok());
+        assert!(interpreter.invoke().is_ok());
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        assert_eq!(expected, interpreter.output(0).unwrap().data::<f32>());
+
+        drop(interpreter);
+        assert_eq!(0, counter(b"stub_delegate_live_count\0"));
+        drop(library);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_delegate_failure_policy() {
+        let path = build_stub_delegate("delegate_failure_policy");
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let load = |options: &[(&str, &str)]| ExternalDelegate::load(&path, options).unwrap();
+
+        let err = InterpreterBuilder::new(&model)
+            .add_delegate(load(&[("prepare", "error")]))
+            .delegate_failure_policy(DelegateFailurePolicy::Fail)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::FailedToCreateInterpreter, err.kind());
+
+        let interpreter = InterpreterBuilder::new(&model)
+            .add_delegate(load(&[("prepare", "error")]))
+            .add_delegate(load(&[]))
+            .build()
+            .expect("Cannot create interpreter!");
+        let status = interpreter.delegate_status();
+        assert_eq!(2, status.len());
+        assert!(matches!(status[0].status, DelegateStatus::Rejected { .. }));
+        assert_eq!(DelegateStatus::NotApplied, status[1].status);
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        assert!(interpreter.copy(&data[..], 0).is_ok());
+        assert!(interpreter.invoke().is_ok());
+        drop(interpreter);
+        std::fs::remove_file(path).unwrap();
+    }
+}
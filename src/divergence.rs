@@ -0,0 +1,658 @@
+//! Locating the first layer where two configurations of an
+//! [`Interpreter`][crate::interpreter::Interpreter] compute different values.
+//!
+//! A [`DivergenceLocator`] runs the same inputs through a reference and a candidate
+//! configuration of one [`Model`], compares the output tensors of every node and reports
+//! the first and the worst diverging nodes.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::divergence::{DivergenceLocator, Tolerance};
+//! use tflitec::model::Model;
+//! let model = Model::new("tests/add.bin")?;
+//! let report = DivergenceLocator::new(&model)
+//!     .candidate(|builder| builder.add_xnnpack_delegate())
+//!     .tolerance(Tolerance {
+//!         absolute: 1e-4,
+//!         relative: 1e-3,
+//!     })
+//!     .locate(|interpreter| interpreter.copy(&[1.0f32; 192][..], 0))?;
+//! if let Some(divergence) = report.first_divergence() {
+//!     println!("node {} ({}) diverges first:\n{}", divergence.node, divergence.op, report);
+//! }
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::execution_plan::{ExecutionPlan, NodeBackend};
+use crate::interpreter::{Configuration, Interpreter, InterpreterBuilder};
+use crate::model::Model;
+use crate::tensor::{DataType, Tensor};
+use crate::{Error, ErrorKind, Result};
+
+/// The tolerance of the comparison of a candidate value with a reference value.
+///
+/// Values diverge if `|candidate - reference| > absolute + relative * |reference|`,
+/// like `numpy.isclose`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    /// The allowed absolute difference.
+    pub absolute: f64,
+    /// The allowed difference relative to the magnitude of the reference value.
+    pub relative: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            absolute: 1e-5,
+            relative: 1e-4,
+        }
+    }
+}
+
+impl Tolerance {
+    /// Returns whether `candidate` is close enough to `reference`.
+    fn is_close(&self, reference: f64, candidate: f64) -> bool {
+        if reference.is_nan() || candidate.is_nan() {
+            return reference.is_nan() && candidate.is_nan();
+        }
+        if reference == candidate {
+            return true;
+        }
+        (candidate - reference).abs() <= self.absolute + self.relative * reference.abs()
+    }
+}
+
+/// The comparison of an output tensor of a node in a [`DivergenceReport`].
+///
+/// Quantized tensors are compared after dequantization. If the shapes of the tensors
+/// differ, every element is a mismatch and the errors are infinite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorDivergence {
+    /// The index of the node producing the tensor.
+    pub node: usize,
+    /// The name of the operator of the node.
+    pub op: String,
+    /// The index of the tensor.
+    pub tensor: usize,
+    /// The name of the tensor.
+    pub name: String,
+    /// The number of compared elements.
+    pub elements: usize,
+    /// The number of elements outside of the [`Tolerance`].
+    pub mismatches: usize,
+    /// The largest absolute difference of an element.
+    pub max_absolute_error: f64,
+    /// The mean absolute difference of the elements.
+    pub mean_absolute_error: f64,
+    /// The largest difference relative to a non-zero reference element.
+    pub max_relative_error: f64,
+}
+
+impl TensorDivergence {
+    /// Returns whether any element is outside of the [`Tolerance`].
+    pub fn is_diverging(&self) -> bool {
+        self.mismatches > 0
+    }
+}
+
+/// The comparisons of the output tensors of all nodes, in execution order of the
+/// reference configuration.
+///
+/// Tensors computed inside a delegate partition are never written to the tensor buffers,
+/// so only the outputs of partitions can be compared for nodes run by delegates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivergenceReport {
+    tensors: Vec<TensorDivergence>,
+    uncompared_tensors: Vec<usize>,
+}
+
+impl DivergenceReport {
+    /// Returns the comparisons of the compared tensors in execution order.
+    pub fn tensors(&self) -> &[TensorDivergence] {
+        &self.tensors
+    }
+
+    /// Returns the indices of the node outputs which could not be compared, because
+    /// a delegate keeps them inside a partition, they have no data or they do not hold real
+    /// numbers, e.g. strings.
+    pub fn uncompared_tensors(&self) -> &[usize] {
+        &self.uncompared_tensors
+    }
+
+    /// Returns whether any tensor diverges.
+    pub fn is_diverging(&self) -> bool {
+        self.first_divergence().is_some()
+    }
+
+    /// Returns the first diverging tensor in execution order, which is where
+    /// the configurations start to disagree.
+    pub fn first_divergence(&self) -> Option<&TensorDivergence> {
+        self.tensors.iter().find(|tensor| tensor.is_diverging())
+    }
+
+    /// Returns the diverging tensor with the largest absolute error.
+    pub fn worst_divergence(&self) -> Option<&TensorDivergence> {
+        self.tensors
+            .iter()
+            .filter(|tensor| tensor.is_diverging())
+            .reduce(|worst, tensor| {
+                if tensor.max_absolute_error > worst.max_absolute_error {
+                    tensor
+                } else {
+                    worst
+                }
+            })
+    }
+}
+
+impl Display for DivergenceReport {
+    /// Formats the report as a table of compared tensors followed by the first and
+    /// the worst divergences.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op_width = self
+            .tensors
+            .iter()
+            .map(|tensor| tensor.op.len())
+            .chain(std::iter::once(2))
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "{:>5}  {:<op_width$}  {:>6}  {:>10}  {:>12}  {:>12}  {:>12}",
+            "node",
+            "op",
+            "tensor",
+            "mismatches",
+            "max abs",
+            "mean abs",
+            "max rel",
+            op_width = op_width
+        )?;
+        for tensor in &self.tensors {
+            writeln!(
+                f,
+                "{:>5}  {:<op_width$}  {:>6}  {:>10}  {:>12.6e}  {:>12.6e}  {:>12.6e}",
+                tensor.node,
+                tensor.op,
+                tensor.tensor,
+                tensor.mismatches,
+                tensor.max_absolute_error,
+                tensor.mean_absolute_error,
+                tensor.max_relative_error,
+                op_width = op_width
+            )?;
+        }
+        if !self.uncompared_tensors.is_empty() {
+            writeln!(f, "{} tensors not compared", self.uncompared_tensors.len())?;
+        }
+        match (self.first_divergence(), self.worst_divergence()) {
+            (Some(first), Some(worst)) => write!(
+                f,
+                "first divergence at node {} ({}), worst at node {} ({})",
+                first.node, first.op, worst.node, worst.op
+            ),
+            _ => write!(f, "no divergence"),
+        }
+    }
+}
+
+/// Compares the output tensors of every node of two interpreter configurations.
+///
+/// Both configurations default to an interpreter without delegates, which runs the
+/// reference CPU kernels.
+pub struct DivergenceLocator<'a> {
+    model: &'a Model<'a>,
+    reference: Box<Configuration<'a>>,
+    candidate: Box<Configuration<'a>>,
+    tolerance: Tolerance,
+}
+
+impl Debug for DivergenceLocator<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DivergenceLocator")
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+impl<'a> DivergenceLocator<'a> {
+    /// Creates a locator comparing interpreters of `model`.
+    pub fn new(model: &'a Model<'a>) -> DivergenceLocator<'a> {
+        DivergenceLocator {
+            model,
+            reference: Box::new(|builder| builder),
+            candidate: Box::new(|builder| builder),
+            tolerance: Tolerance::default(),
+        }
+    }
+
+    /// Sets the configuration of the reference interpreter.
+    pub fn reference<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(InterpreterBuilder<'a>) -> InterpreterBuilder<'a> + 'a,
+    {
+        self.reference = Box::new(configure);
+        self
+    }
+
+    /// Sets the configuration of the candidate interpreter.
+    pub fn candidate<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(InterpreterBuilder<'a>) -> InterpreterBuilder<'a> + 'a,
+    {
+        self.candidate = Box::new(configure);
+        self
+    }
+
+    /// Sets the [`Tolerance`] of the comparison.
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Runs both configurations and compares the output tensors of every node.
+    ///
+    /// # Arguments
+    ///
+    /// * `feed`: Copies the inputs into an interpreter whose tensors are allocated.
+    ///   It is called once for each configuration and may resize the inputs if it
+    ///   allocates the tensors again.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an interpreter cannot be built, `feed` fails or an invocation fails.
+    pub fn locate<F>(&self, feed: F) -> Result<DivergenceReport>
+    where
+        F: Fn(&Interpreter) -> Result<()>,
+    {
+        let reference = self.run(&self.reference, &feed)?;
+        let candidate = self.run(&self.candidate, &feed)?;
+        let reference_plan = execution_plan(&reference)?;
+        let reference_available = available_tensors(&reference, reference_plan)?;
+        let candidate_available = available_tensors(&candidate, execution_plan(&candidate)?)?;
+
+        let mut tensors = Vec::new();
+        let mut uncompared_tensors = Vec::new();
+        for node in reference_plan.nodes() {
+            for index in &node.outputs {
+                let is_available =
+                    reference_available.contains(index) && candidate_available.contains(index);
+                let pair = match (reference.tensor(*index), candidate.tensor(*index)) {
+                    (Ok(reference), Ok(candidate))
+                        if is_available && has_real_values(reference.data_type()) =>
+                    {
+                        Some((reference, candidate))
+                    }
+                    _ => None,
+                };
+                let comparison = pair.map(|(reference, candidate)| {
+                    let mut comparison = compare(&reference, &candidate, &self.tolerance);
+                    comparison.node = node.index;
+                    comparison.op = node.op.clone();
+                    comparison.tensor = *index;
+                    comparison
+                });
+                match comparison {
+                    Some(comparison) => tensors.push(comparison),
+                    None => uncompared_tensors.push(*index),
+                }
+            }
+        }
+        Ok(DivergenceReport {
+            tensors,
+            uncompared_tensors,
+        })
+    }
+
+    /// Builds an interpreter with `configure`, feeds it and invokes it.
+    fn run<F>(&self, configure: &Configuration<'a>, feed: &F) -> Result<Interpreter<'a>>
+    where
+        F: Fn(&Interpreter) -> Result<()>,
+    {
+        let interpreter = configure(InterpreterBuilder::new(self.model))
+            .preserve_all_tensors(true)
+            .record_execution_plan()
+            .build()?;
+        interpreter.allocate_tensors()?;
+        feed(&interpreter)?;
+        interpreter.invoke()?;
+        Ok(interpreter)
+    }
+}
+
+/// Returns the execution plan, which is always recorded by a [`DivergenceLocator`].
+fn execution_plan<'b>(interpreter: &'b Interpreter) -> Result<&'b ExecutionPlan> {
+    interpreter
+        .execution_plan()
+        .ok_or_else(|| Error::new(ErrorKind::ExecutionPlanUnavailable))
+}
+
+/// Returns the indices of the node outputs written to the tensor buffers, which are those
+/// computed by CPU kernels, and the outputs of delegate partitions.
+fn available_tensors(interpreter: &Interpreter, plan: &ExecutionPlan) -> Result<HashSet<usize>> {
+    let mut outputs = HashSet::new();
+    for index in 0..interpreter.output_tensor_count() {
+        let name = interpreter.output(index)?.name().to_owned();
+        outputs.extend(interpreter.tensor_index(&name));
+    }
+    let nodes = plan.nodes();
+    let mut available = HashSet::new();
+    for node in nodes {
+        for index in &node.outputs {
+            // A tensor leaves a delegate partition if it is an output of the graph or
+            // it is read by a node which is not run by the same delegate.
+            let is_available = node.backend == NodeBackend::Cpu
+                || outputs.contains(index)
+                || nodes.iter().any(|consumer| {
+                    consumer.backend != node.backend && consumer.inputs.contains(index)
+                });
+            if is_available {
+                available.insert(*index);
+            }
+        }
+    }
+    Ok(available)
+}
+
+/// Compares the values of `candidate` with those of `reference`.
+fn compare(reference: &Tensor, candidate: &Tensor, tolerance: &Tolerance) -> TensorDivergence {
+    let reference_values = real_values(reference);
+    let candidate_values = real_values(candidate);
+    let mut divergence = TensorDivergence {
+        node: 0,
+        op: String::new(),
+        tensor: 0,
+        name: reference.name().to_owned(),
+        elements: reference_values.len().max(candidate_values.len()),
+        mismatches: 0,
+        max_absolute_error: 0.0,
+        mean_absolute_error: 0.0,
+        max_relative_error: 0.0,
+    };
+    if reference.shape() != candidate.shape() || reference_values.len() != candidate_values.len() {
+        divergence.mismatches = divergence.elements;
+        divergence.max_absolute_error = f64::INFINITY;
+        divergence.mean_absolute_error = f64::INFINITY;
+        divergence.max_relative_error = f64::INFINITY;
+        return divergence;
+    }
+    let mut total_absolute_error = 0.0;
+    for (reference, candidate) in reference_values.iter().zip(&candidate_values) {
+        let absolute_error = if reference.is_nan() && candidate.is_nan() {
+            0.0
+        } else if reference.is_nan() || candidate.is_nan() {
+            f64::INFINITY
+        } else {
+            (candidate - reference).abs()
+        };
+        if !tolerance.is_close(*reference, *candidate) {
+            divergence.mismatches += 1;
+        }
+        total_absolute_error += absolute_error;
+        divergence.max_absolute_error = divergence.max_absolute_error.max(absolute_error);
+        if *reference != 0.0 {
+            let relative_error = absolute_error / reference.abs();
+            divergence.max_relative_error = divergence.max_relative_error.max(relative_error);
+        }
+    }
+    if divergence.elements > 0 {
+        divergence.mean_absolute_error = total_absolute_error / divergence.elements as f64;
+    }
+    divergence
+}
+
+/// Returns whether tensors of `data_type` hold real numbers which can be compared.
+fn has_real_values(data_type: DataType) -> bool {
+    !matches!(
+        data_type,
+        DataType::String
+            | DataType::Complex64
+            | DataType::Complex128
+            | DataType::Resource
+            | DataType::Variant
+            | DataType::Unknown(_)
+    )
+}
+
+/// Returns the values of `tensor` as real numbers, dequantizing quantized tensors.
+fn real_values(tensor: &Tensor) -> Vec<f64> {
+    let values: Vec<f64> = match tensor.data_type() {
+        DataType::Bool => tensor
+            .data::<u8>()
+            .iter()
+            .map(|v| (*v != 0) as u8 as f64)
+            .collect(),
+        DataType::Uint8 => tensor.data::<u8>().iter().map(|v| *v as f64).collect(),
+        DataType::Int8 => tensor.data::<i8>().iter().map(|v| *v as f64).collect(),
+        DataType::Int16 => tensor.data::<i16>().iter().map(|v| *v as f64).collect(),
+        DataType::Int32 => tensor.data::<i32>().iter().map(|v| *v as f64).collect(),
+        DataType::Int64 => tensor.data::<i64>().iter().map(|v| *v as f64).collect(),
+        DataType::Uint16 => tensor.data::<u16>().iter().map(|v| *v as f64).collect(),
+        DataType::Uint32 => tensor.data::<u32>().iter().map(|v| *v as f64).collect(),
+        DataType::Uint64 => tensor.data::<u64>().iter().map(|v| *v as f64).collect(),
+        DataType::Int4 => tensor
+            .int4_values()
+            .unwrap_or_default()
+            .iter()
+            .map(|v| *v as f64)
+            .collect(),
+        DataType::Float16 => tensor
+            .data::<u16>()
+            .iter()
+            .map(|v| f16_to_f64(*v))
+            .collect(),
+        DataType::BFloat16 => tensor
+            .data::<u16>()
+            .iter()
+            .map(|v| f32::from_bits((*v as u32) << 16) as f64)
+            .collect(),
+        DataType::Float32 => tensor.data::<f32>().iter().map(|v| *v as f64).collect(),
+        DataType::Float64 => tensor.data::<f64>().to_vec(),
+        // Other tensors have no real values and are never compared, see `has_real_values`.
+        DataType::String
+        | DataType::Complex64
+        | DataType::Complex128
+        | DataType::Resource
+        | DataType::Variant
+        | DataType::Unknown(_) => Vec::new(),
+    };
+    let is_quantized = matches!(
+        tensor.data_type(),
+        DataType::Uint8 | DataType::Int8 | DataType::Int16 | DataType::Int4
+    );
+    match tensor.quantization_parameters() {
+        Some(parameters) if is_quantized && parameters.scale != 0.0 => values
+            .iter()
+            .map(|v| parameters.scale as f64 * (v - parameters.zero_point as f64))
+            .collect(),
+        _ => values,
+    }
+}
+
+/// Converts the bits of a half precision float to a double precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f64;
+    match exponent {
+        0 => sign * fraction * 2f64.powi(-24),
+        0x1f if fraction == 0.0 => sign * f64::INFINITY,
+        0x1f => f64::NAN,
+        _ => sign * (1.0 + fraction / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::custom_op::{CustomOp, OpError, OpTensor};
+    use crate::divergence::{f16_to_f64, DivergenceLocator, Tolerance};
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::model::Model;
+    use crate::tensor::QuantizationParameters;
+    use crate::test_models::{custom_op_model, quantized_custom_op_model};
+
+    /// Multiplies its input by a fixed factor.
+    struct Scale(f32);
+
+    impl CustomOp for Scale {
+        type State = ();
+
+        fn init(&self, _: &[u8]) {}
+
+        fn prepare(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            outputs[0].resize(inputs[0].shape().clone())
+        }
+
+        fn invoke(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            let input = inputs[0].data::<f32>();
+            for (y, x) in outputs[0].data_mut::<f32>().iter_mut().zip(input) {
+                *y = *x * self.0;
+            }
+            Ok(())
+        }
+    }
+
+    /// Multiplies the quantized values of its int8 input by a fixed factor.
+    struct QuantizedScale(i8);
+
+    impl CustomOp for QuantizedScale {
+        type State = ();
+
+        fn init(&self, _: &[u8]) {}
+
+        fn prepare(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            outputs[0].resize(inputs[0].shape().clone())
+        }
+
+        fn invoke(
+            &self,
+            _: &mut (),
+            inputs: &[OpTensor],
+            outputs: &mut [OpTensor],
+        ) -> Result<(), OpError> {
+            let input = inputs[0].data::<i8>();
+            for (y, x) in outputs[0].data_mut::<i8>().iter_mut().zip(input) {
+                *y = *x * self.0;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let tolerance = Tolerance {
+            absolute: 0.1,
+            relative: 0.01,
+        };
+        assert!(tolerance.is_close(10.0, 10.2));
+        assert!(!tolerance.is_close(10.0, 10.3));
+        assert!(tolerance.is_close(f64::NAN, f64::NAN));
+        assert!(!tolerance.is_close(0.0, f64::NAN));
+        assert!(tolerance.is_close(f64::INFINITY, f64::INFINITY));
+        assert_eq!(1.0, f16_to_f64(0x3c00));
+        assert_eq!(-2.0, f16_to_f64(0xc000));
+        assert_eq!(f64::INFINITY, f16_to_f64(0x7c00));
+    }
+
+    #[test]
+    fn test_divergence_locator_same_configuration() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        let report = DivergenceLocator::new(&model)
+            .locate(|interpreter| interpreter.copy(&data[..], 0))
+            .expect("Cannot locate divergence!");
+        assert!(!report.is_diverging());
+        assert_eq!(2, report.tensors().len());
+        assert!(report.uncompared_tensors().is_empty());
+        assert!(report.tensors().iter().all(|tensor| tensor.op == "ADD"));
+        assert!(report.to_string().ends_with("no divergence"));
+    }
+
+    #[test]
+    fn test_divergence_locator() {
+        let model_data = custom_op_model("RustScale", &[]);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let report = DivergenceLocator::new(&model)
+            .reference(|builder| builder.add_custom_op("RustScale", Scale(2.0)))
+            .candidate(|builder| builder.add_custom_op("RustScale", Scale(2.5)))
+            .locate(|interpreter| interpreter.copy(&[1.0f32, 2.0, 3.0, 4.0][..], 0))
+            .expect("Cannot locate divergence!");
+        assert!(report.is_diverging());
+        let first = report.first_divergence().unwrap();
+        assert_eq!(first, report.worst_divergence().unwrap());
+        assert_eq!(0, first.node);
+        assert_eq!("RustScale", first.op);
+        assert_eq!(4, first.elements);
+        assert_eq!(4, first.mismatches);
+        assert_eq!(2.0, first.max_absolute_error);
+        assert_eq!(1.25, first.mean_absolute_error);
+        assert_eq!(0.25, first.max_relative_error);
+    }
+
+    #[test]
+    fn test_divergence_locator_quantized() {
+        let model_data = quantized_custom_op_model("RustQuantizedScale", 0.5, 10);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        // The input dequantizes to [1, 2, 3, 4].
+        let input = [12i8, 14, 16, 18];
+        let report = DivergenceLocator::new(&model)
+            .reference(|builder| builder.add_custom_op("RustQuantizedScale", QuantizedScale(1)))
+            .candidate(|builder| builder.add_custom_op("RustQuantizedScale", QuantizedScale(2)))
+            .locate(|interpreter| {
+                let quantization_parameters = Some(QuantizationParameters {
+                    scale: 0.5,
+                    zero_point: 10,
+                });
+                let tensor = interpreter.input(0)?;
+                assert_eq!(quantization_parameters, tensor.quantization_parameters());
+                assert_eq!(
+                    quantization_parameters,
+                    tensor.to_owned().quantization_parameters()
+                );
+                interpreter.copy(&input[..], 0)
+            })
+            .expect("Cannot locate divergence!");
+        assert!(report.is_diverging());
+        let first = report.first_divergence().unwrap();
+        assert_eq!(4, first.mismatches);
+        // The outputs dequantize to [1, 2, 3, 4] and [7, 9, 11, 13], while their
+        // quantized values differ by 12 to 18.
+        assert_eq!(9.0, first.max_absolute_error);
+        assert_eq!(7.5, first.mean_absolute_error);
+        assert_eq!(6.0, first.max_relative_error);
+    }
+
+    #[cfg(feature = "xnnpack")]
+    #[test]
+    fn test_divergence_locator_xnnpack() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        let report = DivergenceLocator::new(&model)
+            .candidate(|builder| builder.add_xnnpack_delegate())
+            .locate(|interpreter| interpreter.copy(&data[..], 0))
+            .expect("Cannot locate divergence!");
+        assert!(!report.is_diverging());
+        // The intermediate tensor stays inside the partition of XNNPACK.
+        assert_eq!(1, report.tensors().len());
+        assert_eq!(1, report.uncompared_tensors().len());
+    }
+}
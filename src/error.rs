@@ -7,6 +7,10 @@ pub enum ErrorKind {
     /// Indicates given tensor index (first value) is larger than maximum index (second value).
     InvalidTensorIndex(/* index: */ usize, /* max_index: */ usize),
     /// Indicates given data length (first value) is not equal to required length (second value).
+    ///
+    /// Tensor data lengths are counted in bytes, e.g. by
+    /// [`Interpreter::copy()`][crate::interpreter::Interpreter::copy], except for
+    /// [`Tensor::set_data()`][crate::tensor::Tensor::set_data], which counts elements of `T`.
     InvalidTensorDataCount(/* provided: */ usize, /* required: */ usize),
     /// Indicates failure to resize tensor with index (first value).
     FailedToResizeInputTensor(/* index: */ usize),
@@ -18,6 +22,59 @@ pub enum ErrorKind {
     FailedToCreateInterpreter,
     ReadTensorError,
     InvokeInterpreterRequired,
+    /// Indicates the invocation was cancelled through a [`CancelHandle`][crate::interpreter::CancelHandle]
+    /// or because its deadline elapsed.
+    Cancelled,
+    /// Indicates cancellation was requested on an interpreter created without
+    /// [`Options::is_cancellation_enabled`][crate::interpreter::Options::is_cancellation_enabled].
+    CancellationNotEnabled,
+    /// Indicates the interpreter options conflict, with the reason of the conflict.
+    InvalidOptions(/* reason: */ &'static str),
+    /// Indicates failure to prepare the directory of the XNNPACK weight cache file.
+    FailedToPrepareWeightCache,
+    /// Indicates failure to create an XNNPACK weights cache.
+    FailedToCreateWeightsCache,
+    /// Indicates failure to finalize an XNNPACK weights cache, or that it is already finalized.
+    FailedToFinalizeWeightsCache,
+    /// Indicates failure to load a delegate library, or that it does not export
+    /// the external delegate interface.
+    FailedToLoadDelegate,
+    /// Indicates the delegate library failed to create a delegate.
+    FailedToCreateDelegate,
+    /// Indicates TensorFlow Lite C failed to create the operator of a
+    /// [`CustomOp`][crate::custom_op::CustomOp].
+    FailedToRegisterCustomOp,
+    /// Indicates TensorFlow Lite C failed to invoke the interpreter, e.g. because an
+    /// operator failed.
+    FailedToInvokeInterpreter,
+    /// Indicates failure to write tensor data to a file.
+    FailedToWriteTensor,
+    /// Indicates the interpreter did not record its execution plan.
+    ExecutionPlanUnavailable,
+    /// Indicates failure to reset the variable tensors to their initial values.
+    FailedToResetVariables,
+    /// Indicates the state was not taken from an interpreter with the same variable tensors.
+    IncompatibleState,
+    /// Indicates a state input and output pair of a
+    /// [`StreamingSession`][crate::streaming::StreamingSession] is invalid, with the reason.
+    InvalidStatePair(/* reason: */ &'static str),
+    /// Indicates the request queue of an `AsyncInterpreter` is full.
+    QueueFull,
+    /// Indicates the worker threads of an `AsyncInterpreter` or a
+    /// [`Batcher`][crate::batching::Batcher] have stopped.
+    InterpreterShutDown,
+    /// Indicates the shape given for the input with index (first value) does not match
+    /// the rank or a fixed dimension of its shape signature.
+    IncompatibleInputShape(/* index: */ usize),
+    /// Indicates a dimension or the number of elements of the shape given for the input with
+    /// index (first value) is too large for TensorFlow Lite.
+    ShapeOverflow(/* index: */ usize),
+    /// Indicates the shape of an array does not match the shape of the input tensor with
+    /// index (first value).
+    ShapeMismatch(/* index: */ usize),
+    /// Indicates the number of given inputs or input shapes (first value) does not match
+    /// the number of input tensors (second value).
+    InputCountMismatch(/* provided: */ usize, /* required: */ usize),
 }
 
 impl ErrorKind {
@@ -48,6 +105,52 @@ impl ErrorKind {
             ErrorKind::FailedToCreateInterpreter => "failed to create the interpreter".to_string(),
             ErrorKind::ReadTensorError => "failed to read tensor".to_string(),
             ErrorKind::InvokeInterpreterRequired => "must call invoke()".to_string(),
+            ErrorKind::Cancelled => "invocation was cancelled".to_string(),
+            ErrorKind::CancellationNotEnabled => {
+                "cancellation is not enabled for the interpreter".to_string()
+            }
+            ErrorKind::InvalidOptions(reason) => format!("invalid interpreter options: {}", reason),
+            ErrorKind::FailedToPrepareWeightCache => {
+                "failed to prepare the XNNPACK weight cache file".to_string()
+            }
+            ErrorKind::FailedToCreateWeightsCache => {
+                "failed to create the XNNPACK weights cache".to_string()
+            }
+            ErrorKind::FailedToFinalizeWeightsCache => {
+                "failed to finalize the XNNPACK weights cache".to_string()
+            }
+            ErrorKind::FailedToLoadDelegate => "failed to load the delegate library".to_string(),
+            ErrorKind::FailedToCreateDelegate => "failed to create the delegate".to_string(),
+            ErrorKind::FailedToRegisterCustomOp => {
+                "failed to register the custom operator".to_string()
+            }
+            ErrorKind::FailedToInvokeInterpreter => "failed to invoke the interpreter".to_string(),
+            ErrorKind::FailedToWriteTensor => "failed to write the tensor file".to_string(),
+            ErrorKind::ExecutionPlanUnavailable => {
+                "the execution plan of the interpreter was not recorded".to_string()
+            }
+            ErrorKind::FailedToResetVariables => "failed to reset the variable tensors".to_string(),
+            ErrorKind::IncompatibleState => {
+                "the state does not match the variable tensors of the interpreter".to_string()
+            }
+            ErrorKind::InvalidStatePair(reason) => format!("invalid state pair: {}", reason),
+            ErrorKind::QueueFull => "the request queue is full".to_string(),
+            ErrorKind::InterpreterShutDown => "the interpreter is shut down".to_string(),
+            ErrorKind::IncompatibleInputShape(index) => format!(
+                "shape of input tensor at index {} does not match its shape signature",
+                index
+            ),
+            ErrorKind::ShapeOverflow(index) => {
+                format!("shape of input tensor at index {} is too large", index)
+            }
+            ErrorKind::ShapeMismatch(index) => format!(
+                "array shape does not match the shape of input tensor at index {}",
+                index
+            ),
+            ErrorKind::InputCountMismatch(provided, required) => format!(
+                "provided input count {} must match the input tensor count {}",
+                provided, required
+            ),
         }
     }
 }
@@ -0,0 +1,477 @@
+//! The [`ExecutionPlan`] of an [`Interpreter`][crate::interpreter::Interpreter], which tells
+//! the nodes run by delegates apart from those falling back to CPU kernels.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::delegate::ExternalDelegate;
+//! use tflitec::interpreter::InterpreterBuilder;
+//! use tflitec::model::Model;
+//! let model = Model::new("tests/add.bin")?;
+//! let interpreter = InterpreterBuilder::new(&model)
+//!     .add_delegate(ExternalDelegate::load("libmy_delegate.so", &[])?)
+//!     .build()?;
+//! let plan = interpreter.execution_plan().expect("a delegate is added");
+//! assert_eq!(plan.cpu_nodes(), 0, "some nodes fell back to CPU kernels:\n{}", plan);
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::os::raw::{c_int, c_void};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+use crate::delegate::Delegate;
+use crate::minimal_bindings::*;
+
+/// The backend running a node of an [`ExecutionPlan`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub enum NodeBackend {
+    /// The node runs in a partition of the delegate with the given name.
+    Delegate(String),
+    /// The node runs on the builtin CPU kernels of TensorFlow Lite.
+    Cpu,
+}
+
+/// A node of the original graph in an [`ExecutionPlan`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct PlanNode {
+    /// The index of the node in the graph.
+    pub index: usize,
+    /// The name of the operator, such as `CONV_2D`, or the name of a custom operator.
+    pub op: String,
+    /// The indices of the input tensors, without the omitted optional inputs.
+    pub inputs: Vec<usize>,
+    /// The indices of the output tensors.
+    pub outputs: Vec<usize>,
+    /// The backend running the node.
+    pub backend: NodeBackend,
+}
+
+/// The nodes claimed by a delegate in an [`ExecutionPlan`].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct DelegateSummary {
+    /// The name of the delegate.
+    pub name: String,
+    /// The number of nodes of the original graph claimed by the delegate.
+    pub delegated_nodes: usize,
+    /// The number of partitions the delegate replaced its nodes with.
+    pub partitions: usize,
+}
+
+/// The nodes of a graph with the backend running each of them.
+///
+/// The plan is recorded while delegates are applied to the graph. Nodes are listed in
+/// the execution order of the original graph, where the nodes of a delegate partition
+/// run together at the position of the partition. Delegates applied by TensorFlow Lite
+/// itself when tensors are allocated are not reflected.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct ExecutionPlan {
+    nodes: Vec<PlanNode>,
+    delegates: Vec<DelegateSummary>,
+}
+
+impl ExecutionPlan {
+    /// Creates the plan from the execution plans recorded before the first delegate and
+    /// after each of the delegates with the given names.
+    pub(crate) fn from_probes(plans: &[Vec<ProbedNode>], names: &[String]) -> ExecutionPlan {
+        debug_assert_eq!(plans.len(), names.len() + 1);
+        let original = plans.first().map(Vec::as_slice).unwrap_or_default();
+        // Nodes of the original graph keep their indices, so the nodes claimed by a delegate
+        // are those which disappear from the execution plan when it is applied, and its
+        // partitions are the nodes which appear.
+        let is_in = |plan: &[ProbedNode], index: usize| plan.iter().any(|n| n.index == index);
+        let mut nodes = original
+            .iter()
+            .map(|node| PlanNode {
+                index: node.index,
+                op: node.op.clone(),
+                inputs: node.inputs.clone(),
+                outputs: node.outputs.clone(),
+                backend: NodeBackend::Cpu,
+            })
+            .collect::<Vec<PlanNode>>();
+        let mut delegates = Vec::with_capacity(names.len());
+        for (position, name) in names.iter().enumerate() {
+            let (before, after) = (&plans[position], &plans[position + 1]);
+            let mut delegated_nodes = 0;
+            for node in nodes.iter_mut() {
+                if is_in(before, node.index) && !is_in(after, node.index) {
+                    node.backend = NodeBackend::Delegate(name.clone());
+                    delegated_nodes += 1;
+                }
+            }
+            let partitions = after
+                .iter()
+                .filter(|node| node.index >= original.len() && !is_in(before, node.index))
+                .count();
+            delegates.push(DelegateSummary {
+                name: name.clone(),
+                delegated_nodes,
+                partitions,
+            });
+        }
+        ExecutionPlan { nodes, delegates }
+    }
+
+    /// Returns the nodes of the original graph in execution order.
+    pub fn nodes(&self) -> &[PlanNode] {
+        &self.nodes
+    }
+
+    /// Returns the delegates applied to the graph in order of application.
+    pub fn delegates(&self) -> &[DelegateSummary] {
+        &self.delegates
+    }
+
+    /// Returns the number of nodes of the original graph.
+    pub fn total_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of nodes claimed by any of the delegates.
+    pub fn delegated_nodes(&self) -> usize {
+        self.total_nodes() - self.cpu_nodes()
+    }
+
+    /// Returns the number of nodes running on the builtin CPU kernels.
+    pub fn cpu_nodes(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| node.backend == NodeBackend::Cpu)
+            .count()
+    }
+}
+
+impl Display for ExecutionPlan {
+    /// Formats the plan as a table of nodes followed by the summary counts.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op_width = self
+            .nodes
+            .iter()
+            .map(|node| node.op.len())
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "{:>5}  {:<op_width$}  backend",
+            "node",
+            "op",
+            op_width = op_width
+        )?;
+        for node in &self.nodes {
+            let backend = match &node.backend {
+                NodeBackend::Delegate(name) => name.as_str(),
+                NodeBackend::Cpu => "CPU",
+            };
+            writeln!(
+                f,
+                "{:>5}  {:<op_width$}  {}",
+                node.index,
+                node.op,
+                backend,
+                op_width = op_width
+            )?;
+        }
+        for delegate in &self.delegates {
+            writeln!(
+                f,
+                "{}: {} nodes in {} partitions",
+                delegate.name, delegate.delegated_nodes, delegate.partitions
+            )?;
+        }
+        write!(
+            f,
+            "{} of {} nodes delegated, {} on CPU",
+            self.delegated_nodes(),
+            self.total_nodes(),
+            self.cpu_nodes()
+        )
+    }
+}
+
+/// A node of an execution plan recorded by a [`PlanProbe`].
+#[derive(Debug, Clone)]
+pub(crate) struct ProbedNode {
+    index: usize,
+    op: String,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+/// A delegate which claims no nodes and records the execution plan when it is applied.
+///
+/// Probes are applied around the delegates of an interpreter, so that the nodes claimed by
+/// each delegate, and the delegate whose application failed, can be told apart.
+pub(crate) struct PlanProbe {
+    /// The underlying opaque [`TfLiteDelegate`] C pointer.
+    delegate_ptr: NonNull<TfLiteDelegate>,
+
+    /// The execution plan, which is boxed since its address is the user data of the delegate.
+    plan: Box<Mutex<Option<Vec<ProbedNode>>>>,
+}
+
+unsafe impl Send for PlanProbe {}
+
+impl PlanProbe {
+    /// Creates a new probe.
+    ///
+    /// returns: [`None`] if TensorFlow Lite C fails to create the delegate.
+    pub(crate) fn new() -> Option<PlanProbe> {
+        let plan = Box::new(Mutex::new(None));
+        let builder = TfLiteOpaqueDelegateBuilder {
+            data: plan.as_ref() as *const Mutex<Option<Vec<ProbedNode>>> as *mut c_void,
+            prepare: Some(record_execution_plan),
+            copy_from_buffer_handle: None,
+            copy_to_buffer_handle: None,
+            free_buffer_handle: None,
+            flags: TFLITE_DELEGATE_FLAGS_ALLOW_DYNAMIC_TENSORS,
+        };
+        let delegate_ptr = unsafe { TfLiteOpaqueDelegateCreate(&builder) };
+        NonNull::new(delegate_ptr).map(|delegate_ptr| PlanProbe { delegate_ptr, plan })
+    }
+
+    /// Returns the nodes of the execution plan when the probe was last applied,
+    /// or [`None`] if it has not been applied since the last [`PlanProbe::reset()`].
+    pub(crate) fn plan(&self) -> Option<Vec<ProbedNode>> {
+        self.plan.lock().unwrap().clone()
+    }
+
+    /// Forgets the recorded execution plan.
+    pub(crate) fn reset(&self) {
+        *self.plan.lock().unwrap() = None;
+    }
+}
+
+unsafe impl Delegate for PlanProbe {
+    fn as_ptr(&self) -> *mut TfLiteDelegate {
+        self.delegate_ptr.as_ptr()
+    }
+
+    fn name(&self) -> &str {
+        "execution plan probe"
+    }
+}
+
+impl Drop for PlanProbe {
+    fn drop(&mut self) {
+        unsafe { TfLiteOpaqueDelegateDelete(self.delegate_ptr.as_ptr()) }
+    }
+}
+
+/// Records the execution plan of `context` into the [`PlanProbe`] passed as `data`.
+unsafe extern "C" fn record_execution_plan(
+    context: *mut TfLiteOpaqueContext,
+    _delegate: *mut TfLiteOpaqueDelegate,
+    data: *mut c_void,
+) -> TfLiteStatus {
+    let plan = &*(data as *const Mutex<Option<Vec<ProbedNode>>>);
+    let mut execution_plan: *mut TfLiteIntArray = std::ptr::null_mut();
+    if TfLiteOpaqueContextGetExecutionPlan(context, &mut execution_plan).is_error()
+        || execution_plan.is_null()
+    {
+        // A probe never fails the graph, it just does not record anything.
+        return TfLiteStatus::kTfLiteOk;
+    }
+    let size = (*execution_plan).size.max(0) as usize;
+    let node_indices = std::slice::from_raw_parts((*execution_plan).data.as_ptr(), size);
+    let nodes = node_indices
+        .iter()
+        .map(|index| probe_node(context, *index))
+        .collect::<Vec<ProbedNode>>();
+    if let Ok(mut plan) = plan.lock() {
+        *plan = Some(nodes);
+    }
+    TfLiteStatus::kTfLiteOk
+}
+
+/// Reads the operator and tensors of the node with the given index of `context`.
+unsafe fn probe_node(context: *mut TfLiteOpaqueContext, index: c_int) -> ProbedNode {
+    let mut node_ptr: *mut TfLiteOpaqueNode = std::ptr::null_mut();
+    let mut operator_ptr: *mut TfLiteOperator = std::ptr::null_mut();
+    let mut probed = ProbedNode {
+        index: index as usize,
+        op: "UNKNOWN".to_string(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    if TfLiteOpaqueContextGetNodeAndRegistration(context, index, &mut node_ptr, &mut operator_ptr)
+        .is_error()
+    {
+        return probed;
+    }
+    if !operator_ptr.is_null() {
+        probed.op = operator_name(operator_ptr);
+    }
+    if !node_ptr.is_null() {
+        probed.inputs = tensor_indices(node_ptr, TfLiteOpaqueNodeInputs);
+        probed.outputs = tensor_indices(node_ptr, TfLiteOpaqueNodeOutputs);
+    }
+    probed
+}
+
+/// Returns the builtin operator name or, for custom operators and delegate kernels,
+/// the custom name of `operator_ptr`.
+unsafe fn operator_name(operator_ptr: *const TfLiteOperator) -> String {
+    let builtin_code = TfLiteOperatorGetBuiltInCode(operator_ptr);
+    let custom_name_ptr = TfLiteOperatorGetCustomName(operator_ptr);
+    if (builtin_code == TFLITE_BUILTIN_CUSTOM || builtin_code == BUILTIN_DELEGATE)
+        && !custom_name_ptr.is_null()
+    {
+        return CStr::from_ptr(custom_name_ptr)
+            .to_string_lossy()
+            .into_owned();
+    }
+    match BUILTIN_OPERATOR_NAMES.get(builtin_code as usize) {
+        Some(name) if builtin_code >= 0 => name.to_string(),
+        _ => format!("BUILTIN_{}", builtin_code),
+    }
+}
+
+/// Returns the tensor indices read by `accessor`, skipping omitted optional tensors.
+unsafe fn tensor_indices(
+    node_ptr: *const TfLiteOpaqueNode,
+    accessor: unsafe extern "C" fn(
+        *const TfLiteOpaqueNode,
+        *mut *const c_int,
+        *mut c_int,
+    ) -> TfLiteStatus,
+) -> Vec<usize> {
+    let mut indices_ptr: *const c_int = std::ptr::null();
+    let mut count: c_int = 0;
+    if accessor(node_ptr, &mut indices_ptr, &mut count).is_error() || indices_ptr.is_null() {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(indices_ptr, count.max(0) as usize)
+        .iter()
+        .filter(|index| **index >= 0)
+        .map(|index| *index as usize)
+        .collect()
+}
+
+/// The builtin code of the kernels replacing the nodes claimed by a delegate.
+const BUILTIN_DELEGATE: c_int = 51;
+
+/// The names of the builtin operators, indexed by builtin code as in `builtin_ops.h`.
+#[rustfmt::skip]
+const BUILTIN_OPERATOR_NAMES: [&str; 210] = [
+    "ADD", "AVERAGE_POOL_2D", "CONCATENATION", "CONV_2D", "DEPTHWISE_CONV_2D", "DEPTH_TO_SPACE",
+    "DEQUANTIZE", "EMBEDDING_LOOKUP", "FLOOR", "FULLY_CONNECTED", "HASHTABLE_LOOKUP",
+    "L2_NORMALIZATION", "L2_POOL_2D", "LOCAL_RESPONSE_NORMALIZATION", "LOGISTIC", "LSH_PROJECTION",
+    "LSTM", "MAX_POOL_2D", "MUL", "RELU", "RELU_N1_TO_1", "RELU6", "RESHAPE", "RESIZE_BILINEAR",
+    "RNN", "SOFTMAX", "SPACE_TO_DEPTH", "SVDF", "TANH", "CONCAT_EMBEDDINGS", "SKIP_GRAM", "CALL",
+    "CUSTOM", "EMBEDDING_LOOKUP_SPARSE", "PAD", "UNIDIRECTIONAL_SEQUENCE_RNN", "GATHER",
+    "BATCH_TO_SPACE_ND", "SPACE_TO_BATCH_ND", "TRANSPOSE", "MEAN", "SUB", "DIV", "SQUEEZE",
+    "UNIDIRECTIONAL_SEQUENCE_LSTM", "STRIDED_SLICE", "BIDIRECTIONAL_SEQUENCE_RNN", "EXP", "TOPK_V2",
+    "SPLIT", "LOG_SOFTMAX", "DELEGATE", "BIDIRECTIONAL_SEQUENCE_LSTM", "CAST", "PRELU", "MAXIMUM",
+    "ARG_MAX", "MINIMUM", "LESS", "NEG", "PADV2", "GREATER", "GREATER_EQUAL", "LESS_EQUAL",
+    "SELECT", "SLICE", "SIN", "TRANSPOSE_CONV", "SPARSE_TO_DENSE", "TILE", "EXPAND_DIMS", "EQUAL",
+    "NOT_EQUAL", "LOG", "SUM", "SQRT", "RSQRT", "SHAPE", "POW", "ARG_MIN", "FAKE_QUANT",
+    "REDUCE_PROD", "REDUCE_MAX", "PACK", "LOGICAL_OR", "ONE_HOT", "LOGICAL_AND", "LOGICAL_NOT",
+    "UNPACK", "REDUCE_MIN", "FLOOR_DIV", "REDUCE_ANY", "SQUARE", "ZEROS_LIKE", "FILL", "FLOOR_MOD",
+    "RANGE", "RESIZE_NEAREST_NEIGHBOR", "LEAKY_RELU", "SQUARED_DIFFERENCE", "MIRROR_PAD", "ABS",
+    "SPLIT_V", "UNIQUE", "CEIL", "REVERSE_V2", "ADD_N", "GATHER_ND", "COS", "WHERE", "RANK", "ELU",
+    "REVERSE_SEQUENCE", "MATRIX_DIAG", "QUANTIZE", "MATRIX_SET_DIAG", "ROUND", "HARD_SWISH", "IF",
+    "WHILE", "NON_MAX_SUPPRESSION_V4", "NON_MAX_SUPPRESSION_V5", "SCATTER_ND", "SELECT_V2",
+    "DENSIFY", "SEGMENT_SUM", "BATCH_MATMUL", "PLACEHOLDER_FOR_GREATER_OP_CODES", "CUMSUM",
+    "CALL_ONCE", "BROADCAST_TO", "RFFT2D", "CONV_3D", "IMAG", "REAL", "COMPLEX_ABS", "HASHTABLE",
+    "HASHTABLE_FIND", "HASHTABLE_IMPORT", "HASHTABLE_SIZE", "REDUCE_ALL", "CONV_3D_TRANSPOSE",
+    "VAR_HANDLE", "READ_VARIABLE", "ASSIGN_VARIABLE", "BROADCAST_ARGS", "RANDOM_STANDARD_NORMAL",
+    "BUCKETIZE", "RANDOM_UNIFORM", "MULTINOMIAL", "GELU", "DYNAMIC_UPDATE_SLICE", "RELU_0_TO_1",
+    "UNSORTED_SEGMENT_PROD", "UNSORTED_SEGMENT_MAX", "UNSORTED_SEGMENT_SUM", "ATAN2",
+    "UNSORTED_SEGMENT_MIN", "SIGN", "BITCAST", "BITWISE_XOR", "RIGHT_SHIFT", "STABLEHLO_LOGISTIC",
+    "STABLEHLO_ADD", "STABLEHLO_DIVIDE", "STABLEHLO_MULTIPLY", "STABLEHLO_MAXIMUM",
+    "STABLEHLO_RESHAPE", "STABLEHLO_CLAMP", "STABLEHLO_CONCATENATE", "STABLEHLO_BROADCAST_IN_DIM",
+    "STABLEHLO_CONVOLUTION", "STABLEHLO_SLICE", "STABLEHLO_CUSTOM_CALL", "STABLEHLO_REDUCE",
+    "STABLEHLO_ABS", "STABLEHLO_AND", "STABLEHLO_COSINE", "STABLEHLO_EXPONENTIAL",
+    "STABLEHLO_FLOOR", "STABLEHLO_LOG", "STABLEHLO_MINIMUM", "STABLEHLO_NEGATE", "STABLEHLO_OR",
+    "STABLEHLO_POWER", "STABLEHLO_REMAINDER", "STABLEHLO_RSQRT", "STABLEHLO_SELECT",
+    "STABLEHLO_SUBTRACT", "STABLEHLO_TANH", "STABLEHLO_SCATTER", "STABLEHLO_COMPARE",
+    "STABLEHLO_CONVERT", "STABLEHLO_DYNAMIC_SLICE", "STABLEHLO_DYNAMIC_UPDATE_SLICE",
+    "STABLEHLO_PAD", "STABLEHLO_IOTA", "STABLEHLO_DOT_GENERAL", "STABLEHLO_REDUCE_WINDOW",
+    "STABLEHLO_SORT", "STABLEHLO_WHILE", "STABLEHLO_GATHER", "STABLEHLO_TRANSPOSE", "DILATE",
+    "STABLEHLO_RNG_BIT_GENERATOR", "REDUCE_WINDOW", "STABLEHLO_COMPOSITE", "STABLEHLO_SHIFT_LEFT",
+    "STABLEHLO_CBRT", "STABLEHLO_CASE",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probed(index: usize, op: &str) -> ProbedNode {
+        ProbedNode {
+            index,
+            op: op.to_string(),
+            inputs: vec![index],
+            outputs: vec![index + 1],
+        }
+    }
+
+    #[test]
+    fn test_execution_plan_from_probes() {
+        let plans = vec![
+            vec![probed(0, "CONV_2D"), probed(1, "ADD"), probed(2, "TOPK_V2")],
+            vec![probed(3, "TfLiteXNNPackDelegate"), probed(2, "TOPK_V2")],
+            vec![probed(3, "TfLiteXNNPackDelegate"), probed(2, "TOPK_V2")],
+        ];
+        let names = vec!["XNNPACK".to_string(), "stub".to_string()];
+        let plan = ExecutionPlan::from_probes(&plans, &names);
+
+        assert_eq!(3, plan.total_nodes());
+        assert_eq!(2, plan.delegated_nodes());
+        assert_eq!(1, plan.cpu_nodes());
+        let backends = plan
+            .nodes()
+            .iter()
+            .map(|node| node.backend.clone())
+            .collect::<Vec<NodeBackend>>();
+        let xnnpack = NodeBackend::Delegate("XNNPACK".to_string());
+        assert_eq!(vec![xnnpack.clone(), xnnpack, NodeBackend::Cpu], backends);
+        assert_eq!("ADD", plan.nodes()[1].op);
+        assert_eq!(vec![1], plan.nodes()[1].inputs);
+        assert_eq!(
+            &[
+                DelegateSummary {
+                    name: "XNNPACK".to_string(),
+                    delegated_nodes: 2,
+                    partitions: 1,
+                },
+                DelegateSummary {
+                    name: "stub".to_string(),
+                    delegated_nodes: 0,
+                    partitions: 0,
+                },
+            ],
+            plan.delegates()
+        );
+        let table = plan.to_string();
+        assert!(table.contains("    2  TOPK_V2  CPU"));
+        assert!(table.ends_with("2 of 3 nodes delegated, 1 on CPU"));
+    }
+
+    #[cfg(feature = "xnnpack")]
+    #[test]
+    fn test_execution_plan_xnnpack() {
+        use crate::interpreter::tests::MODEL_PATH;
+        use crate::interpreter::{Interpreter, Options};
+        use crate::model::Model;
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let options = Options {
+            is_xnnpack_enabled: true,
+            ..Default::default()
+        };
+        let interpreter =
+            Interpreter::new(&model, Some(options)).expect("Cannot create interpreter!");
+        let plan = interpreter.execution_plan().expect("XNNPACK is applied");
+        assert!(plan.total_nodes() > 0);
+        assert_eq!(0, plan.cpu_nodes());
+        assert!(plan.nodes().iter().all(|node| node.op == "ADD"));
+        assert_eq!(1, plan.delegates().len());
+        assert_eq!("XNNPACK", plan.delegates()[0].name);
+        assert_eq!(1, plan.delegates()[0].partitions);
+
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        assert!(interpreter.execution_plan().is_none());
+    }
+}
@@ -1,11 +1,27 @@
 //! API of TensorFlow Lite [`Interpreter`] that performs inference.
-use std::ffi::c_void;
-use std::os::raw::c_int;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::ffi::{c_void, CStr};
+use std::io::BufWriter;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::custom_op::{CustomOp, CustomOpRegistration};
+use crate::delegate::{Delegate, DelegateFailurePolicy, DelegateReport, DelegateStatus};
+use crate::execution_plan::{ExecutionPlan, PlanProbe, ProbedNode};
 use crate::minimal_bindings::*;
 use crate::model::Model;
+use crate::profiler::Profiler;
 use crate::tensor;
-use crate::tensor::Tensor;
+use crate::tensor::{AlignedBuffer, OwnedTensor, Tensor, TensorElement};
+#[cfg(feature = "xnnpack")]
+use crate::xnnpack::{
+    WeightCacheFile, WeightCacheReport, WeightsCache, XnnpackDelegate, XnnpackOptions,
+};
 use crate::{Error, ErrorKind, Result};
 use std::fmt::{Debug, Formatter};
 
@@ -35,11 +51,31 @@ pub struct Options {
     ///
     /// * Startup time and resize time may increase.
     /// * Baseline memory consumption may increase.
-    /// * Quantized models will not see any benefit unless features `xnnpack_qu8` or `xnnpack_qs8`
-    /// are enabled.
+    /// * Quantized models will not see any benefit if [`XnnpackOptions::qs8`] and
+    ///   [`XnnpackOptions::qu8`] are disabled, or if XNNPACK is built without features
+    ///   `xnnpack_qs8` and `xnnpack_qu8`.
     #[cfg(feature = "xnnpack")]
     #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
     pub is_xnnpack_enabled: bool,
+
+    /// Options of the XNNPACK delegate, which are used if
+    /// [`Options::is_xnnpack_enabled`] is `true`.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub xnnpack: XnnpackOptions,
+
+    /// Indicates whether a running invocation can be cancelled.
+    ///
+    /// Enabling this flag is required for [`Interpreter::cancel_handle()`] and
+    /// [`Interpreter::invoke_with_deadline()`]. The interpreter checks the cancellation
+    /// flag between operators, so a single long-running operator is not interrupted.
+    pub is_cancellation_enabled: bool,
+
+    /// Indicates whether the time spent in every node is recorded.
+    ///
+    /// Enabling this flag is required for [`Interpreter::profiler()`]. Recording adds
+    /// a small overhead to every operator, so only enable it while profiling.
+    pub is_profiling_enabled: bool,
 }
 
 impl Default for Options {
@@ -48,10 +84,985 @@ impl Default for Options {
             thread_count: -1,
             #[cfg(feature = "xnnpack")]
             is_xnnpack_enabled: false,
+            #[cfg(feature = "xnnpack")]
+            xnnpack: XnnpackOptions::default(),
+            is_cancellation_enabled: false,
+            is_profiling_enabled: false,
+        }
+    }
+}
+
+/// The interpreter pointer shared by [`CancelHandle`]s.
+///
+/// It is reset to null when the [`Interpreter`] is dropped, so that handles
+/// which outlive the interpreter never touch a deleted instance.
+struct CancelTarget {
+    interpreter_ptr: Mutex<*const TfLiteInterpreter>,
+}
+
+unsafe impl Send for CancelTarget {}
+unsafe impl Sync for CancelTarget {}
+
+/// A cloneable handle that cancels the running invocation of an [`Interpreter`].
+///
+/// Handles can be sent to and used from other threads. They are obtained with
+/// [`Interpreter::cancel_handle()`].
+#[derive(Clone)]
+pub struct CancelHandle {
+    target: Arc<CancelTarget>,
+}
+
+impl Debug for CancelHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancelHandle").finish()
+    }
+}
+
+impl CancelHandle {
+    /// Requests cancellation of the ongoing [`Interpreter::invoke()`] call.
+    ///
+    /// The cancelled invocation returns [`ErrorKind::Cancelled`]. A request made while
+    /// the interpreter is idle has no effect on the next invocation.
+    ///
+    /// returns: `false` if the interpreter has already been dropped, otherwise `true`.
+    pub fn cancel(&self) -> bool {
+        let interpreter_ptr = self.target.interpreter_ptr.lock().unwrap();
+        if interpreter_ptr.is_null() {
+            return false;
+        }
+        unsafe { TfLiteInterpreterCancel(*interpreter_ptr) };
+        true
+    }
+}
+
+/// The values of the variable tensors of an [`Interpreter`], such as the hidden state of
+/// a recurrent model.
+///
+/// States are taken with [`Interpreter::snapshot_state()`] and written back with
+/// [`Interpreter::restore_state()`], so that one interpreter can process several streams
+/// by swapping their states between invocations.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct InterpreterState {
+    /// The index and data of every variable tensor.
+    tensors: Vec<(usize, Vec<u8>)>,
+}
+
+impl InterpreterState {
+    /// Returns the number of variable tensors in the state.
+    pub fn tensor_count(&self) -> usize {
+        self.tensors.len()
+    }
+
+    /// Returns the total number of bytes of the variable tensors.
+    pub fn byte_size(&self) -> usize {
+        self.tensors.iter().map(|(_, data)| data.len()).sum()
+    }
+}
+
+/// A callback receiving error messages reported by TensorFlow Lite.
+pub type ErrorReporter = dyn Fn(&str) + Send;
+
+/// The set of operators an [`Interpreter`] can resolve while loading a model.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Default)]
+pub enum OpResolver {
+    /// All builtin operators of TensorFlow Lite.
+    #[default]
+    Builtin,
+    /// Only the custom operators registered with [`InterpreterBuilder::add_custom_op()`].
+    ///
+    /// Models containing any other operator fail to load.
+    Selected,
+}
+
+/// A delegate entry of an [`InterpreterBuilder`], kept in the order of application.
+enum DelegateEntry<'a> {
+    /// The XNNPACK delegate created from [`Options`].
+    #[cfg(feature = "xnnpack")]
+    Xnnpack,
+    /// A delegate owned by the interpreter.
+    Owned(Box<dyn Delegate + 'a>),
+    /// A delegate owned by the caller.
+    Raw(*mut TfLiteDelegate),
+}
+
+impl Debug for DelegateEntry<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "xnnpack")]
+            DelegateEntry::Xnnpack => f.write_str("Xnnpack"),
+            DelegateEntry::Owned(delegate) => {
+                f.debug_tuple("Owned").field(&delegate.as_ptr()).finish()
+            }
+            DelegateEntry::Raw(delegate_ptr) => f.debug_tuple("Raw").field(delegate_ptr).finish(),
         }
     }
 }
 
+/// A builder which configures every option of an [`Interpreter`].
+///
+/// Options are validated when [`InterpreterBuilder::build()`] is called, so
+/// conflicting combinations are reported instead of being ignored silently.
+///
+/// # Examples
+///
+/// ```
+/// use tflitec::interpreter::InterpreterBuilder;
+/// use tflitec::model::Model;
+/// let model = Model::new("tests/add.bin")?;
+/// let interpreter = InterpreterBuilder::new(&model)
+///     .thread_count(2)
+///     .cancellation(true)
+///     .error_reporter(|message| eprintln!("TensorFlow Lite: {}", message))
+///     .build()?;
+/// assert!(interpreter.cancel_handle().is_some());
+/// # Ok::<(), tflitec::Error>(())
+/// ```
+pub struct InterpreterBuilder<'a> {
+    model: &'a Model<'a>,
+    options: Option<Options>,
+    error_reporter: Option<Box<ErrorReporter>>,
+    delegates: Vec<DelegateEntry<'a>>,
+    is_tensor_preservation_enabled: bool,
+    is_execution_plan_recorded: bool,
+    large_tensors_threshold: Option<usize>,
+    op_resolver: OpResolver,
+    delegate_failure_policy: DelegateFailurePolicy,
+    custom_ops: Vec<CustomOpRegistration<'a>>,
+    #[cfg(feature = "xnnpack")]
+    xnnpack_weight_cache_file: Option<WeightCacheFile>,
+    #[cfg(feature = "xnnpack")]
+    xnnpack_weights_cache: Option<WeightsCache>,
+}
+
+/// A configuration of the [`InterpreterBuilder`] of the interpreters run by a type, e.g.
+/// an [`InterpreterPool`][crate::pool::InterpreterPool].
+pub(crate) type Configuration<'a> = dyn Fn(InterpreterBuilder<'a>) -> InterpreterBuilder<'a> + 'a;
+
+/// A [`Configuration`] for models of any lifetime, which worker threads can share.
+pub(crate) type SharedConfiguration =
+    dyn for<'m> Fn(InterpreterBuilder<'m>) -> InterpreterBuilder<'m> + Send + Sync;
+
+impl Debug for InterpreterBuilder<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("InterpreterBuilder");
+        #[cfg(feature = "xnnpack")]
+        debug
+            .field("xnnpack_weight_cache_file", &self.xnnpack_weight_cache_file)
+            .field("xnnpack_weights_cache", &self.xnnpack_weights_cache);
+        debug
+            .field("options", &self.options)
+            .field("has_error_reporter", &self.error_reporter.is_some())
+            .field("delegates", &self.delegates)
+            .field(
+                "is_tensor_preservation_enabled",
+                &self.is_tensor_preservation_enabled,
+            )
+            .field(
+                "is_execution_plan_recorded",
+                &self.is_execution_plan_recorded,
+            )
+            .field("large_tensors_threshold", &self.large_tensors_threshold)
+            .field("op_resolver", &self.op_resolver)
+            .field("delegate_failure_policy", &self.delegate_failure_policy)
+            .field("custom_ops", &self.custom_ops)
+            .finish()
+    }
+}
+
+impl<'a> InterpreterBuilder<'a> {
+    /// Creates a new builder for an [`Interpreter`] of the given `model`.
+    ///
+    /// # Arguments
+    ///
+    /// * `model`: TensorFlow Lite [model][`Model`]
+    pub fn new(model: &'a Model<'a>) -> InterpreterBuilder<'a> {
+        InterpreterBuilder {
+            model,
+            options: None,
+            error_reporter: None,
+            delegates: Vec::new(),
+            is_tensor_preservation_enabled: false,
+            is_execution_plan_recorded: false,
+            large_tensors_threshold: None,
+            op_resolver: OpResolver::default(),
+            delegate_failure_policy: DelegateFailurePolicy::default(),
+            custom_ops: Vec::new(),
+            #[cfg(feature = "xnnpack")]
+            xnnpack_weight_cache_file: None,
+            #[cfg(feature = "xnnpack")]
+            xnnpack_weights_cache: None,
+        }
+    }
+
+    /// Replaces the [`Options`] of the builder.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Sets [`Options::thread_count`].
+    pub fn thread_count(mut self, thread_count: i32) -> Self {
+        self.options_mut().thread_count = thread_count;
+        self
+    }
+
+    /// Sets [`Options::is_cancellation_enabled`].
+    pub fn cancellation(mut self, enabled: bool) -> Self {
+        self.options_mut().is_cancellation_enabled = enabled;
+        self
+    }
+
+    /// Sets [`Options::is_profiling_enabled`].
+    pub fn profiling(mut self, enabled: bool) -> Self {
+        self.options_mut().is_profiling_enabled = enabled;
+        self
+    }
+
+    /// Sets the callback receiving error messages of TensorFlow Lite.
+    ///
+    /// The reporter is used for the whole lifetime of the [`Interpreter`].
+    /// By default, errors are logged with [`log::error!`].
+    pub fn error_reporter<F>(mut self, reporter: F) -> Self
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.error_reporter = Some(Box::new(reporter));
+        self
+    }
+
+    /// Appends the XNNPACK delegate to the delegate list and enables
+    /// [`Options::is_xnnpack_enabled`].
+    ///
+    /// Delegates are applied in the order they are added. If XNNPACK is only enabled
+    /// through [`Options`], it is applied before every other delegate.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn add_xnnpack_delegate(mut self) -> Self {
+        self.options_mut().is_xnnpack_enabled = true;
+        self.delegates.push(DelegateEntry::Xnnpack);
+        self
+    }
+
+    /// Sets [`Options::xnnpack`].
+    ///
+    /// The options are only used if the XNNPACK delegate is enabled.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn xnnpack_options(mut self, xnnpack_options: XnnpackOptions) -> Self {
+        self.options_mut().xnnpack = xnnpack_options;
+        self
+    }
+
+    /// Sets the persistent weight cache file of the XNNPACK delegate.
+    ///
+    /// The file is only used if the XNNPACK delegate is enabled, and it cannot be combined
+    /// with [`InterpreterBuilder::xnnpack_weights_cache()`].
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn xnnpack_weight_cache_file(mut self, weight_cache_file: WeightCacheFile) -> Self {
+        self.xnnpack_weight_cache_file = Some(weight_cache_file);
+        self
+    }
+
+    /// Sets the weights cache shared with other interpreters of the same model.
+    ///
+    /// The cache is only used if the XNNPACK delegate is enabled, and it cannot be combined
+    /// with [`InterpreterBuilder::xnnpack_weight_cache_file()`].
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn xnnpack_weights_cache(mut self, weights_cache: WeightsCache) -> Self {
+        self.xnnpack_weights_cache = Some(weights_cache);
+        self
+    }
+
+    /// Appends a [`Delegate`] to the delegate list.
+    ///
+    /// Delegates are applied in the order they are added. The built [`Interpreter`]
+    /// owns the delegate and drops it after the underlying interpreter is deleted.
+    pub fn add_delegate<D: Delegate + 'a>(mut self, delegate: D) -> Self {
+        self.delegates
+            .push(DelegateEntry::Owned(Box::new(delegate)));
+        self
+    }
+
+    /// Appends a delegate owned by the caller to the delegate list.
+    ///
+    /// Delegates are applied in the order they are added.
+    ///
+    /// # Safety
+    ///
+    /// `delegate` must point to a valid [`TfLiteDelegate`] which outlives the
+    /// built [`Interpreter`], and it must not be used by another interpreter concurrently.
+    pub unsafe fn add_delegate_raw(mut self, delegate: *mut TfLiteDelegate) -> Self {
+        self.delegates.push(DelegateEntry::Raw(delegate));
+        self
+    }
+
+    /// Keeps the values of all tensors after an invocation, so intermediate tensors can be
+    /// read with [`Interpreter::tensor()`] or written with [`Interpreter::dump_tensors()`].
+    ///
+    /// Every tensor gets its own buffer instead of sharing the arena with tensors which
+    /// are not used at the same time, which increases memory consumption. Tensors computed
+    /// inside a delegate partition are not written, so disable delegates while inspecting.
+    pub fn preserve_all_tensors(mut self, enabled: bool) -> Self {
+        self.is_tensor_preservation_enabled = enabled;
+        self
+    }
+
+    /// Records the [`ExecutionPlan`] even if no delegate is added.
+    pub(crate) fn record_execution_plan(mut self) -> Self {
+        self.is_execution_plan_recorded = true;
+        self
+    }
+
+    /// Allocates tensors larger than `threshold_bytes` dynamically instead of
+    /// planning them in the arena.
+    ///
+    /// This reduces peak memory consumption of models with a few large intermediate
+    /// tensors at the cost of extra allocations during invocation.
+    pub fn dynamic_allocation_for_large_tensors(mut self, threshold_bytes: usize) -> Self {
+        self.large_tensors_threshold = Some(threshold_bytes);
+        self
+    }
+
+    /// Sets the [`DelegateFailurePolicy`] applied when a delegate cannot be created, cannot
+    /// be applied to the graph or fails an invocation.
+    /// The default is [`DelegateFailurePolicy::RetryWithoutDelegate`].
+    pub fn delegate_failure_policy(mut self, policy: DelegateFailurePolicy) -> Self {
+        self.delegate_failure_policy = policy;
+        self
+    }
+
+    /// Sets the [`OpResolver`] used to resolve operators of the model.
+    pub fn op_resolver(mut self, op_resolver: OpResolver) -> Self {
+        self.op_resolver = op_resolver;
+        self
+    }
+
+    /// Registers a [`CustomOp`] for the nodes of the model with the custom code `name`
+    /// and version 1.
+    ///
+    /// The built [`Interpreter`] owns the operator and drops it after the underlying
+    /// interpreter is deleted.
+    pub fn add_custom_op<O: CustomOp + 'a>(mut self, name: &str, op: O) -> Self {
+        self.custom_ops.push(CustomOpRegistration::new(name, op));
+        self
+    }
+
+    fn options_mut(&mut self) -> &mut Options {
+        self.options.get_or_insert_with(Options::default)
+    }
+
+    /// Returns the number of XNNPACK delegates added explicitly.
+    #[cfg(feature = "xnnpack")]
+    fn xnnpack_delegate_count(&self) -> usize {
+        self.delegates
+            .iter()
+            .filter(|delegate| matches!(delegate, DelegateEntry::Xnnpack))
+            .count()
+    }
+
+    /// Inserts the XNNPACK delegate enabled only through [`Options`] before every
+    /// other delegate, so the delegate list is in the order of application.
+    fn order_delegates(&mut self) {
+        #[cfg(feature = "xnnpack")]
+        {
+            let is_xnnpack_enabled = self
+                .options
+                .as_ref()
+                .map(|s| s.is_xnnpack_enabled)
+                .unwrap_or(false);
+            if is_xnnpack_enabled && self.xnnpack_delegate_count() == 0 {
+                self.delegates.insert(0, DelegateEntry::Xnnpack);
+            }
+        }
+    }
+
+    /// Checks that the configured options are consistent.
+    fn validate(&self) -> Result<()> {
+        let invalid = |reason| Err(Error::new(ErrorKind::InvalidOptions(reason)));
+        if let Some(options) = self.options.as_ref() {
+            if options.thread_count < -1 {
+                return invalid("thread_count must be greater than or equal to -1");
+            }
+        }
+        #[cfg(feature = "xnnpack")]
+        {
+            let xnnpack_count = self.xnnpack_delegate_count();
+            if xnnpack_count > 1 {
+                return invalid("the XNNPACK delegate is added more than once");
+            }
+            let is_xnnpack_enabled = self
+                .options
+                .as_ref()
+                .map(|s| s.is_xnnpack_enabled)
+                .unwrap_or(false);
+            if xnnpack_count == 1 && !is_xnnpack_enabled {
+                return invalid("the XNNPACK delegate is added but disabled in options");
+            }
+            let xnnpack_thread_count = self.options.as_ref().and_then(|s| s.xnnpack.thread_count);
+            if xnnpack_thread_count
+                .map(|count| count < -1)
+                .unwrap_or(false)
+            {
+                return invalid("XNNPACK thread_count must be greater than or equal to -1");
+            }
+            if self.xnnpack_weights_cache.is_some() && self.xnnpack_weight_cache_file.is_some() {
+                return invalid(
+                    "XNNPACK weights_cache and weight_cache_file are mutually exclusive",
+                );
+            }
+        }
+        let has_null_delegate = self.delegates.iter().any(|delegate| match delegate {
+            DelegateEntry::Owned(delegate) => delegate.as_ptr().is_null(),
+            DelegateEntry::Raw(delegate_ptr) => delegate_ptr.is_null(),
+            #[cfg(feature = "xnnpack")]
+            DelegateEntry::Xnnpack => false,
+        });
+        if has_null_delegate {
+            return invalid("a delegate pointer is null");
+        }
+        if self.delegate_failure_policy == DelegateFailurePolicy::FallBackToCpu
+            && self.delegates.is_empty()
+        {
+            return invalid("falling back to CPU kernels requires at least one delegate");
+        }
+        if let Some(threshold) = self.large_tensors_threshold {
+            if threshold == 0 || threshold > c_int::MAX as usize {
+                return invalid("the large tensor threshold must be in [1, i32::MAX] bytes");
+            }
+        }
+        if self.custom_ops.iter().any(|op| !op.has_valid_name()) {
+            return invalid("a custom operator name contains a nul byte");
+        }
+        if self.op_resolver == OpResolver::Selected && self.custom_ops.is_empty() {
+            return invalid("the selected op resolver requires registered operators");
+        }
+        Ok(())
+    }
+
+    /// Creates the [`Interpreter`].
+    ///
+    /// Delegates which cannot be created or applied are handled according to the
+    /// [`DelegateFailurePolicy`], see [`Interpreter::delegate_status()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidOptions`] if the configured options conflict,
+    /// [`ErrorKind::FailedToRegisterCustomOp`] if a custom operator cannot be created,
+    /// [`ErrorKind::FailedToCreateDelegate`] if a delegate cannot be created with
+    /// [`DelegateFailurePolicy::Fail`] or error if TensorFlow Lite C fails internally.
+    pub fn build(mut self) -> Result<Interpreter<'a>> {
+        self.order_delegates();
+        self.validate()?;
+        if self.custom_ops.iter().any(|op| op.as_ptr().is_null()) {
+            return Err(Error::new(ErrorKind::FailedToRegisterCustomOp));
+        }
+        let delegate_entries = std::mem::take(&mut self.delegates);
+        let error_reporter = Box::new(ErrorReporterState {
+            reporter: self.error_reporter.take(),
+            last_message: Mutex::new(None),
+            is_muted: AtomicBool::new(false),
+        });
+        let options = self.options.as_ref();
+        let policy = self.delegate_failure_policy;
+        let profiler = options
+            .filter(|s| s.is_profiling_enabled)
+            .map(|_| Profiler::new());
+
+        #[cfg(feature = "xnnpack")]
+        let mut xnnpack_weight_cache_report = None;
+        // Packing weights into a shared cache must not happen concurrently, so the cache
+        // is locked until the interpreter has been created.
+        #[cfg(feature = "xnnpack")]
+        let xnnpack_weights_cache = self
+            .xnnpack_weights_cache
+            .take()
+            .filter(|_| options.map(|s| s.is_xnnpack_enabled).unwrap_or(false));
+        #[cfg(feature = "xnnpack")]
+        let weights_cache_guard = xnnpack_weights_cache.as_ref().map(|cache| cache.lock());
+
+        let mut slots = Vec::new();
+        for delegate in delegate_entries {
+            match delegate {
+                #[cfg(feature = "xnnpack")]
+                DelegateEntry::Xnnpack => {
+                    let options = options.expect("XNNPACK is enabled through options");
+                    let mut weight_cache_path = None;
+                    if let Some(cache_file) = self.xnnpack_weight_cache_file.as_ref() {
+                        let report = cache_file.prepare(self.model)?;
+                        weight_cache_path = Some(report.c_path()?);
+                        xnnpack_weight_cache_report = Some(report);
+                    }
+                    let weights_cache_ptr = weights_cache_guard
+                        .as_ref()
+                        .map(|cache_ptr| **cache_ptr)
+                        .unwrap_or(std::ptr::null_mut());
+                    let xnnpack_delegate = XnnpackDelegate::create(
+                        options,
+                        weight_cache_path,
+                        xnnpack_weights_cache.clone(),
+                        weights_cache_ptr,
+                    );
+                    match xnnpack_delegate {
+                        Some(xnnpack_delegate) => {
+                            slots.push(DelegateSlot::owned(Box::new(xnnpack_delegate)))
+                        }
+                        None if policy == DelegateFailurePolicy::Fail => {
+                            return Err(Error::new(ErrorKind::FailedToCreateDelegate));
+                        }
+                        None => {
+                            let mut slot = DelegateSlot::named("XNNPACK", std::ptr::null_mut());
+                            slot.reject("failed to create the XNNPACK delegate".to_string());
+                            slots.push(slot);
+                        }
+                    }
+                }
+                DelegateEntry::Owned(delegate) => slots.push(DelegateSlot::owned(delegate)),
+                DelegateEntry::Raw(delegate_ptr) => {
+                    slots.push(DelegateSlot::named("raw delegate", delegate_ptr))
+                }
+            }
+        }
+        // A probe is applied before every delegate and after the last one.
+        let probes = if slots.is_empty() && !self.is_execution_plan_recorded {
+            Vec::new()
+        } else {
+            (0..=slots.len())
+                .map(|_| PlanProbe::new())
+                .collect::<Option<Vec<PlanProbe>>>()
+                .ok_or_else(|| Error::new(ErrorKind::FailedToCreateInterpreter))?
+        };
+
+        let (interpreter_ptr, applied) = loop {
+            let applied = (0..slots.len())
+                .filter(|index| slots[*index].status.is_none())
+                .collect::<Vec<usize>>();
+            let mut delegate_ptrs = Vec::new();
+            if let Some(probe) = probes.first() {
+                delegate_ptrs.push(probe.as_ptr());
+            }
+            for (position, index) in applied.iter().enumerate() {
+                delegate_ptrs.push(slots[*index].delegate_ptr);
+                delegate_ptrs.push(probes[position + 1].as_ptr());
+            }
+            probes.iter().for_each(PlanProbe::reset);
+            *error_reporter.last_message.lock().unwrap() = None;
+
+            let interpreter_ptr = unsafe {
+                self.create_interpreter(&delegate_ptrs, &error_reporter, profiler.as_ref())?
+            };
+            if !interpreter_ptr.is_null() {
+                break (interpreter_ptr, applied);
+            }
+            // The delegate whose application failed is followed by the first probe
+            // which was not applied.
+            let failed = (0..applied.len())
+                .find(|position| {
+                    probes[*position].plan().is_some() && probes[*position + 1].plan().is_none()
+                })
+                .map(|position| applied[position]);
+            match failed {
+                Some(index) if policy != DelegateFailurePolicy::Fail => {
+                    let reason = error_reporter
+                        .last_message
+                        .lock()
+                        .unwrap()
+                        .take()
+                        .unwrap_or_else(|| "failed to apply the delegate".to_string());
+                    slots[index].reject(reason);
+                }
+                _ => return Err(Error::new(ErrorKind::FailedToCreateInterpreter)),
+            }
+        };
+        #[cfg(feature = "xnnpack")]
+        drop(weights_cache_guard);
+
+        let execution_plan = if probes.is_empty() {
+            None
+        } else {
+            let plans = probes
+                .iter()
+                .map(|probe| probe.plan().unwrap_or_default())
+                .collect::<Vec<Vec<ProbedNode>>>();
+            let names = applied
+                .iter()
+                .map(|index| slots[*index].name.clone())
+                .collect::<Vec<String>>();
+            Some(ExecutionPlan::from_probes(&plans, &names))
+        };
+        for (position, index) in applied.iter().enumerate() {
+            let plan = execution_plan
+                .as_ref()
+                .expect("probes are applied with delegates");
+            let delegated_nodes = plan.delegates()[position].delegated_nodes;
+            let total_nodes = plan.total_nodes();
+            let status = if delegated_nodes == 0 {
+                DelegateStatus::NotApplied
+            } else if delegated_nodes == total_nodes {
+                DelegateStatus::Applied
+            } else {
+                DelegateStatus::PartiallyApplied {
+                    delegated_nodes,
+                    total_nodes,
+                }
+            };
+            log::debug!("{} delegate: {:?}", slots[*index].name, status);
+            slots[*index].status = Some(status);
+        }
+
+        let mut delegate_reports = Vec::with_capacity(slots.len());
+        let mut delegates: Vec<Box<dyn Delegate + 'a>> = Vec::new();
+        for slot in slots {
+            let status = slot.status.expect("every delegate has a status");
+            // Rejected delegates are not used by the interpreter, so they are dropped now.
+            if let Some(delegate) = slot.owned {
+                if !matches!(status, DelegateStatus::Rejected { .. }) {
+                    delegates.push(delegate);
+                }
+            }
+            delegate_reports.push(DelegateReport {
+                name: slot.name,
+                status,
+            });
+        }
+        delegates.extend(
+            probes
+                .into_iter()
+                .map(|probe| Box::new(probe) as Box<dyn Delegate>),
+        );
+
+        let is_cancellation_enabled = options.map(|s| s.is_cancellation_enabled).unwrap_or(false);
+        let cancel_target = if is_cancellation_enabled {
+            Some(Arc::new(CancelTarget {
+                interpreter_ptr: Mutex::new(interpreter_ptr),
+            }))
+        } else {
+            None
+        };
+        let tensor_count = unsafe { count_tensors(interpreter_ptr) };
+        let variable_tensors = (0..tensor_count)
+            .filter(|index| unsafe {
+                let tensor_ptr = TfLiteInterpreterGetTensor(interpreter_ptr, *index as c_int);
+                // Tensors of the interpreter are the opaque tensors of the opaque API.
+                TfLiteOpaqueTensorIsVariable(tensor_ptr as *const TfLiteOpaqueTensor) != 0
+            })
+            .collect();
+        let preserved_tensors = if self.is_tensor_preservation_enabled {
+            Some(PreservedTensors::default())
+        } else {
+            None
+        };
+        Ok(Interpreter {
+            options: self.options,
+            interpreter_ptr,
+            delegates,
+            delegate_reports,
+            execution_plan,
+            #[cfg(feature = "xnnpack")]
+            xnnpack_weight_cache_report,
+            #[cfg(feature = "xnnpack")]
+            xnnpack_weights_cache,
+            cancel_target,
+            error_reporter,
+            custom_ops: self.custom_ops,
+            profiler,
+            tensor_count,
+            variable_tensors,
+            preserved_tensors,
+            model: self.model,
+        })
+    }
+
+    /// Builds the [`Interpreter`], resizes the inputs at the given indices to their shapes
+    /// and allocates its tensors.
+    pub(crate) fn build_allocated(
+        self,
+        input_shapes: &[(usize, tensor::Shape)],
+    ) -> Result<Interpreter<'a>> {
+        let interpreter = self.build()?;
+        for (index, shape) in input_shapes {
+            interpreter.resize_input(*index, shape.clone())?;
+        }
+        interpreter.allocate_tensors()?;
+        Ok(interpreter)
+    }
+
+    /// Creates the underlying interpreter with the given delegates applied in order.
+    ///
+    /// returns: A null pointer if TensorFlow Lite C fails to create the interpreter.
+    unsafe fn create_interpreter(
+        &self,
+        delegate_ptrs: &[*mut TfLiteDelegate],
+        error_reporter: &ErrorReporterState,
+        profiler: Option<&Profiler>,
+    ) -> Result<*mut TfLiteInterpreter> {
+        let options_ptr = TfLiteInterpreterOptionsCreate();
+        if options_ptr.is_null() {
+            return Err(Error::new(ErrorKind::FailedToCreateInterpreter));
+        }
+        if let Some(thread_count) = self.options.as_ref().map(|s| s.thread_count) {
+            TfLiteInterpreterOptionsSetNumThreads(options_ptr, thread_count);
+        }
+        let is_cancellation_enabled = self
+            .options
+            .as_ref()
+            .map(|s| s.is_cancellation_enabled)
+            .unwrap_or(false);
+        if is_cancellation_enabled
+            && TfLiteInterpreterOptionsEnableCancellation(options_ptr, true).is_error()
+        {
+            TfLiteInterpreterOptionsDelete(options_ptr);
+            return Err(Error::new(ErrorKind::FailedToCreateInterpreter));
+        }
+        let user_data = error_reporter as *const ErrorReporterState as *mut c_void;
+        TfLiteInterpreterOptionsSetErrorReporter(options_ptr, Some(report_error), user_data);
+        if let Some(profiler) = profiler {
+            TfLiteInterpreterOptionsSetTelemetryProfiler(options_ptr, profiler.as_ptr());
+        }
+
+        for custom_op in &self.custom_ops {
+            TfLiteInterpreterOptionsAddOperator(options_ptr, custom_op.as_ptr());
+        }
+        for delegate_ptr in delegate_ptrs {
+            TfLiteInterpreterOptionsAddDelegate(options_ptr, *delegate_ptr);
+        }
+        if self.delegate_failure_policy == DelegateFailurePolicy::FallBackToCpu {
+            TfLiteInterpreterOptionsSetEnableDelegateFallback(options_ptr, true);
+        }
+        if let Some(threshold) = self.large_tensors_threshold {
+            TfLiteInterpreterOptionsOptimizeMemoryForLargeTensors(options_ptr, threshold as c_int);
+        }
+
+        let model_ptr = self.model.model_ptr as *const TfLiteModel;
+        let interpreter_ptr = match self.op_resolver {
+            OpResolver::Builtin => TfLiteInterpreterCreate(model_ptr, options_ptr),
+            OpResolver::Selected => TfLiteInterpreterCreateWithSelectedOps(model_ptr, options_ptr),
+        };
+        TfLiteInterpreterOptionsDelete(options_ptr);
+        Ok(interpreter_ptr)
+    }
+}
+
+/// A delegate of an [`Interpreter`] being built.
+struct DelegateSlot<'a> {
+    name: String,
+    delegate_ptr: *mut TfLiteDelegate,
+    /// The delegate if it is owned by the interpreter.
+    owned: Option<Box<dyn Delegate + 'a>>,
+    /// The status, which is only known early if the delegate is rejected.
+    status: Option<DelegateStatus>,
+}
+
+impl<'a> DelegateSlot<'a> {
+    fn owned(delegate: Box<dyn Delegate + 'a>) -> DelegateSlot<'a> {
+        DelegateSlot {
+            name: delegate.name().to_string(),
+            delegate_ptr: delegate.as_ptr(),
+            owned: Some(delegate),
+            status: None,
+        }
+    }
+
+    fn named(name: &str, delegate_ptr: *mut TfLiteDelegate) -> DelegateSlot<'a> {
+        DelegateSlot {
+            name: name.to_string(),
+            delegate_ptr,
+            owned: None,
+            status: None,
+        }
+    }
+
+    fn reject(&mut self, reason: String) {
+        log::warn!(
+            "{} delegate rejected, its nodes fall back to CPU kernels: {}",
+            self.name,
+            reason
+        );
+        self.status = Some(DelegateStatus::Rejected { reason });
+    }
+}
+
+/// The user data of the error reporter passed to TensorFlow Lite C.
+struct ErrorReporterState {
+    /// The reporter of the user. Messages are logged as errors without one.
+    reporter: Option<Box<ErrorReporter>>,
+    /// The last reported message, used as the reason of rejected delegates.
+    last_message: Mutex<Option<String>>,
+    /// Whether messages are dropped, while errors are expected.
+    is_muted: AtomicBool,
+}
+
+/// Returns the number of tensors of the primary subgraph of `interpreter_ptr`.
+unsafe fn count_tensors(interpreter_ptr: *const TfLiteInterpreter) -> usize {
+    let exists =
+        |index: usize| !TfLiteInterpreterGetTensor(interpreter_ptr, index as c_int).is_null();
+    // TensorFlow Lite C returns null for indices out of range, so the count is found
+    // with an exponential search followed by a binary search.
+    let mut end = 1;
+    while end <= c_int::MAX as usize && exists(end - 1) {
+        end *= 2;
+    }
+    let (mut low, mut high) = (end / 2, end.min(c_int::MAX as usize + 1));
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if exists(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// The buffers given to the tensors of an interpreter, so that tensors do not share
+/// the arena and keep their values after an invocation.
+#[derive(Default)]
+struct PreservedTensors {
+    /// The buffers by tensor index.
+    buffers: Mutex<HashMap<usize, AlignedBuffer>>,
+    /// The indices of tensors which are not in the arena, e.g. constants.
+    rejected: Mutex<HashSet<usize>>,
+}
+
+impl PreservedTensors {
+    /// Allocates the tensors of `interpreter_ptr`, giving a buffer to every tensor of the
+    /// arena which does not have a large enough one yet.
+    unsafe fn allocate(
+        &self,
+        interpreter_ptr: *mut TfLiteInterpreter,
+        tensor_count: usize,
+        error_reporter: &ErrorReporterState,
+    ) -> TfLiteStatus {
+        let mut buffers = self.buffers.lock().unwrap();
+        let mut rejected = self.rejected.lock().unwrap();
+        // Tensors which are not in the arena reject buffers, and buffers which are too small
+        // for resized tensors fail the allocation after the tensor sizes are updated, so only
+        // errors of the last allocation are reported.
+        error_reporter.is_muted.store(true, Ordering::Relaxed);
+        let status = TfLiteInterpreterAllocateTensors(interpreter_ptr);
+        let mut is_changed = false;
+        for index in 0..tensor_count {
+            if rejected.contains(&index) {
+                continue;
+            }
+            let tensor_ptr = TfLiteInterpreterGetTensor(interpreter_ptr, index as c_int);
+            let bytes = TfLiteTensorByteSize(tensor_ptr);
+            let is_large_enough = buffers
+                .get(&index)
+                .map(|buffer| buffer.layout.size() >= bytes)
+                .unwrap_or(false);
+            if bytes == 0 || is_large_enough {
+                continue;
+            }
+            let buffer = match AlignedBuffer::new(bytes) {
+                Some(buffer) => buffer,
+                None => continue,
+            };
+            let allocation = TfLiteCustomAllocation {
+                data: buffer.data.as_ptr() as *mut c_void,
+                bytes,
+            };
+            let status = TfLiteInterpreterSetCustomAllocationForTensor(
+                interpreter_ptr,
+                index as c_int,
+                &allocation,
+                0,
+            );
+            if status.is_error() {
+                rejected.insert(index);
+            } else {
+                buffers.insert(index, buffer);
+                is_changed = true;
+            }
+        }
+        error_reporter.is_muted.store(false, Ordering::Relaxed);
+        if is_changed || status.is_error() {
+            TfLiteInterpreterAllocateTensors(interpreter_ptr)
+        } else {
+            status
+        }
+    }
+}
+
+/// Formats a message of TensorFlow Lite C and forwards it to an [`ErrorReporter`].
+unsafe extern "C" fn report_error(user_data: *mut c_void, format: *const c_char, args: VaListPtr) {
+    let state = &*(user_data as *const ErrorReporterState);
+    if state.is_muted.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut buffer = [0 as c_char; 1024];
+    if vsnprintf(buffer.as_mut_ptr(), buffer.len(), format, args) < 0 {
+        return;
+    }
+    let message = CStr::from_ptr(buffer.as_ptr()).to_string_lossy();
+    if let Ok(mut last_message) = state.last_message.lock() {
+        *last_message = Some(message.to_string());
+    }
+    // Unwinding into TensorFlow Lite C is not allowed.
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        match state.reporter.as_ref() {
+            Some(reporter) => reporter(&message),
+            None => log::error!("{}", message),
+        }
+    }));
+}
+
+/// Data which [`Interpreter::copy()`] copies to an input [`Tensor`], i.e. a slice, array or
+/// vector of elements, or an [`OwnedTensor`].
+pub trait InputData {
+    /// Copies the data to the input [`Tensor`] at the given `index` of `interpreter`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the data does not fit the input tensor, the given index is not
+    /// a valid input tensor index or TensorFlow Lite C fails internally.
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()>;
+}
+
+impl<T: TensorElement> InputData for [T] {
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(self.as_ptr() as *const u8, std::mem::size_of_val(self))
+        };
+        interpreter.copy_bytes(bytes, index)
+    }
+}
+
+impl<T: TensorElement, const N: usize> InputData for [T; N] {
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()> {
+        self[..].copy_to(interpreter, index)
+    }
+}
+
+impl<T: TensorElement> InputData for Vec<T> {
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()> {
+        self[..].copy_to(interpreter, index)
+    }
+}
+
+impl<D: InputData + ?Sized> InputData for &D {
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()> {
+        (**self).copy_to(interpreter, index)
+    }
+}
+
+/// The data type and shape of the tensor must match the input tensor. The buffer of a string
+/// tensor must also have the size of the input buffer, since the input buffer is not
+/// reallocated; copy strings of another size with [`Interpreter::copy_strings()`] instead.
+impl InputData for OwnedTensor {
+    fn copy_to(&self, interpreter: &Interpreter, index: usize) -> Result<()> {
+        let input = interpreter.input(index)?;
+        if self.data_type() != input.data_type() {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        if self.shape() != input.shape() {
+            return Err(Error::new(ErrorKind::ShapeMismatch(index)));
+        }
+        interpreter.copy_bytes(self.data::<u8>(), index)
+    }
+}
+
 /// A TensorFlow Lite interpreter that performs inference from a given model.
 ///
 /// - Note: Interpreter instances are *not* thread-safe.
@@ -62,9 +1073,56 @@ pub struct Interpreter<'a> {
     /// The underlying [`TfLiteInterpreter`] C pointer.
     interpreter_ptr: *mut TfLiteInterpreter,
 
-    /// The underlying [`TfLiteDelegate`] C pointer for XNNPACK delegate.
+    /// The [`Delegate`]s owned by the interpreter, in the order of application.
+    ///
+    /// They are dropped after the underlying interpreter is deleted.
+    #[allow(dead_code)]
+    delegates: Vec<Box<dyn Delegate + 'a>>,
+
+    /// The outcome of applying each delegate, in the order of application.
+    delegate_reports: Vec<DelegateReport>,
+
+    /// The nodes with their backends, recorded if any delegate was applied.
+    execution_plan: Option<ExecutionPlan>,
+
+    /// The report of the XNNPACK weight cache file.
     #[cfg(feature = "xnnpack")]
-    xnnpack_delegate_ptr: Option<*mut TfLiteDelegate>,
+    xnnpack_weight_cache_report: Option<WeightCacheReport>,
+
+    /// The XNNPACK weights cache shared with other interpreters.
+    #[cfg(feature = "xnnpack")]
+    xnnpack_weights_cache: Option<WeightsCache>,
+
+    /// The target of [`CancelHandle`]s if cancellation is enabled.
+    cancel_target: Option<Arc<CancelTarget>>,
+
+    /// The error reporter passed to TensorFlow Lite C as user data.
+    ///
+    /// It is dropped after the underlying interpreter is deleted.
+    #[allow(dead_code)]
+    error_reporter: Box<ErrorReporterState>,
+
+    /// The [`CustomOp`]s registered on the interpreter.
+    ///
+    /// They are dropped after the underlying interpreter is deleted.
+    #[allow(dead_code)]
+    custom_ops: Vec<CustomOpRegistration<'a>>,
+
+    /// The profiler if profiling is enabled.
+    ///
+    /// It is dropped after the underlying interpreter is deleted.
+    profiler: Option<Profiler>,
+
+    /// The number of tensors of the primary subgraph.
+    tensor_count: usize,
+
+    /// The indices of the variable tensors of the primary subgraph.
+    variable_tensors: Vec<usize>,
+
+    /// The buffers of the tensors if all tensors are preserved.
+    ///
+    /// They are dropped after the underlying interpreter is deleted.
+    preserved_tensors: Option<PreservedTensors>,
 
     /// The underlying `Model` to limit lifetime of the interpreter.
     /// See this issue for details:
@@ -104,46 +1162,10 @@ impl<'a> Interpreter<'a> {
     ///
     /// Returns error if TensorFlow Lite C fails internally.
     pub fn new(model: &'a Model<'a>, options: Option<Options>) -> Result<Interpreter<'a>> {
-        unsafe {
-            let options_ptr = TfLiteInterpreterOptionsCreate();
-            if options_ptr.is_null() {
-                return Err(Error::new(ErrorKind::FailedToCreateInterpreter));
-            }
-            if let Some(thread_count) = options.as_ref().map(|s| s.thread_count) {
-                TfLiteInterpreterOptionsSetNumThreads(options_ptr, thread_count);
-            }
-
-            #[cfg(feature = "xnnpack")]
-            let mut xnnpack_delegate_ptr: Option<*mut TfLiteDelegate> = None;
-            #[cfg(feature = "xnnpack")]
-            {
-                if let Some(options) = options.as_ref() {
-                    if options.is_xnnpack_enabled {
-                        let delegate_ptr = Interpreter::configure_xnnpack(options, options_ptr);
-                        // Only store the delegate pointer if it's not null
-                        if !delegate_ptr.is_null() {
-                            xnnpack_delegate_ptr = Some(delegate_ptr);
-                        }
-                    }
-                }
-            }
-
-            // TODO(ebraraktas): TfLiteInterpreterOptionsSetErrorReporter
-            let model_ptr = model.model_ptr as *const TfLiteModel;
-            let interpreter_ptr = TfLiteInterpreterCreate(model_ptr, options_ptr);
-            TfLiteInterpreterOptionsDelete(options_ptr);
-
-            if interpreter_ptr.is_null() {
-                Err(Error::new(ErrorKind::FailedToCreateInterpreter))
-            } else {
-                Ok(Interpreter {
-                    options,
-                    interpreter_ptr,
-                    #[cfg(feature = "xnnpack")]
-                    xnnpack_delegate_ptr,
-                    model,
-                })
-            }
+        let builder = InterpreterBuilder::new(model);
+        match options {
+            Some(options) => builder.options(options).build(),
+            None => builder.build(),
         }
     }
 
@@ -161,15 +1183,79 @@ impl<'a> Interpreter<'a> {
     ///
     /// # Errors
     ///
-    /// Returns error if TensorFlow Lite C fails to invoke.
+    /// Returns [`ErrorKind::AllocateTensorsRequired`] if the tensors are not allocated,
+    /// [`ErrorKind::FailedToInvokeInterpreter`] if TensorFlow Lite C fails to invoke, e.g.
+    /// because an operator failed, or [`ErrorKind::Cancelled`] if the invocation was
+    /// cancelled through a [`CancelHandle`].
     pub fn invoke(&self) -> Result<()> {
-        if TfLiteStatus::kTfLiteOk == unsafe { TfLiteInterpreterInvoke(self.interpreter_ptr) } {
-            Ok(())
-        } else {
-            Err(Error::new(ErrorKind::AllocateTensorsRequired))
+        if let Some(profiler) = self.profiler.as_ref() {
+            profiler.begin_run();
+        }
+        let status = unsafe { TfLiteInterpreterInvoke(self.interpreter_ptr) };
+        if let Some(profiler) = self.profiler.as_ref() {
+            profiler.end_run();
+        }
+        match status {
+            TfLiteStatus::kTfLiteOk => Ok(()),
+            TfLiteStatus::kTfLiteCancelled => Err(Error::new(ErrorKind::Cancelled)),
+            _ => {
+                let is_allocated =
+                    (0..self.input_tensor_count()).all(|index| match self.input(index) {
+                        Err(error) => error.kind() != ErrorKind::AllocateTensorsRequired,
+                        Ok(_) => true,
+                    });
+                if is_allocated {
+                    Err(Error::new(ErrorKind::FailedToInvokeInterpreter))
+                } else {
+                    Err(Error::new(ErrorKind::AllocateTensorsRequired))
+                }
+            }
         }
     }
 
+    /// Invokes the interpreter and cancels the invocation if it does not finish
+    /// within the given `deadline`.
+    ///
+    /// A watchdog thread is spawned for the duration of the call, and it cancels
+    /// the invocation through a [`CancelHandle`] once the deadline elapses.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline`: The maximum duration of the invocation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::CancellationNotEnabled`] if the interpreter was created without
+    /// [`Options::is_cancellation_enabled`], [`ErrorKind::Cancelled`] if the deadline elapsed
+    /// before the invocation finished, or error if TensorFlow Lite C fails to invoke.
+    pub fn invoke_with_deadline(&self, deadline: Duration) -> Result<()> {
+        let handle = self
+            .cancel_handle()
+            .ok_or_else(|| Error::new(ErrorKind::CancellationNotEnabled))?;
+        let (done_sender, done_receiver) = mpsc::channel::<()>();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                if let Err(mpsc::RecvTimeoutError::Timeout) = done_receiver.recv_timeout(deadline) {
+                    handle.cancel();
+                }
+            });
+            let result = self.invoke();
+            drop(done_sender);
+            result
+        })
+    }
+
+    /// Returns a [`CancelHandle`] which cancels invocations of this interpreter
+    /// from any thread.
+    ///
+    /// returns: [`None`] if the interpreter was created without
+    /// [`Options::is_cancellation_enabled`].
+    pub fn cancel_handle(&self) -> Option<CancelHandle> {
+        self.cancel_target.as_ref().map(|target| CancelHandle {
+            target: Arc::clone(target),
+        })
+    }
+
     /// Returns the input [`Tensor`] at the given `index`.
     ///
     /// # Arguments
@@ -227,6 +1313,120 @@ impl<'a> Interpreter<'a> {
         }
     }
 
+    /// Returns the total number of tensors in the primary subgraph, including inputs,
+    /// outputs, constants and intermediate tensors.
+    pub fn tensor_count(&self) -> usize {
+        self.tensor_count
+    }
+
+    /// Returns the [`Tensor`] of the primary subgraph at the given `index`.
+    ///
+    /// Intermediate tensors only hold the values of the last invocation if the interpreter
+    /// was built with [`InterpreterBuilder::preserve_all_tensors()`], otherwise their
+    /// buffers are reused by other tensors.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: The index of the tensor, in [0, [`Interpreter::tensor_count()`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `index` is out of range, the tensor has no data yet or
+    /// its data type is not supported.
+    pub fn tensor(&self, index: usize) -> Result<Tensor<'_>> {
+        if index >= self.tensor_count {
+            return Err(Error::new(ErrorKind::InvalidTensorIndex(
+                index,
+                self.tensor_count.saturating_sub(1),
+            )));
+        }
+        unsafe {
+            let tensor_ptr = TfLiteInterpreterGetTensor(self.interpreter_ptr, index as c_int);
+            Tensor::from_raw(tensor_ptr).map_err(|error| {
+                if error.kind() == ErrorKind::ReadTensorError {
+                    Error::new(ErrorKind::AllocateTensorsRequired)
+                } else {
+                    error
+                }
+            })
+        }
+    }
+
+    /// Returns the index of the first tensor with the given `name`.
+    pub fn tensor_index(&self, name: &str) -> Option<usize> {
+        (0..self.tensor_count).find(|index| self.tensor_name(*index) == Some(name))
+    }
+
+    fn tensor_name(&self, index: usize) -> Option<&str> {
+        unsafe {
+            let tensor_ptr = TfLiteInterpreterGetTensor(self.interpreter_ptr, index as c_int);
+            if tensor_ptr.is_null() {
+                return None;
+            }
+            let name_ptr = TfLiteTensorName(tensor_ptr);
+            if name_ptr.is_null() {
+                return None;
+            }
+            CStr::from_ptr(name_ptr).to_str().ok()
+        }
+    }
+
+    /// Writes the tensors whose names are accepted by `select` to `directory` in the
+    /// NumPy `.npy` format, see [`Tensor::write_npy()`].
+    ///
+    /// Files are named after the index and the name of the tensor, e.g. `0003_conv1.npy`.
+    /// Tensors without data or with an unsupported data type are skipped. Names can be
+    /// selected with a list, or with a regular expression as below.
+    ///
+    /// ```ignore
+    /// let pattern = regex::Regex::new("^conv[0-9]+$").unwrap();
+    /// interpreter.dump_tensors("dump", |name| pattern.is_match(name))?;
+    /// ```
+    ///
+    /// returns: The paths of the written files.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::FailedToWriteTensor`] if the directory cannot be created
+    /// or a file cannot be written.
+    pub fn dump_tensors<P, F>(&self, directory: P, mut select: F) -> Result<Vec<PathBuf>>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&str) -> bool,
+    {
+        let directory = directory.as_ref();
+        let write_error = |_| Error::new(ErrorKind::FailedToWriteTensor);
+        std::fs::create_dir_all(directory).map_err(write_error)?;
+        let mut paths = Vec::new();
+        for index in 0..self.tensor_count {
+            let name = match self.tensor_name(index) {
+                Some(name) if select(name) => name,
+                _ => continue,
+            };
+            let tensor = match self.tensor(index) {
+                Ok(tensor) => tensor,
+                Err(_) => continue,
+            };
+            let file_name = name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>();
+            let path = directory.join(format!("{:04}_{}.npy", index, file_name));
+            let file = std::fs::File::create(&path).map_err(write_error)?;
+            tensor
+                .write_npy(BufWriter::new(file))
+                .map_err(write_error)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
     /// Resizes the input [`Tensor`] at the given index to the
     /// specified [`Shape`][tensor::Shape].
     ///
@@ -243,23 +1443,44 @@ impl<'a> Interpreter<'a> {
     ///
     /// Returns error if given index is not a valid input tensor index in
     /// [0, [`Interpreter::input_tensor_count()`]) or TensorFlow Lite C fails internally.
-    pub fn resize_input(&self, index: usize, shape: tensor::Shape) -> Result<()> {
+    /// Returns [`ErrorKind::IncompatibleInputShape`] if the input has a dynamic
+    /// [`ShapeSignature`][tensor::ShapeSignature] and `shape` differs from it in rank or
+    /// a fixed dimension. Inputs without dynamic dimensions are not validated, since models
+    /// exported without shape signatures report all dimensions as fixed.
+    /// Returns [`ErrorKind::ShapeOverflow`] if a dimension does not fit in `i32` or
+    /// the number of elements overflows `usize`.
+    pub fn resize_input<S: Into<tensor::Shape>>(&self, index: usize, shape: S) -> Result<()> {
         let max_index = self.input_tensor_count() - 1;
         if index > max_index {
             return Err(Error::new(ErrorKind::InvalidTensorIndex(index, max_index)));
         }
+        let shape = shape.into();
+        if shape.checked_num_elements().is_none() {
+            return Err(Error::new(ErrorKind::ShapeOverflow(index)));
+        }
         let dims = shape
             .dimensions()
             .iter()
-            .map(|v| *v as i32)
-            .collect::<Vec<i32>>();
+            .map(|v| c_int::try_from(*v))
+            .collect::<std::result::Result<Vec<c_int>, _>>()
+            .map_err(|_| Error::new(ErrorKind::ShapeOverflow(index)))?;
+        let signature = unsafe {
+            let tensor_ptr = TfLiteInterpreterGetInputTensor(self.interpreter_ptr, index as i32);
+            if tensor_ptr.is_null() {
+                return Err(Error::new(ErrorKind::ReadTensorError));
+            }
+            tensor::ShapeSignature::from_raw(tensor_ptr)
+        };
+        if signature.is_dynamic() && !signature.accepts(&shape) {
+            return Err(Error::new(ErrorKind::IncompatibleInputShape(index)));
+        }
 
         unsafe {
             if TfLiteStatus::kTfLiteOk
                 == TfLiteInterpreterResizeInputTensor(
                     self.interpreter_ptr,
                     index as i32,
-                    dims.as_ptr() as *const c_int,
+                    dims.as_ptr(),
                     dims.len() as i32,
                 )
             {
@@ -281,12 +1502,23 @@ impl<'a> Interpreter<'a> {
     /// Returns error if TensorFlow Lite C fails to allocate memory
     /// for the input tensors.
     pub fn allocate_tensors(&self) -> Result<()> {
-        match unsafe { TfLiteInterpreterAllocateTensors(self.interpreter_ptr) } {
-            TfLiteStatus::kTfLiteOk => {
-                Ok(())
-            }
+        // The XNNPACK delegate may pack weights into a shared cache while allocating.
+        #[cfg(feature = "xnnpack")]
+        let _weights_cache_guard = self.xnnpack_weights_cache.as_ref().map(|c| c.lock());
+        let status = match self.preserved_tensors.as_ref() {
+            Some(preserved_tensors) => unsafe {
+                preserved_tensors.allocate(
+                    self.interpreter_ptr,
+                    self.tensor_count,
+                    &self.error_reporter,
+                )
+            },
+            None => unsafe { TfLiteInterpreterAllocateTensors(self.interpreter_ptr) },
+        };
+        match status {
+            TfLiteStatus::kTfLiteOk => Ok(()),
             status => {
-                println!("❌ Tensor allocation FAILED with status: {:?}", status);
+                log::error!("failed to allocate tensors with status {:?}", status);
                 Err(Error::new(ErrorKind::FailedToAllocateTensors))
             }
         }
@@ -311,7 +1543,15 @@ impl<'a> Interpreter<'a> {
         }
         unsafe {
             let tensor_ptr = TfLiteInterpreterGetInputTensor(self.interpreter_ptr, index as i32);
-            let byte_count = TfLiteTensorByteSize(tensor_ptr);
+            let shape = tensor::Shape::new(
+                (0..TfLiteTensorNumDims(tensor_ptr))
+                    .map(|i| TfLiteTensorDim(tensor_ptr, i) as usize)
+                    .collect(),
+            );
+            // Falls back to the size of the buffer for data types without a fixed size.
+            let byte_count = tensor::DataType::new(TfLiteTensorType(tensor_ptr))
+                .and_then(|data_type| shape.checked_num_elements()?.checked_mul(data_type.size()?))
+                .unwrap_or_else(|| TfLiteTensorByteSize(tensor_ptr));
             if data.len() != byte_count {
                 return Err(Error::new(ErrorKind::InvalidTensorDataCount(
                     data.len(),
@@ -332,7 +1572,8 @@ impl<'a> Interpreter<'a> {
     ///
     /// # Arguments
     ///
-    /// * `data`: The data to be copied to the input `Tensor`'s data buffer.
+    /// * `data`: The data to be copied to the input `Tensor`'s data buffer, e.g. a slice of
+    ///   elements or an [`OwnedTensor`] (see [`InputData`]).
     /// * `index`: The index for the input [`Tensor`].
     ///
     /// # Errors
@@ -340,88 +1581,240 @@ impl<'a> Interpreter<'a> {
     /// Returns error if byte count of the data does not match the buffer size of the
     /// input tensor or the given index is not a valid input tensor index in
     /// [0, [`Interpreter::input_tensor_count()`]) or TensorFlow Lite C fails internally.
-    pub fn copy<T>(&self, data: &[T], index: usize) -> Result<()> {
-        let element_size = std::mem::size_of::<T>();
-        let d = unsafe {
-            std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * element_size)
-        };
-        self.copy_bytes(d, index)
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the data type of an [`OwnedTensor`]
+    /// differs from the data type of the input tensor and [`ErrorKind::ShapeMismatch`] if
+    /// their shapes differ.
+    ///
+    /// Strings of another encoded size than the buffer of a string input tensor must be
+    /// copied with [`Interpreter::copy_strings()`], e.g. the strings of an [`OwnedTensor`]
+    /// with `interpreter.copy_strings(&owned.byte_strings()?, index)`.
+    pub fn copy<D: InputData + ?Sized>(&self, data: &D, index: usize) -> Result<()> {
+        data.copy_to(self, index)
     }
 
-    /// Returns optional reference of [`Options`].
-    pub fn options(&self) -> Option<&Options> {
-        self.options.as_ref()
+    /// Copies the given `strings` to the string input [`Tensor`] at the given `index`.
+    ///
+    /// The buffer of the tensor is reallocated if the encoded strings have another size.
+    /// Borrowing the interpreter mutably ensures that no [`Tensor`] still refers to the
+    /// previous buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `strings`: The strings in row-major order, e.g. `&["first", "second"]`.
+    /// * `index`: The index for the input [`Tensor`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`Interpreter::allocate_tensors()`] was not called, the given index
+    /// is not a valid input tensor index in [0, [`Interpreter::input_tensor_count()`]) or
+    /// the strings cannot be written (see [`Tensor::set_strings()`]).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tflitec::interpreter::Interpreter;
+    /// use tflitec::model::Model;
+    /// let model = Model::new("text_classifier.tflite")?;
+    /// let mut interpreter = Interpreter::new(&model, None)?;
+    /// interpreter.allocate_tensors()?;
+    /// interpreter.copy_strings(&["what a great movie"], 0)?;
+    /// assert_eq!(vec!["what a great movie"], interpreter.input(0)?.strings()?);
+    /// interpreter.invoke()?;
+    /// # Ok::<(), tflitec::Error>(())
+    /// ```
+    pub fn copy_strings<S: AsRef<[u8]>>(&mut self, strings: &[S], index: usize) -> Result<()> {
+        let mut input = self.input(index)?;
+        // No other `Tensor` can exist while the interpreter is borrowed mutably.
+        unsafe { input.set_strings(strings) }
     }
 
-    #[cfg(feature = "xnnpack")]
-    unsafe fn configure_xnnpack(
-        options: &Options,
-        interpreter_options_ptr: *mut TfLiteInterpreterOptions,
-    ) -> *mut TfLiteDelegate {
-        println!("🔧 Creating XNNPACK delegate with v2.19.0 library...");
-
-        // Use the library's default XNNPACK options (includes proper flags)
-        let mut xnnpack_options = unsafe { TfLiteXNNPackDelegateOptionsDefault() };
-        // Override just the thread count to match our settings
-        xnnpack_options.num_threads = options.thread_count as c_int;
-        
-        // Explicitly enable quantized inference flags (commonly needed for models)
-        xnnpack_options.flags |= TFLITE_XNNPACK_DELEGATE_FLAG_QS8 | TFLITE_XNNPACK_DELEGATE_FLAG_QU8;
-        
-        // Try enabling additional flags that might help with dynamic tensors and newer operators
-        xnnpack_options.flags |= TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_SUBGRAPH_RESHAPING;
-        xnnpack_options.flags |= TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS;
-
-        println!(
-            "🔧 Using XNNPACK options: threads={}, flags={}, weights_cache={:?}, handle_variable_ops={}, weight_cache_file_path={:?}",
-            xnnpack_options.num_threads, 
-            xnnpack_options.flags,
-            xnnpack_options.weights_cache,
-            xnnpack_options.handle_variable_ops,
-            xnnpack_options.weight_cache_file_path
-        );
+    /// Copies the given `array` to the input [`Tensor`] at the given `index`.
+    ///
+    /// Arrays which are not contiguous in standard (row-major) order, e.g. transposed or
+    /// sliced views, are copied in their logical order.
+    ///
+    /// # Arguments
+    ///
+    /// * `array`: The array whose shape must match the shape of the input `Tensor`.
+    /// * `index`: The index for the input [`Tensor`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::ShapeMismatch`] if the shape of `array` differs from the shape of
+    /// the input tensor. Returns error if [`Interpreter::allocate_tensors()`] was not called,
+    /// byte count of the data does not match the buffer size of the input tensor, the given
+    /// index is not a valid input tensor index in [0, [`Interpreter::input_tensor_count()`])
+    /// or TensorFlow Lite C fails internally.
+    #[cfg(feature = "ndarray")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn copy_array<S, D>(&self, array: &ndarray::ArrayBase<S, D>, index: usize) -> Result<()>
+    where
+        S: ndarray::Data,
+        S::Elem: TensorElement,
+        D: ndarray::Dimension,
+    {
+        if array.shape() != &self.input(index)?.shape().dimensions()[..] {
+            return Err(Error::new(ErrorKind::ShapeMismatch(index)));
+        }
+        match array.as_slice() {
+            Some(data) => self.copy(data, index),
+            None => self.copy(&array.iter().copied().collect::<Vec<S::Elem>>()[..], index),
+        }
+    }
+
+    /// Resets the variable tensors to their initial values, which are zeros unless the
+    /// model gives them constant data.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`Interpreter::allocate_tensors()`] was not called before calling this
+    /// or TensorFlow Lite C fails internally.
+    pub fn reset_variables(&self) -> Result<()> {
+        self.variable_tensor_data()?;
+        let status = unsafe { TfLiteInterpreterResetVariableTensors(self.interpreter_ptr) };
+        if status.is_error() {
+            return Err(Error::new(ErrorKind::FailedToResetVariables));
+        }
+        Ok(())
+    }
 
-        let xnnpack_delegate_ptr = unsafe { TfLiteXNNPackDelegateCreate(&xnnpack_options) };
-        println!("🔧 XNNPACK delegate created: {:?}", xnnpack_delegate_ptr);
+    /// Copies the values of all variable tensors into a new [`InterpreterState`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`Interpreter::allocate_tensors()`] was not called before calling this.
+    pub fn snapshot_state(&self) -> Result<InterpreterState> {
+        let mut state = InterpreterState::default();
+        self.snapshot_state_into(&mut state)?;
+        Ok(state)
+    }
 
-        if !xnnpack_delegate_ptr.is_null() {
-            println!("🔧 Adding XNNPACK delegate to interpreter options...");
-            TfLiteInterpreterOptionsAddDelegate(interpreter_options_ptr, xnnpack_delegate_ptr);
-            println!("✅ XNNPACK delegate added successfully!");
-        } else {
-            println!("❌ XNNPACK delegate creation failed - delegate is null");
+    /// Copies the values of all variable tensors into `state`, reusing its buffers.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`Interpreter::allocate_tensors()`] was not called before calling this.
+    pub fn snapshot_state_into(&self, state: &mut InterpreterState) -> Result<()> {
+        let tensors = self.variable_tensor_data()?;
+        state.tensors.resize_with(tensors.len(), Default::default);
+        for ((index, data), (state_index, state_data)) in tensors.iter().zip(&mut state.tensors) {
+            *state_index = *index;
+            state_data.clear();
+            state_data.extend_from_slice(data);
+        }
+        Ok(())
+    }
+
+    /// Writes the values of `state` into the variable tensors.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`Interpreter::allocate_tensors()`] was not called before calling this
+    /// or `state` was taken from an interpreter with different variable tensors, e.g. of
+    /// another model or with other input shapes.
+    pub fn restore_state(&self, state: &InterpreterState) -> Result<()> {
+        let tensors = self.variable_tensor_data()?;
+        let is_compatible = tensors.len() == state.tensors.len()
+            && tensors.iter().zip(&state.tensors).all(
+                |((index, data), (state_index, state_data))| {
+                    index == state_index && data.len() == state_data.len()
+                },
+            );
+        if !is_compatible {
+            return Err(Error::new(ErrorKind::IncompatibleState));
         }
+        for (index, data) in &state.tensors {
+            let tensor_ptr =
+                unsafe { TfLiteInterpreterGetTensor(self.interpreter_ptr, *index as c_int) };
+            let status = unsafe {
+                TfLiteTensorCopyFromBuffer(tensor_ptr, data.as_ptr() as *const c_void, data.len())
+            };
+            if status.is_error() {
+                return Err(Error::new(ErrorKind::IncompatibleState));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the index and data of every variable tensor.
+    fn variable_tensor_data(&self) -> Result<Vec<(usize, &[u8])>> {
+        self.variable_tensors
+            .iter()
+            .map(|index| unsafe {
+                let tensor_ptr = TfLiteInterpreterGetTensor(self.interpreter_ptr, *index as c_int);
+                let data_ptr = TfLiteTensorData(tensor_ptr) as *const u8;
+                let byte_size = TfLiteTensorByteSize(tensor_ptr);
+                if data_ptr.is_null() && byte_size > 0 {
+                    return Err(Error::new(ErrorKind::AllocateTensorsRequired));
+                }
+                let data = if byte_size == 0 {
+                    &[][..]
+                } else {
+                    std::slice::from_raw_parts(data_ptr, byte_size)
+                };
+                Ok((*index, data))
+            })
+            .collect()
+    }
+
+    /// Returns the outcome of applying each delegate, in the order of application.
+    ///
+    /// Delegates rejected according to the [`DelegateFailurePolicy`] are reported as
+    /// [`DelegateStatus::Rejected`] with the reason of the failure.
+    pub fn delegate_status(&self) -> &[DelegateReport] {
+        &self.delegate_reports
+    }
+
+    /// Returns the nodes of the graph with the delegate or CPU backend running each of them.
+    ///
+    /// returns: [`None`] if the interpreter was built without delegates, since the plan is
+    /// recorded while delegates are applied. All nodes run on the CPU kernels then.
+    pub fn execution_plan(&self) -> Option<&ExecutionPlan> {
+        self.execution_plan.as_ref()
+    }
+
+    /// Returns the [`Profiler`] recording the time spent in every node, or [`None`] if
+    /// the interpreter was created without [`Options::is_profiling_enabled`].
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Returns optional reference of [`Options`].
+    pub fn options(&self) -> Option<&Options> {
+        self.options.as_ref()
+    }
 
-        xnnpack_delegate_ptr
+    /// Returns the report of the XNNPACK weight cache file if it is set with
+    /// [`InterpreterBuilder::xnnpack_weight_cache_file()`] and the XNNPACK delegate is enabled.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn weight_cache_report(&self) -> Option<&WeightCacheReport> {
+        self.xnnpack_weight_cache_report.as_ref()
     }
 }
 
 impl Drop for Interpreter<'_> {
     fn drop(&mut self) {
-        unsafe {
-            TfLiteInterpreterDelete(self.interpreter_ptr);
-
-            #[cfg(feature = "xnnpack")]
-            {
-                if let Some(delegate_ptr) = self.xnnpack_delegate_ptr {
-                    TfLiteXNNPackDelegateDelete(delegate_ptr)
-                }
-            }
+        if let Some(target) = self.cancel_target.as_ref() {
+            *target.interpreter_ptr.lock().unwrap() = std::ptr::null();
         }
+        unsafe { TfLiteInterpreterDelete(self.interpreter_ptr) }
     }
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::interpreter::Interpreter;
+pub(crate) mod tests {
+    use crate::interpreter::{Interpreter, InterpreterState};
     use crate::model::Model;
     use crate::tensor;
     use crate::ErrorKind;
 
+    /// The path of the model computing `output = input + input + input` for
+    /// inputs of shape `[1, 8, 8, 3]`.
     #[cfg(target_os = "windows")]
-    const MODEL_PATH: &str = "tests\\add.bin";
+    pub(crate) const MODEL_PATH: &str = "tests\\add.bin";
     #[cfg(not(target_os = "windows"))]
-    const MODEL_PATH: &str = "tests/add.bin";
+    pub(crate) const MODEL_PATH: &str = "tests/add.bin";
 
     #[test]
     fn test_interpreter_input_output_count() {
@@ -447,6 +1840,8 @@ mod tests {
         assert!(invalid_tensor.is_err());
         let err = invalid_tensor.err().unwrap();
         assert_eq!(ErrorKind::AllocateTensorsRequired, err.kind());
+        let err = interpreter.invoke().err().unwrap();
+        assert_eq!(ErrorKind::AllocateTensorsRequired, err.kind());
 
         interpreter.allocate_tensors().unwrap();
         let valid_tensor = interpreter.input(0);
@@ -511,16 +1906,127 @@ mod tests {
         assert_eq!(expected, output_vector);
     }
 
+    #[test]
+    fn test_interpreter_cancellation() {
+        use crate::interpreter::Options;
+        use std::time::Duration;
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        assert!(interpreter.cancel_handle().is_none());
+        let err = interpreter
+            .invoke_with_deadline(Duration::from_secs(1))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::CancellationNotEnabled, err.kind());
+
+        let options = Some(Options {
+            is_cancellation_enabled: true,
+            ..Default::default()
+        });
+        let interpreter = Interpreter::new(&model, options).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        interpreter.copy(&data[..], 0).unwrap();
+
+        let handle = interpreter
+            .cancel_handle()
+            .expect("Cancellation must be enabled");
+        // A cancellation requested while idle must not affect the next invocation.
+        assert!(handle.clone().cancel());
+        assert!(interpreter
+            .invoke_with_deadline(Duration::from_secs(10))
+            .is_ok());
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        assert_eq!(expected, interpreter.output(0).unwrap().data::<f32>());
+
+        drop(interpreter);
+        assert!(!handle.cancel());
+    }
+
+    #[test]
+    fn test_interpreter_builder_validation() {
+        use crate::delegate::DelegateFailurePolicy;
+        use crate::interpreter::{InterpreterBuilder, OpResolver};
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let invalid_builders = vec![
+            InterpreterBuilder::new(&model).thread_count(-2),
+            InterpreterBuilder::new(&model)
+                .delegate_failure_policy(DelegateFailurePolicy::FallBackToCpu),
+            InterpreterBuilder::new(&model).dynamic_allocation_for_large_tensors(0),
+            InterpreterBuilder::new(&model).op_resolver(OpResolver::Selected),
+            unsafe { InterpreterBuilder::new(&model).add_delegate_raw(std::ptr::null_mut()) },
+        ];
+        for builder in invalid_builders {
+            let err = builder.build().err().unwrap();
+            assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+        }
+
+        let interpreter = InterpreterBuilder::new(&model)
+            .thread_count(1)
+            .cancellation(true)
+            .dynamic_allocation_for_large_tensors(1 << 20)
+            .error_reporter(|message| eprintln!("{}", message))
+            .build()
+            .expect("Cannot create interpreter!");
+        assert!(interpreter.cancel_handle().is_some());
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        assert!(interpreter.copy(&data[..], 0).is_ok());
+        assert!(interpreter.invoke().is_ok());
+    }
+
+    #[cfg(feature = "xnnpack")]
+    #[test]
+    fn test_interpreter_builder_xnnpack_validation() {
+        use crate::delegate::DelegateFailurePolicy;
+        use crate::interpreter::{InterpreterBuilder, Options};
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let err = InterpreterBuilder::new(&model)
+            .add_xnnpack_delegate()
+            .add_xnnpack_delegate()
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+
+        let err = InterpreterBuilder::new(&model)
+            .add_xnnpack_delegate()
+            .options(Options::default())
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+
+        let interpreter = InterpreterBuilder::new(&model)
+            .add_xnnpack_delegate()
+            .delegate_failure_policy(DelegateFailurePolicy::FallBackToCpu)
+            .build();
+        assert!(interpreter.is_ok());
+    }
+
     #[cfg(feature = "xnnpack")]
     #[test]
     fn test_interpreter_invoke_xnnpack() {
+        use crate::delegate::DelegateStatus;
         use crate::interpreter::Options;
         let options = Some(Options {
             thread_count: 2,
             is_xnnpack_enabled: true,
+            ..Default::default()
         });
         let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
         let interpreter = Interpreter::new(&model, options).expect("Cannot create interpreter!");
+        let status = interpreter.delegate_status();
+        assert_eq!(1, status.len());
+        assert_eq!("XNNPACK", status[0].name);
+        assert_eq!(DelegateStatus::Applied, status[0].status);
 
         interpreter
             .resize_input(0, tensor::Shape::new(vec![10, 8, 8, 3]))
@@ -538,4 +2044,376 @@ mod tests {
         let output_vector = output_tensor.data::<f32>().to_vec();
         assert_eq!(expected, output_vector);
     }
+
+    #[test]
+    fn test_interpreter_variable_state() {
+        use crate::test_models::variable_add_model;
+
+        let model_data = variable_add_model();
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        assert_eq!(
+            ErrorKind::AllocateTensorsRequired,
+            interpreter.snapshot_state().err().unwrap().kind()
+        );
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let input = [1.0f32, 2.0, 3.0, 4.0];
+        assert!(interpreter.copy(&input[..], 0).is_ok());
+
+        let state_index = interpreter.tensor_index("state").unwrap();
+        let stream_state = [10.0f32, 20.0, 30.0, 40.0];
+        assert!(interpreter
+            .tensor(state_index)
+            .unwrap()
+            .set_data(&stream_state[..])
+            .is_ok());
+        let state = interpreter.snapshot_state().expect("Cannot snapshot state");
+        assert_eq!(1, state.tensor_count());
+        assert_eq!(16, state.byte_size());
+
+        interpreter
+            .reset_variables()
+            .expect("Cannot reset variables");
+        assert!(interpreter.invoke().is_ok());
+        assert_eq!(&input, interpreter.output(0).unwrap().data::<f32>());
+
+        interpreter
+            .restore_state(&state)
+            .expect("Cannot restore state");
+        assert!(interpreter.invoke().is_ok());
+        assert_eq!(
+            &[11.0f32, 22.0, 33.0, 44.0],
+            interpreter.output(0).unwrap().data::<f32>()
+        );
+
+        let mut reused = InterpreterState::default();
+        interpreter
+            .snapshot_state_into(&mut reused)
+            .expect("Cannot snapshot state");
+        assert_eq!(state, reused);
+        assert_eq!(
+            ErrorKind::IncompatibleState,
+            interpreter
+                .restore_state(&InterpreterState::default())
+                .err()
+                .unwrap()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_preserve_all_tensors() {
+        use crate::interpreter::InterpreterBuilder;
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = InterpreterBuilder::new(&model)
+            .preserve_all_tensors(true)
+            .build()
+            .expect("Cannot create interpreter!");
+        assert!(interpreter.tensor_count() >= 3);
+        assert!(interpreter.tensor(interpreter.tensor_count()).is_err());
+
+        interpreter
+            .resize_input(0, tensor::Shape::new(vec![10, 8, 8, 3]))
+            .expect("Resize failed");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let data = (0..1920).map(|x| x as f32).collect::<Vec<f32>>();
+        assert!(interpreter.copy(&data[..], 0).is_ok());
+        assert!(interpreter.invoke().is_ok());
+
+        let input_name = interpreter.input(0).unwrap().name().to_owned();
+        assert!(interpreter.tensor_index(&input_name).is_some());
+        let doubled: Vec<f32> = data.iter().map(|e| e * 2.0).collect();
+        let tripled: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        let values: Vec<Vec<f32>> = (0..interpreter.tensor_count())
+            .filter_map(|index| interpreter.tensor(index).ok())
+            .map(|tensor| tensor.data::<f32>().to_vec())
+            .collect();
+        assert!(values.contains(&doubled));
+        assert!(values.contains(&tripled));
+
+        let dir = std::env::temp_dir().join(format!("tflitec_dump_{}", std::process::id()));
+        let paths = interpreter
+            .dump_tensors(&dir, |_| true)
+            .expect("Cannot dump tensors");
+        assert!(!paths.is_empty());
+        for path in &paths {
+            let bytes = std::fs::read(path).expect("Cannot read dumped tensor!");
+            assert!(bytes.starts_with(b"\x93NUMPY"));
+        }
+        std::fs::remove_dir_all(&dir).expect("Cannot remove dump directory!");
+    }
+
+    #[test]
+    fn test_interpreter_shape_signature() {
+        use crate::tensor::Dimension;
+        use crate::test_models::dynamic_batch_model;
+
+        let model_data = dynamic_batch_model();
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        let err = interpreter
+            .resize_input(0, tensor::Shape::new(vec![3, 5]))
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::IncompatibleInputShape(0), err.kind());
+        assert!(interpreter
+            .resize_input(0, tensor::Shape::new(vec![3]))
+            .is_err());
+        interpreter
+            .resize_input(0, tensor::Shape::new(vec![3, 4]))
+            .expect("Resize failed");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+
+        let input = interpreter.input(0).unwrap();
+        assert_eq!(&vec![3, 4], input.shape().dimensions());
+        assert_eq!(
+            &vec![Dimension::Dynamic, Dimension::Fixed(4)],
+            input.shape_signature().dimensions()
+        );
+        let data = (0..12).map(|x| x as f32).collect::<Vec<f32>>();
+        interpreter.copy(&data[..], 0).expect("Cannot copy input");
+        interpreter.invoke().expect("Cannot invoke");
+        let expected: Vec<f32> = data.iter().map(|e| e * 2.0).collect();
+        assert_eq!(&expected[..], interpreter.output(0).unwrap().data::<f32>());
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        let err = interpreter
+            .resize_input(0, [usize::MAX, 8, 8, 3])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::ShapeOverflow(0), err.kind());
+        interpreter.allocate_tensors().unwrap();
+        assert!(!interpreter.input(0).unwrap().shape_signature().is_dynamic());
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_interpreter_copy_array() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+
+        let channels_first =
+            ndarray::Array::from_shape_fn((1, 3, 8, 8), |(_, c, h, w)| (c * 64 + h * 8 + w) as f32);
+        let channels_last = channels_first.view().permuted_axes([0, 2, 3, 1]);
+        assert!(channels_last.as_slice().is_none());
+        interpreter
+            .copy_array(&channels_last, 0)
+            .expect("Cannot copy array");
+        interpreter.invoke().expect("Cannot invoke");
+        let output = interpreter.output(0).unwrap();
+        assert_eq!(&[1, 8, 8, 3], output.as_array::<f32>().shape());
+        assert_eq!(
+            channels_last.mapv(|x| x * 3.0).into_dyn(),
+            output.as_array::<f32>()
+        );
+
+        let mut input = interpreter.input(0).unwrap();
+        // No other handle of the input is used while the view is alive.
+        unsafe { input.as_array_mut::<f32>() }.fill(1.0);
+        assert!(input.data::<f32>().iter().all(|x| *x == 1.0));
+
+        let err = interpreter.copy_array(&channels_first, 0).err().unwrap();
+        assert_eq!(ErrorKind::ShapeMismatch(0), err.kind());
+    }
+
+    #[test]
+    #[cfg(feature = "half")]
+    fn test_interpreter_float16_tensor() {
+        use crate::tensor::{f16, DataType};
+        use crate::test_models::identity_model;
+
+        const FLOAT16: u8 = 1;
+        let model_data = identity_model(FLOAT16);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let mut input = interpreter.input(0).unwrap();
+        assert_eq!(DataType::Float16, input.data_type());
+
+        input
+            .set_f32_data(&[0.5, -2.0, 1.0 / 3.0, 65504.0])
+            .expect("Cannot set data");
+        interpreter.invoke().expect("Cannot invoke");
+        let output = interpreter.output(0).unwrap();
+        assert_eq!(
+            &[
+                f16::from_f32(0.5),
+                f16::from_f32(-2.0),
+                f16::from_f32(1.0 / 3.0),
+                f16::MAX
+            ],
+            output.data::<f16>()
+        );
+        let values = output.to_f32_vec().expect("Cannot convert data");
+        assert_eq!(vec![0.5, -2.0, 0.33325195, 65504.0], values);
+        assert!(input.set_f32_data(&[0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_interpreter_string_tensor() {
+        use crate::tensor::DataType;
+        use crate::test_models::identity_model;
+
+        const STRING: u8 = 5;
+        let model_data = identity_model(STRING);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let mut interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let input = interpreter.input(0).unwrap();
+        assert_eq!(DataType::String, input.data_type());
+        assert!(input.strings().unwrap().is_empty());
+        // An unwritten string tensor has no buffer, but its empty data is still aligned.
+        assert!(input.data::<f32>().is_empty());
+
+        let strings = ["hello", "", "tensorflow lite", "\u{1f600}"];
+        interpreter
+            .copy_strings(&strings, 0)
+            .expect("Cannot copy strings");
+        assert_eq!(
+            &strings[..],
+            &interpreter.input(0).unwrap().strings().unwrap()[..]
+        );
+        interpreter
+            .copy_strings(&["a", "b", "c", "d"], 0)
+            .expect("Cannot copy strings");
+        interpreter.invoke().expect("Cannot invoke");
+        let output = interpreter.output(0).unwrap();
+        assert_eq!(vec!["a", "b", "c", "d"], output.strings().unwrap());
+        assert_eq!(
+            vec![b"a".as_ref(), b"b", b"c", b"d"],
+            output.byte_strings().unwrap()
+        );
+
+        let err = interpreter.copy_strings(&["a"], 0).err().unwrap();
+        assert_eq!(ErrorKind::InvalidTensorDataCount(1, 4), err.kind());
+        let input = interpreter.input(0).unwrap();
+        assert!(input.data::<u8>().starts_with(&4i32.to_ne_bytes()));
+
+        // Owned strings of the same size are copied, others need `copy_strings`.
+        let owned = interpreter.output(0).unwrap().to_owned();
+        interpreter
+            .copy(&owned, 0)
+            .expect("Cannot copy owned strings");
+        interpreter
+            .copy_strings(&strings, 0)
+            .expect("Cannot copy strings");
+        let err = interpreter.copy(&owned, 0).err().unwrap();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::InvalidTensorDataCount(_, _)
+        ));
+        interpreter
+            .copy_strings(&owned.byte_strings().unwrap(), 0)
+            .expect("Cannot copy owned strings");
+        assert_eq!(
+            vec!["a", "b", "c", "d"],
+            interpreter.input(0).unwrap().strings().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_unsigned_tensor() {
+        use crate::tensor::DataType;
+        use crate::test_models::identity_model;
+
+        const UINT32: u8 = 15;
+        let model_data = identity_model(UINT32);
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        let input = interpreter.input(0).unwrap();
+        assert_eq!(DataType::Uint32, input.data_type());
+        assert_eq!(Some(4), input.data_type().size());
+
+        let data = [0u32, 1, u32::MAX - 1, u32::MAX];
+        interpreter.copy(&data[..], 0).expect("Cannot copy input");
+        interpreter.invoke().expect("Cannot invoke");
+        assert_eq!(&data[..], interpreter.output(0).unwrap().data::<u32>());
+        assert!(input.int4_values().is_err());
+    }
+
+    #[test]
+    fn test_owned_tensor_int4_values() {
+        use crate::tensor::{DataType, OwnedTensor, Shape};
+
+        let packed =
+            OwnedTensor::new("packed", DataType::Int4, Shape::from([3]), &[0xe1u8, 0x07]).unwrap();
+        assert_eq!(vec![1, -2, 7], packed.int4_values().unwrap());
+        let unpacked =
+            OwnedTensor::new("unpacked", DataType::Int4, Shape::from([3]), &[1i8, -2, 7]).unwrap();
+        assert_eq!(vec![1, -2, 7], unpacked.int4_values().unwrap());
+        // A single value has the same length in both layouts.
+        for byte in [0x0eu8, 0xfe] {
+            let single =
+                OwnedTensor::new("single", DataType::Int4, Shape::from([1]), &[byte]).unwrap();
+            assert_eq!(vec![-2], single.int4_values().unwrap());
+        }
+        let invalid =
+            OwnedTensor::new("invalid", DataType::Int4, Shape::from([3]), &[0u8; 4]).unwrap();
+        assert_eq!(
+            ErrorKind::ReadTensorError,
+            invalid.int4_values().err().unwrap().kind()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_owned_tensor() {
+        use crate::tensor::{DataType, OwnedTensor, Shape};
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        interpreter.copy(&data[..], 0).unwrap();
+        interpreter.invoke().expect("Cannot invoke");
+        let output = interpreter.output(0).unwrap().to_owned();
+        let output = std::thread::spawn(move || output).join().unwrap();
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        assert_eq!(DataType::Float32, output.data_type());
+        assert_eq!(&vec![1, 8, 8, 3], output.shape().dimensions());
+        assert_eq!(&expected[..], output.data::<f32>());
+        assert_eq!(0, output.data::<u8>().as_ptr() as usize % 64);
+
+        let input = OwnedTensor::new(
+            "input",
+            DataType::Float32,
+            Shape::from([1, 8, 8, 3]),
+            &expected,
+        )
+        .unwrap();
+        interpreter.copy(&input, 0).expect("Cannot copy tensor");
+        assert_eq!(&expected[..], interpreter.input(0).unwrap().data::<f32>());
+        assert_eq!(input, input.clone());
+
+        let err = interpreter.copy(&input, 1).err().unwrap();
+        assert_eq!(ErrorKind::InvalidTensorIndex(1, 0), err.kind());
+        let wrong_shape =
+            OwnedTensor::new("input", DataType::Float32, Shape::from([2, 96]), &expected).unwrap();
+        let err = interpreter.copy(&wrong_shape, 0).err().unwrap();
+        assert_eq!(ErrorKind::ShapeMismatch(0), err.kind());
+        let err = OwnedTensor::new("input", DataType::Float32, Shape::from([2]), &[0u8; 4])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidTensorDataCount(4, 8), err.kind());
+    }
 }
@@ -3,11 +3,28 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub mod async_interpreter;
+pub mod batching;
+pub mod custom_op;
+pub mod delegate;
+pub mod divergence;
 mod error;
+pub mod execution_plan;
 pub mod interpreter;
 pub mod minimal_bindings;
 pub mod model;
+pub mod pool;
+pub mod profiler;
+pub mod shape_cache;
+pub mod streaming;
 pub mod tensor;
+#[cfg(test)]
+mod test_models;
+#[cfg(feature = "xnnpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+pub mod xnnpack;
 
 // Re-export the minimal bindings for direct use if needed
 pub use minimal_bindings::*;
@@ -34,6 +34,68 @@ pub struct TfLiteDelegate {
     _private: [u8; 0],
 }
 
+#[repr(C)]
+pub struct TfLiteXNNPackDelegateWeightsCache {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct TfLiteOpaqueContext {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct TfLiteOpaqueTensor {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct TfLiteOpaqueNode {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct TfLiteOperator {
+    _private: [u8; 0],
+}
+
+/// Opaque delegates share the memory layout of [`TfLiteDelegate`].
+pub type TfLiteOpaqueDelegate = TfLiteDelegate;
+
+pub type TfLiteBufferHandle = c_int;
+
+/// A buffer provided by the application for the data of a tensor.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TfLiteCustomAllocation {
+    pub data: *mut c_void,
+    pub bytes: usize,
+}
+
+/// The affine quantization of a tensor, where `real = scale * (quantized - zero_point)`.
+///
+/// A `scale` of zero means the tensor is not quantized.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TfLiteQuantizationParams {
+    pub scale: f32,
+    pub zero_point: i32,
+}
+
+/// The alignment of tensor data required by TensorFlow Lite.
+pub const TFLITE_DEFAULT_TENSOR_ALIGNMENT: usize = 64;
+
+/// A variable length array of `int`s, e.g. node indices of an execution plan.
+#[repr(C)]
+pub struct TfLiteIntArray {
+    pub size: c_int,
+    pub data: [c_int; 0],
+}
+
+/// The tensor types of TensorFlow Lite 2.19.
+///
+/// Tensor types are read as raw `c_int` values, since a newer runtime may return types
+/// which are not listed here (see [`TfLiteType::from_raw()`]).
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TfLiteType {
@@ -54,6 +116,40 @@ pub enum TfLiteType {
     kTfLiteResource = 14,
     kTfLiteVariant = 15,
     kTfLiteUInt32 = 16,
+    kTfLiteUInt16 = 17,
+    kTfLiteInt4 = 18,
+    kTfLiteBFloat16 = 19,
+}
+
+impl TfLiteType {
+    /// Returns the type with the given raw value, or [`None`] if it is not known.
+    pub fn from_raw(value: c_int) -> Option<TfLiteType> {
+        use TfLiteType::*;
+        let tflite_type = match value {
+            0 => kTfLiteNoType,
+            1 => kTfLiteFloat32,
+            2 => kTfLiteInt32,
+            3 => kTfLiteUInt8,
+            4 => kTfLiteInt64,
+            5 => kTfLiteString,
+            6 => kTfLiteBool,
+            7 => kTfLiteInt16,
+            8 => kTfLiteComplex64,
+            9 => kTfLiteInt8,
+            10 => kTfLiteFloat16,
+            11 => kTfLiteFloat64,
+            12 => kTfLiteComplex128,
+            13 => kTfLiteUInt64,
+            14 => kTfLiteResource,
+            15 => kTfLiteVariant,
+            16 => kTfLiteUInt32,
+            17 => kTfLiteUInt16,
+            18 => kTfLiteInt4,
+            19 => kTfLiteBFloat16,
+            _ => return None,
+        };
+        Some(tflite_type)
+    }
 }
 
 #[repr(C)]
@@ -67,6 +163,8 @@ pub enum TfLiteStatus {
     kTfLiteDelegateDataWriteError = 5,
     kTfLiteDelegateDataReadError = 6,
     kTfLiteUnresolvedOps = 7,
+    kTfLiteCancelled = 8,
+    kTfLiteOutputShapeNotKnown = 9,
 }
 
 // =============================================================================
@@ -90,6 +188,112 @@ pub const TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS: u32 = 0x00000040
 // Enable XNNPack subgraph reshaping for dynamic tensors.
 pub const TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_SUBGRAPH_RESHAPING: u32 = 0x00000080;
 
+// =============================================================================
+// OPAQUE DELEGATE TYPES (from tensorflow/lite/core/c/common.h)
+// =============================================================================
+
+// The delegate can be applied to graphs with dynamic tensors.
+pub const TFLITE_DELEGATE_FLAGS_ALLOW_DYNAMIC_TENSORS: i64 = 1;
+
+#[repr(C)]
+pub struct TfLiteOpaqueDelegateBuilder {
+    pub data: *mut c_void,
+    pub prepare: Option<
+        unsafe extern "C" fn(
+            context: *mut TfLiteOpaqueContext,
+            delegate: *mut TfLiteOpaqueDelegate,
+            data: *mut c_void,
+        ) -> TfLiteStatus,
+    >,
+    pub copy_from_buffer_handle: Option<
+        unsafe extern "C" fn(
+            context: *mut TfLiteOpaqueContext,
+            delegate: *mut TfLiteOpaqueDelegate,
+            data: *mut c_void,
+            buffer_handle: TfLiteBufferHandle,
+            tensor: *mut TfLiteOpaqueTensor,
+        ) -> TfLiteStatus,
+    >,
+    pub copy_to_buffer_handle: Option<
+        unsafe extern "C" fn(
+            context: *mut TfLiteOpaqueContext,
+            delegate: *mut TfLiteOpaqueDelegate,
+            data: *mut c_void,
+            buffer_handle: TfLiteBufferHandle,
+            tensor: *mut TfLiteOpaqueTensor,
+        ) -> TfLiteStatus,
+    >,
+    pub free_buffer_handle: Option<
+        unsafe extern "C" fn(
+            context: *mut TfLiteOpaqueContext,
+            delegate: *mut TfLiteOpaqueDelegate,
+            data: *mut c_void,
+            buffer_handle: *mut TfLiteBufferHandle,
+        ),
+    >,
+    pub flags: i64,
+}
+
+// =============================================================================
+// TELEMETRY PROFILER (from tensorflow/lite/profiling/telemetry/c/profiler.h)
+// =============================================================================
+
+#[repr(C)]
+pub struct TfLiteTelemetrySettings {
+    _private: [u8; 0],
+}
+
+/// Returned by `report_begin_op_invoke_event` for events which are not tracked.
+pub const TFLITE_TELEMETRY_INVALID_EVENT_HANDLE: u32 = u32::MAX;
+
+#[repr(C)]
+pub struct TfLiteTelemetryProfilerStruct {
+    pub data: *mut c_void,
+    pub report_telemetry_event: Option<
+        unsafe extern "C" fn(
+            profiler: *mut TfLiteTelemetryProfilerStruct,
+            event_name: *const c_char,
+            status: u64,
+        ),
+    >,
+    pub report_telemetry_op_event: Option<
+        unsafe extern "C" fn(
+            profiler: *mut TfLiteTelemetryProfilerStruct,
+            event_name: *const c_char,
+            op_idx: i64,
+            subgraph_idx: i64,
+            status: u64,
+        ),
+    >,
+    pub report_settings: Option<
+        unsafe extern "C" fn(
+            profiler: *mut TfLiteTelemetryProfilerStruct,
+            setting_name: *const c_char,
+            settings: *const TfLiteTelemetrySettings,
+        ),
+    >,
+    pub report_begin_op_invoke_event: Option<
+        unsafe extern "C" fn(
+            profiler: *mut TfLiteTelemetryProfilerStruct,
+            op_name: *const c_char,
+            op_idx: i64,
+            subgraph_idx: i64,
+        ) -> u32,
+    >,
+    pub report_end_op_invoke_event: Option<
+        unsafe extern "C" fn(profiler: *mut TfLiteTelemetryProfilerStruct, event_handle: u32),
+    >,
+    pub report_op_invoke_event: Option<
+        unsafe extern "C" fn(
+            profiler: *mut TfLiteTelemetryProfilerStruct,
+            op_name: *const c_char,
+            elapsed_time: u64,
+            op_idx: i64,
+            subgraph_idx: i64,
+        ),
+    >,
+}
+
 // =============================================================================
 // XNNPACK TYPES (with correct signatures for latest TF Lite)
 // =============================================================================
@@ -99,8 +303,8 @@ pub const TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_SUBGRAPH_RESHAPING: u32 = 0x000000
 pub struct TfLiteXNNPackDelegateOptions {
     pub num_threads: c_int,
     pub flags: u32,
-    pub weights_cache: *mut c_void, // struct TfLiteXNNPackDelegateWeightsCache*
-    pub handle_variable_ops: bool,  // bool
+    pub weights_cache: *mut TfLiteXNNPackDelegateWeightsCache,
+    pub handle_variable_ops: bool,             // bool
     pub weight_cache_file_path: *const c_char, // const char*
 }
 
@@ -121,6 +325,13 @@ extern "C" {
         options: *mut TfLiteInterpreterOptions,
         num_threads: c_int,
     );
+    pub fn TfLiteInterpreterOptionsSetErrorReporter(
+        options: *mut TfLiteInterpreterOptions,
+        reporter: Option<
+            unsafe extern "C" fn(user_data: *mut c_void, format: *const c_char, args: VaListPtr),
+        >,
+        user_data: *mut c_void,
+    );
 
     // Interpreter management
     pub fn TfLiteInterpreterCreate(
@@ -161,6 +372,8 @@ extern "C" {
     ) -> TfLiteStatus;
     pub fn TfLiteTensorData(tensor: *const TfLiteTensor) -> *mut c_void;
     pub fn TfLiteTensorByteSize(tensor: *const TfLiteTensor) -> usize;
+    // From tensorflow/lite/core/c/common.h, resizes the buffer of a dynamic tensor.
+    pub fn TfLiteTensorRealloc(num_bytes: usize, tensor: *mut TfLiteTensor) -> TfLiteStatus;
 
     // Tensor shape info
     pub fn TfLiteTensorNumDims(tensor: *const TfLiteTensor) -> c_int;
@@ -168,10 +381,260 @@ extern "C" {
 
     // Tensor metadata functions
     pub fn TfLiteTensorName(tensor: *const TfLiteTensor) -> *const c_char;
-    pub fn TfLiteTensorType(tensor: *const TfLiteTensor) -> TfLiteType;
-    pub fn TfLiteTensorQuantizationParams(tensor: *const TfLiteTensor) -> *const c_void;
+    /// Returns the raw [`TfLiteType`] of the tensor.
+    pub fn TfLiteTensorType(tensor: *const TfLiteTensor) -> c_int;
+    pub fn TfLiteTensorQuantizationParams(tensor: *const TfLiteTensor) -> TfLiteQuantizationParams;
 }
 
+// =============================================================================
+// EXPERIMENTAL C API (from tensorflow/lite/core/c/c_api_experimental.h)
+// =============================================================================
+
+extern "C" {
+    // Cancellation
+    pub fn TfLiteInterpreterOptionsEnableCancellation(
+        options: *mut TfLiteInterpreterOptions,
+        enable: bool,
+    ) -> TfLiteStatus;
+    pub fn TfLiteInterpreterCancel(interpreter: *const TfLiteInterpreter) -> TfLiteStatus;
+
+    // Tensors
+    pub fn TfLiteInterpreterGetTensor(
+        interpreter: *const TfLiteInterpreter,
+        index: c_int,
+    ) -> *mut TfLiteTensor;
+    pub fn TfLiteInterpreterSetCustomAllocationForTensor(
+        interpreter: *mut TfLiteInterpreter,
+        tensor_index: c_int,
+        allocation: *const TfLiteCustomAllocation,
+        flags: i64,
+    ) -> TfLiteStatus;
+    pub fn TfLiteInterpreterResetVariableTensors(
+        interpreter: *mut TfLiteInterpreter,
+    ) -> TfLiteStatus;
+
+    // Profiling
+    pub fn TfLiteInterpreterOptionsSetTelemetryProfiler(
+        options: *mut TfLiteInterpreterOptions,
+        profiler: *mut TfLiteTelemetryProfilerStruct,
+    );
+
+    // Delegates and memory planning
+    pub fn TfLiteInterpreterOptionsSetEnableDelegateFallback(
+        options: *mut TfLiteInterpreterOptions,
+        enable: bool,
+    );
+    pub fn TfLiteInterpreterOptionsOptimizeMemoryForLargeTensors(
+        options: *mut TfLiteInterpreterOptions,
+        large_tensors_thresholds_in_bytes: c_int,
+    );
+
+    // Op resolution
+    pub fn TfLiteInterpreterCreateWithSelectedOps(
+        model: *const TfLiteModel,
+        options: *const TfLiteInterpreterOptions,
+    ) -> *mut TfLiteInterpreter;
+    pub fn TfLiteInterpreterOptionsAddOperator(
+        options: *mut TfLiteInterpreterOptions,
+        registration: *mut TfLiteOperator,
+    );
+}
+
+// =============================================================================
+// OPAQUE DELEGATE API (from tensorflow/lite/core/c/c_api_opaque.h)
+// =============================================================================
+
+extern "C" {
+    pub fn TfLiteOpaqueDelegateCreate(
+        opaque_delegate_builder: *const TfLiteOpaqueDelegateBuilder,
+    ) -> *mut TfLiteOpaqueDelegate;
+    pub fn TfLiteOpaqueDelegateDelete(delegate: *mut TfLiteOpaqueDelegate);
+
+    pub fn TfLiteOpaqueContextGetExecutionPlan(
+        context: *mut TfLiteOpaqueContext,
+        execution_plan: *mut *mut TfLiteIntArray,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOpaqueContextGetNodeAndRegistration(
+        context: *mut TfLiteOpaqueContext,
+        node_index: c_int,
+        node: *mut *mut TfLiteOpaqueNode,
+        registration: *mut *mut TfLiteOperator,
+    ) -> TfLiteStatus;
+
+    // Nodes
+    pub fn TfLiteOpaqueNodeInputs(
+        node: *const TfLiteOpaqueNode,
+        inputs: *mut *const c_int,
+        num_inputs: *mut c_int,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOpaqueNodeOutputs(
+        node: *const TfLiteOpaqueNode,
+        outputs: *mut *const c_int,
+        num_outputs: *mut c_int,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOpaqueNodeNumberOfInputs(node: *const TfLiteOpaqueNode) -> c_int;
+    pub fn TfLiteOpaqueNodeNumberOfOutputs(node: *const TfLiteOpaqueNode) -> c_int;
+    pub fn TfLiteOpaqueNodeGetInput(
+        context: *const TfLiteOpaqueContext,
+        node: *const TfLiteOpaqueNode,
+        index: c_int,
+    ) -> *const TfLiteOpaqueTensor;
+    pub fn TfLiteOpaqueNodeGetOutput(
+        context: *mut TfLiteOpaqueContext,
+        node: *const TfLiteOpaqueNode,
+        index: c_int,
+    ) -> *mut TfLiteOpaqueTensor;
+    pub fn TfLiteOpaqueNodeGetUserData(node: *const TfLiteOpaqueNode) -> *mut c_void;
+
+    // Tensors
+    /// Returns the raw [`TfLiteType`] of the tensor.
+    pub fn TfLiteOpaqueTensorType(tensor: *const TfLiteOpaqueTensor) -> c_int;
+    pub fn TfLiteOpaqueTensorNumDims(tensor: *const TfLiteOpaqueTensor) -> i32;
+    pub fn TfLiteOpaqueTensorDim(tensor: *const TfLiteOpaqueTensor, dim_index: i32) -> i32;
+    pub fn TfLiteOpaqueTensorGetNumDimsSignature(tensor: *const TfLiteOpaqueTensor) -> i32;
+    pub fn TfLiteOpaqueTensorGetDimSignature(
+        tensor: *const TfLiteOpaqueTensor,
+        dim_index: i32,
+    ) -> i32;
+    pub fn TfLiteOpaqueTensorByteSize(tensor: *const TfLiteOpaqueTensor) -> usize;
+    pub fn TfLiteOpaqueTensorData(tensor: *const TfLiteOpaqueTensor) -> *mut c_void;
+    pub fn TfLiteOpaqueTensorName(tensor: *const TfLiteOpaqueTensor) -> *const c_char;
+    pub fn TfLiteOpaqueTensorIsVariable(tensor: *const TfLiteOpaqueTensor) -> c_int;
+    pub fn TfLiteOpaqueContextResizeTensor(
+        context: *mut TfLiteOpaqueContext,
+        tensor: *mut TfLiteOpaqueTensor,
+        new_size: *mut TfLiteIntArray,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOpaqueContextReportError(
+        context: *mut TfLiteOpaqueContext,
+        format: *const c_char,
+        ...
+    );
+    pub fn TfLiteIntArrayCreate(size: c_int) -> *mut TfLiteIntArray;
+
+    // Operators
+    pub fn TfLiteOperatorGetBuiltInCode(registration: *const TfLiteOperator) -> c_int;
+    pub fn TfLiteOperatorGetCustomName(registration: *const TfLiteOperator) -> *const c_char;
+    pub fn TfLiteOperatorGetVersion(registration: *const TfLiteOperator) -> c_int;
+}
+
+// =============================================================================
+// OPERATOR API (from tensorflow/lite/core/c/operator.h)
+// =============================================================================
+
+/// The builtin code of custom operators.
+pub const TFLITE_BUILTIN_CUSTOM: c_int = 32;
+
+extern "C" {
+    pub fn TfLiteOperatorCreate(
+        builtin_code: c_int,
+        custom_name: *const c_char,
+        version: c_int,
+        user_data: *mut c_void,
+    ) -> *mut TfLiteOperator;
+    pub fn TfLiteOperatorDelete(registration: *mut TfLiteOperator);
+    pub fn TfLiteOperatorSetInitWithData(
+        registration: *mut TfLiteOperator,
+        init: Option<
+            unsafe extern "C" fn(
+                user_data: *mut c_void,
+                context: *mut TfLiteOpaqueContext,
+                buffer: *const c_char,
+                length: usize,
+            ) -> *mut c_void,
+        >,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOperatorSetFreeWithData(
+        registration: *mut TfLiteOperator,
+        free: Option<
+            unsafe extern "C" fn(
+                user_data: *mut c_void,
+                context: *mut TfLiteOpaqueContext,
+                data: *mut c_void,
+            ),
+        >,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOperatorSetPrepareWithData(
+        registration: *mut TfLiteOperator,
+        prepare: Option<
+            unsafe extern "C" fn(
+                user_data: *mut c_void,
+                context: *mut TfLiteOpaqueContext,
+                node: *mut TfLiteOpaqueNode,
+            ) -> TfLiteStatus,
+        >,
+    ) -> TfLiteStatus;
+    pub fn TfLiteOperatorSetInvokeWithData(
+        registration: *mut TfLiteOperator,
+        invoke: Option<
+            unsafe extern "C" fn(
+                user_data: *mut c_void,
+                context: *mut TfLiteOpaqueContext,
+                node: *mut TfLiteOpaqueNode,
+            ) -> TfLiteStatus,
+        >,
+    ) -> TfLiteStatus;
+}
+
+// =============================================================================
+// C STANDARD LIBRARY (used to format error reporter messages)
+// =============================================================================
+
+/// A `va_list` as received by C callbacks.
+///
+/// On every supported target a `va_list` argument is passed as a pointer-sized value,
+/// which can be forwarded to `vsnprintf` unchanged.
+pub type VaListPtr = *mut c_void;
+
+extern "C" {
+    pub fn vsnprintf(
+        buffer: *mut c_char,
+        buffer_size: usize,
+        format: *const c_char,
+        args: VaListPtr,
+    ) -> c_int;
+}
+
+// =============================================================================
+// DYNAMIC LIBRARY LOADING (used to load external delegates)
+// =============================================================================
+
+/// Resolves all undefined symbols of a library when it is opened.
+#[cfg(unix)]
+pub const RTLD_NOW: c_int = 2;
+
+#[cfg(unix)]
+extern "C" {
+    pub fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+    pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    pub fn dlclose(handle: *mut c_void) -> c_int;
+}
+
+#[cfg(windows)]
+extern "system" {
+    pub fn LoadLibraryW(filename: *const u16) -> *mut c_void;
+    pub fn GetProcAddress(module: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    pub fn FreeLibrary(module: *mut c_void) -> c_int;
+}
+
+// =============================================================================
+// EXTERNAL DELEGATE ABI (from tensorflow/lite/delegates/external/external_delegate_interface.h)
+// =============================================================================
+
+/// Receives error messages of an external delegate library.
+pub type TfLiteExternalDelegateErrorHandler = Option<unsafe extern "C" fn(message: *const c_char)>;
+
+/// `tflite_plugin_create_delegate` exported by external delegate libraries.
+pub type TfLiteExternalDelegateCreate = unsafe extern "C" fn(
+    options_keys: *const *const c_char,
+    options_values: *const *const c_char,
+    num_options: usize,
+    report_error: TfLiteExternalDelegateErrorHandler,
+) -> *mut TfLiteDelegate;
+
+/// `tflite_plugin_destroy_delegate` exported by external delegate libraries.
+pub type TfLiteExternalDelegateDestroy = unsafe extern "C" fn(delegate: *mut TfLiteDelegate);
+
 // =============================================================================
 // XNNPACK DELEGATE API (with correct modern signatures)
 // =============================================================================
@@ -185,6 +648,19 @@ extern "C" {
     ) -> *mut TfLiteDelegate;
     pub fn TfLiteXNNPackDelegateDelete(delegate: *mut TfLiteDelegate);
 
+    // Weights cache shared by several delegates
+    pub fn TfLiteXNNPackDelegateWeightsCacheCreate() -> *mut TfLiteXNNPackDelegateWeightsCache;
+    pub fn TfLiteXNNPackDelegateWeightsCacheCreateWithSize(
+        size: usize,
+    ) -> *mut TfLiteXNNPackDelegateWeightsCache;
+    pub fn TfLiteXNNPackDelegateWeightsCacheFinalizeSoft(
+        cache: *mut TfLiteXNNPackDelegateWeightsCache,
+    ) -> bool;
+    pub fn TfLiteXNNPackDelegateWeightsCacheFinalizeHard(
+        cache: *mut TfLiteXNNPackDelegateWeightsCache,
+    ) -> bool;
+    pub fn TfLiteXNNPackDelegateWeightsCacheDelete(cache: *mut TfLiteXNNPackDelegateWeightsCache);
+
     pub fn TfLiteInterpreterOptionsAddDelegate(
         options: *mut TfLiteInterpreterOptions,
         delegate: *mut TfLiteDelegate,
@@ -13,6 +13,8 @@ use crate::minimal_bindings::{
 use crate::{Error, ErrorKind, Result};
 use std::ffi::{c_void, CString};
 use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 /// A TensorFlow Lite model used by the [`Interpreter`][crate::interpreter::Interpreter] to perform inference.
 pub struct Model<'a> {
@@ -25,6 +27,12 @@ pub struct Model<'a> {
     /// This reference is taken to guarantee that bytes
     /// must be immutable and outlive the model
     pub(crate) bytes: Option<&'a [u8]>,
+
+    /// The local file path of the model if initialized with a file.
+    pub(crate) filepath: Option<PathBuf>,
+
+    /// The fingerprint of the model data, computed on first use.
+    fingerprint: OnceLock<u64>,
 }
 
 impl Debug for Model<'_> {
@@ -57,6 +65,8 @@ impl Model<'_> {
             Ok(Model {
                 model_ptr,
                 bytes: None,
+                filepath: Some(PathBuf::from(filepath)),
+                fingerprint: OnceLock::new(),
             })
         }
     }
@@ -78,9 +88,48 @@ impl Model<'_> {
             Ok(Model {
                 model_ptr,
                 bytes: Some(bytes),
+                filepath: None,
+                fingerprint: OnceLock::new(),
             })
         }
     }
+
+    /// Returns the local file path of the model if it was created with [`Model::new()`].
+    pub fn filepath(&self) -> Option<&Path> {
+        self.filepath.as_deref()
+    }
+
+    /// Returns a 64-bit fingerprint of the model data.
+    ///
+    /// The fingerprint is the FNV-1a hash of the model bytes, so it is stable across
+    /// processes and platforms. For models created with [`Model::new()`], the file is read
+    /// when the fingerprint is requested for the first time.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the model file cannot be read.
+    pub fn fingerprint(&self) -> Result<u64> {
+        if let Some(fingerprint) = self.fingerprint.get() {
+            return Ok(*fingerprint);
+        }
+        let fingerprint = match (self.bytes, self.filepath.as_ref()) {
+            (Some(bytes), _) => fnv1a(bytes),
+            (None, Some(filepath)) => std::fs::read(filepath)
+                .map(|bytes| fnv1a(&bytes))
+                .map_err(|_| Error::new(ErrorKind::FailedToLoadModel))?,
+            (None, None) => return Err(Error::new(ErrorKind::FailedToLoadModel)),
+        };
+        Ok(*self.fingerprint.get_or_init(|| fingerprint))
+    }
+}
+
+/// Computes the 64-bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(PRIME)
+    })
 }
 
 impl Drop for Model<'_> {
@@ -111,4 +160,17 @@ mod tests {
         // We can mutate filepath here, because it is not borrowed.
         filepath.push('/');
     }
+
+    #[test]
+    fn test_model_fingerprint() {
+        let bytes = std::fs::read(MODEL_PATH).unwrap();
+        let from_bytes = Model::from_bytes(&bytes).expect("Cannot load model from bytes");
+        let from_path = Model::new(MODEL_PATH).expect("Cannot load model from file");
+        assert_eq!(
+            from_bytes.fingerprint().unwrap(),
+            from_path.fingerprint().unwrap()
+        );
+        assert_eq!(super::fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(super::fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
 }
@@ -0,0 +1,482 @@
+//! An [`InterpreterPool`] serving concurrent inference with interpreters of one [`Model`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::model::Model;
+//! use tflitec::pool::InterpreterPoolBuilder;
+//! use tflitec::tensor::Shape;
+//! let model = Model::new("tests/add.bin")?;
+//! let pool = InterpreterPoolBuilder::new(&model)
+//!     .size(4)
+//!     .configure(|builder| builder.thread_count(1))
+//!     .input_shape(0, Shape::new(vec![1, 8, 8, 3]))
+//!     .warmup(|interpreter| interpreter.invoke())
+//!     .build()?;
+//! std::thread::scope(|scope| {
+//!     for _ in 0..8 {
+//!         scope.spawn(|| {
+//!             let interpreter = pool.checkout();
+//!             interpreter.copy(&[1.0f32; 192][..], 0).unwrap();
+//!             interpreter.invoke().unwrap();
+//!         });
+//!     }
+//! });
+//! println!("utilization: {:.1}%", pool.statistics().utilization() * 100.0);
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::interpreter::{Configuration, Interpreter, InterpreterBuilder};
+use crate::model::Model;
+use crate::tensor::Shape;
+#[cfg(feature = "xnnpack")]
+use crate::xnnpack::{WeightsCache, WeightsCacheFinalization};
+use crate::{Error, ErrorKind, Result};
+
+/// A warmup run on every interpreter of a pool after its tensors are allocated.
+type Warmup<'a> = dyn Fn(&Interpreter<'a>) -> Result<()> + 'a;
+
+/// A builder of an [`InterpreterPool`].
+pub struct InterpreterPoolBuilder<'a> {
+    model: &'a Model<'a>,
+    size: usize,
+    configuration: Box<Configuration<'a>>,
+    input_shapes: Vec<(usize, Shape)>,
+    warmup: Option<Box<Warmup<'a>>>,
+    #[cfg(feature = "xnnpack")]
+    is_weights_cache_shared: bool,
+}
+
+impl Debug for InterpreterPoolBuilder<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("InterpreterPoolBuilder");
+        debug
+            .field("size", &self.size)
+            .field("input_shapes", &self.input_shapes)
+            .field("is_warmup_enabled", &self.warmup.is_some());
+        #[cfg(feature = "xnnpack")]
+        debug.field("is_weights_cache_shared", &self.is_weights_cache_shared);
+        debug.finish()
+    }
+}
+
+impl<'a> InterpreterPoolBuilder<'a> {
+    /// Creates a builder of a pool of interpreters of `model`.
+    ///
+    /// The pool has one interpreter per available CPU by default.
+    pub fn new(model: &'a Model<'a>) -> InterpreterPoolBuilder<'a> {
+        InterpreterPoolBuilder {
+            model,
+            size: std::thread::available_parallelism()
+                .map(|parallelism| parallelism.get())
+                .unwrap_or(1),
+            configuration: Box::new(|builder| builder),
+            input_shapes: Vec::new(),
+            warmup: None,
+            #[cfg(feature = "xnnpack")]
+            is_weights_cache_shared: false,
+        }
+    }
+
+    /// Sets the number of interpreters of the pool.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the configuration of the [`InterpreterBuilder`] of every interpreter.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(InterpreterBuilder<'a>) -> InterpreterBuilder<'a> + 'a,
+    {
+        self.configuration = Box::new(configure);
+        self
+    }
+
+    /// Resizes the input at the given `index` of every interpreter to `shape` before
+    /// its tensors are allocated.
+    pub fn input_shape(mut self, index: usize, shape: Shape) -> Self {
+        self.input_shapes.push((index, shape));
+        self
+    }
+
+    /// Runs `warmup` on every interpreter after its tensors are allocated, e.g. to invoke it
+    /// once so that the first request does not pay for lazy initialization.
+    pub fn warmup<F>(mut self, warmup: F) -> Self
+    where
+        F: Fn(&Interpreter<'a>) -> Result<()> + 'a,
+    {
+        self.warmup = Some(Box::new(warmup));
+        self
+    }
+
+    /// Shares one XNNPACK [`WeightsCache`] among the interpreters, so the packed weights
+    /// are stored once instead of once per interpreter.
+    ///
+    /// The cache is only used if the configuration enables the XNNPACK delegate. It is
+    /// finalized with [`WeightsCacheFinalization::Soft`] after all interpreters are built,
+    /// so interpreters may still be resized afterwards.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn shared_weights_cache(mut self, enabled: bool) -> Self {
+        self.is_weights_cache_shared = enabled;
+        self
+    }
+
+    /// Builds the interpreters, allocates their tensors and warms them up.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidOptions`] if the size is zero. Returns error if any
+    /// interpreter cannot be built, resized, allocated or warmed up.
+    pub fn build(self) -> Result<InterpreterPool<'a>> {
+        if self.size == 0 {
+            return Err(Error::new(ErrorKind::InvalidOptions(
+                "interpreter pool size must be positive",
+            )));
+        }
+        #[cfg(feature = "xnnpack")]
+        let weights_cache = if self.is_weights_cache_shared {
+            Some(WeightsCache::new()?)
+        } else {
+            None
+        };
+
+        let mut interpreters = Vec::with_capacity(self.size);
+        for _ in 0..self.size {
+            let builder = (self.configuration)(InterpreterBuilder::new(self.model));
+            #[cfg(feature = "xnnpack")]
+            let builder = match weights_cache.as_ref() {
+                Some(weights_cache) => builder.xnnpack_weights_cache(weights_cache.clone()),
+                None => builder,
+            };
+            interpreters.push(builder.build_allocated(&self.input_shapes)?);
+        }
+
+        // Weights are packed while the tensors are allocated, so the cache is finalized
+        // before any interpreter runs.
+        #[cfg(feature = "xnnpack")]
+        let is_xnnpack_enabled = interpreters.iter().any(|interpreter| {
+            interpreter
+                .options()
+                .map(|options| options.is_xnnpack_enabled)
+                .unwrap_or(false)
+        });
+        #[cfg(feature = "xnnpack")]
+        let weights_cache = match weights_cache {
+            Some(weights_cache) if is_xnnpack_enabled => {
+                weights_cache.finalize(WeightsCacheFinalization::Soft)?;
+                Some(weights_cache)
+            }
+            _ => None,
+        };
+        if let Some(warmup) = self.warmup.as_ref() {
+            for interpreter in &interpreters {
+                warmup(interpreter)?;
+            }
+        }
+        Ok(InterpreterPool {
+            size: self.size,
+            idle: Mutex::new(interpreters),
+            returned: Condvar::new(),
+            counters: Mutex::new(PoolCounters::default()),
+            created_at: Instant::now(),
+            #[cfg(feature = "xnnpack")]
+            weights_cache,
+        })
+    }
+}
+
+/// The statistics of an [`InterpreterPool`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PoolStatistics {
+    /// The number of interpreters of the pool.
+    pub size: usize,
+    /// The number of interpreters checked out now.
+    pub in_use: usize,
+    /// The number of completed checkouts.
+    pub checkouts: u64,
+    /// The total time spent waiting for an interpreter.
+    pub total_wait: Duration,
+    /// The longest time spent waiting for an interpreter.
+    pub max_wait: Duration,
+    /// The total time interpreters were checked out, excluding the current checkouts.
+    pub busy: Duration,
+    /// The time since the pool was built.
+    pub uptime: Duration,
+}
+
+impl PoolStatistics {
+    /// Returns the mean time spent waiting for an interpreter.
+    pub fn mean_wait(&self) -> Duration {
+        match self.checkouts {
+            0 => Duration::ZERO,
+            checkouts => Duration::from_secs_f64(self.total_wait.as_secs_f64() / checkouts as f64),
+        }
+    }
+
+    /// Returns the fraction of the capacity of the pool used since it was built,
+    /// in [0, 1].
+    pub fn utilization(&self) -> f64 {
+        let capacity = self.uptime.as_secs_f64() * self.size as f64;
+        if capacity > 0.0 {
+            (self.busy.as_secs_f64() / capacity).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The counters of an [`InterpreterPool`] updated on checkout and return.
+#[derive(Debug, Default)]
+struct PoolCounters {
+    in_use: usize,
+    checkouts: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+    busy: Duration,
+}
+
+/// A fixed set of interpreters of one [`Model`] which are checked out by one thread at
+/// a time.
+///
+/// [`Interpreter`] is not `Sync`, so a pool is the way to share interpreters among threads.
+/// Checked out interpreters return to the pool when their [`PooledInterpreter`] guard is
+/// dropped, keeping any resized inputs and the data of their tensors.
+pub struct InterpreterPool<'a> {
+    size: usize,
+    idle: Mutex<Vec<Interpreter<'a>>>,
+    returned: Condvar,
+    counters: Mutex<PoolCounters>,
+    created_at: Instant,
+    #[cfg(feature = "xnnpack")]
+    weights_cache: Option<WeightsCache>,
+}
+
+impl Debug for InterpreterPool<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterpreterPool")
+            .field("statistics", &self.statistics())
+            .finish()
+    }
+}
+
+impl<'a> InterpreterPool<'a> {
+    /// Checks out an interpreter, waiting until one is returned if all are in use.
+    pub fn checkout(&self) -> PooledInterpreter<'_, 'a> {
+        let started_at = Instant::now();
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(interpreter) = idle.pop() {
+                return self.guard(interpreter, started_at);
+            }
+            idle = self.returned.wait(idle).unwrap();
+        }
+    }
+
+    /// Checks out an interpreter, waiting at most `timeout` until one is returned.
+    ///
+    /// returns: [`None`] if no interpreter was returned in time.
+    pub fn checkout_timeout(&self, timeout: Duration) -> Option<PooledInterpreter<'_, 'a>> {
+        let started_at = Instant::now();
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(interpreter) = idle.pop() {
+                return Some(self.guard(interpreter, started_at));
+            }
+            let remaining = timeout.checked_sub(started_at.elapsed())?;
+            idle = self.returned.wait_timeout(idle, remaining).unwrap().0;
+        }
+    }
+
+    /// Checks out an interpreter if one is idle.
+    pub fn try_checkout(&self) -> Option<PooledInterpreter<'_, 'a>> {
+        let started_at = Instant::now();
+        let interpreter = self.idle.lock().unwrap().pop()?;
+        Some(self.guard(interpreter, started_at))
+    }
+
+    /// Returns the number of interpreters of the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of interpreters which are not checked out.
+    pub fn available(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Returns the statistics of the pool since it was built.
+    pub fn statistics(&self) -> PoolStatistics {
+        let counters = self.counters.lock().unwrap();
+        PoolStatistics {
+            size: self.size,
+            in_use: counters.in_use,
+            checkouts: counters.checkouts,
+            total_wait: counters.total_wait,
+            max_wait: counters.max_wait,
+            busy: counters.busy,
+            uptime: self.created_at.elapsed(),
+        }
+    }
+
+    /// Returns the XNNPACK weights cache shared by the interpreters, if any.
+    #[cfg(feature = "xnnpack")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xnnpack")))]
+    pub fn weights_cache(&self) -> Option<&WeightsCache> {
+        self.weights_cache.as_ref()
+    }
+
+    fn guard(
+        &self,
+        interpreter: Interpreter<'a>,
+        started_at: Instant,
+    ) -> PooledInterpreter<'_, 'a> {
+        let checked_out_at = Instant::now();
+        let wait = checked_out_at - started_at;
+        let mut counters = self.counters.lock().unwrap();
+        counters.in_use += 1;
+        counters.total_wait += wait;
+        counters.max_wait = counters.max_wait.max(wait);
+        PooledInterpreter {
+            pool: self,
+            interpreter: Some(interpreter),
+            checked_out_at,
+        }
+    }
+
+    fn give_back(&self, interpreter: Interpreter<'a>, checked_out_at: Instant) {
+        {
+            let mut counters = self.counters.lock().unwrap();
+            counters.in_use -= 1;
+            counters.checkouts += 1;
+            counters.busy += checked_out_at.elapsed();
+        }
+        self.idle.lock().unwrap().push(interpreter);
+        self.returned.notify_one();
+    }
+}
+
+/// An [`Interpreter`] checked out of an [`InterpreterPool`], which returns to the pool
+/// when dropped.
+pub struct PooledInterpreter<'p, 'a> {
+    pool: &'p InterpreterPool<'a>,
+    interpreter: Option<Interpreter<'a>>,
+    checked_out_at: Instant,
+}
+
+impl Debug for PooledInterpreter<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledInterpreter")
+            .field("interpreter", &self.interpreter)
+            .finish()
+    }
+}
+
+impl<'a> Deref for PooledInterpreter<'_, 'a> {
+    type Target = Interpreter<'a>;
+
+    fn deref(&self) -> &Interpreter<'a> {
+        self.interpreter
+            .as_ref()
+            .expect("the interpreter is only taken on drop")
+    }
+}
+
+impl Drop for PooledInterpreter<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(interpreter) = self.interpreter.take() {
+            self.pool.give_back(interpreter, self.checked_out_at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::model::Model;
+    use crate::pool::InterpreterPoolBuilder;
+    use crate::tensor::Shape;
+    use crate::ErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_interpreter_pool() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let err = InterpreterPoolBuilder::new(&model)
+            .size(0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+
+        let warmups = AtomicUsize::new(0);
+        let pool = InterpreterPoolBuilder::new(&model)
+            .size(2)
+            .input_shape(0, Shape::new(vec![10, 8, 8, 3]))
+            .warmup(|interpreter| {
+                warmups.fetch_add(1, Ordering::SeqCst);
+                interpreter.invoke()
+            })
+            .build()
+            .expect("Cannot build pool!");
+        assert_eq!(2, warmups.load(Ordering::SeqCst));
+        assert_eq!(2, pool.size());
+
+        let data = (0..1920).map(|x| x as f32).collect::<Vec<f32>>();
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let interpreter = pool.checkout();
+                    interpreter.copy(&data[..], 0).unwrap();
+                    interpreter.invoke().unwrap();
+                    assert_eq!(expected, interpreter.output(0).unwrap().data::<f32>());
+                });
+            }
+        });
+
+        let first = pool.try_checkout().unwrap();
+        let second = pool.checkout_timeout(Duration::from_millis(10)).unwrap();
+        assert_eq!(0, pool.available());
+        assert!(pool.try_checkout().is_none());
+        assert!(pool.checkout_timeout(Duration::from_millis(10)).is_none());
+        assert_eq!(2, pool.statistics().in_use);
+        drop(first);
+        drop(second);
+        assert_eq!(2, pool.available());
+
+        let statistics = pool.statistics();
+        assert_eq!(0, statistics.in_use);
+        assert_eq!(6, statistics.checkouts);
+        assert!(statistics.utilization() <= 1.0);
+    }
+
+    #[cfg(feature = "xnnpack")]
+    #[test]
+    fn test_interpreter_pool_shared_weights_cache() {
+        use crate::xnnpack::WeightsCacheFinalization;
+
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let pool = InterpreterPoolBuilder::new(&model)
+            .size(2)
+            .configure(|builder| builder.add_xnnpack_delegate())
+            .shared_weights_cache(true)
+            .build()
+            .expect("Cannot build pool!");
+        let weights_cache = pool.weights_cache().unwrap();
+        assert_eq!(
+            Some(WeightsCacheFinalization::Soft),
+            weights_cache.finalization()
+        );
+        let interpreter = pool.checkout();
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        interpreter.copy(&data[..], 0).unwrap();
+        interpreter.invoke().unwrap();
+    }
+}
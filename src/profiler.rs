@@ -0,0 +1,594 @@
+//! A [`Profiler`] recording the time spent in every node of an
+//! [`Interpreter`][crate::interpreter::Interpreter].
+//!
+//! # Examples
+//!
+//! ```
+//! use tflitec::interpreter::InterpreterBuilder;
+//! use tflitec::model::Model;
+//! let model = Model::new("tests/add.bin")?;
+//! let interpreter = InterpreterBuilder::new(&model).profiling(true).build()?;
+//! interpreter.allocate_tensors()?;
+//! for _ in 0..10 {
+//!     interpreter.invoke()?;
+//! }
+//! let profiler = interpreter.profiler().expect("profiling is enabled");
+//! println!("{}", profiler.report());
+//! // Open the trace in chrome://tracing or Perfetto
+//! let trace = profiler.chrome_trace();
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::CStr;
+use std::fmt::{Debug, Display, Formatter, Write as _};
+use std::os::raw::{c_char, c_void};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::minimal_bindings::*;
+
+/// The maximum number of events kept for [`Profiler::chrome_trace()`].
+///
+/// Later events are still aggregated in the [`ProfileReport`].
+pub const MAX_TRACE_EVENTS: usize = 100_000;
+
+/// The time spent in a node of the graph over all recorded invocations.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NodeProfile {
+    /// The index of the subgraph of the node.
+    pub subgraph: usize,
+    /// The index of the node in its subgraph.
+    pub node: usize,
+    /// The name of the operator, such as `CONV_2D`, or of the delegate kernel.
+    pub op: String,
+    /// The number of times the node ran.
+    pub count: usize,
+    /// The total time spent in the node.
+    pub total: Duration,
+    /// The shortest run of the node.
+    pub min: Duration,
+    /// The longest run of the node.
+    pub max: Duration,
+}
+
+impl NodeProfile {
+    /// Returns the average time spent in a run of the node.
+    pub fn mean(&self) -> Duration {
+        mean(self.total, self.count)
+    }
+}
+
+/// The time spent in all nodes of an operator over all recorded invocations.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OpProfile {
+    /// The name of the operator.
+    pub op: String,
+    /// The number of nodes of the operator.
+    pub nodes: usize,
+    /// The number of times a node of the operator ran.
+    pub count: usize,
+    /// The total time spent in the nodes of the operator.
+    pub total: Duration,
+}
+
+impl OpProfile {
+    /// Returns the average time spent in a run of a node of the operator.
+    pub fn mean(&self) -> Duration {
+        mean(self.total, self.count)
+    }
+}
+
+/// Aggregated timings of the invocations recorded by a [`Profiler`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProfileReport {
+    /// The number of recorded invocations.
+    pub runs: usize,
+    /// The total time spent in the recorded invocations.
+    pub total: Duration,
+    /// The nodes in order of subgraph and node index.
+    pub nodes: Vec<NodeProfile>,
+    /// The operators, the most expensive first.
+    pub ops: Vec<OpProfile>,
+}
+
+impl Display for ProfileReport {
+    /// Formats the report as a table of operators followed by a table of nodes.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let node_total = self.nodes.iter().map(|node| node.total).sum::<Duration>();
+        let share = |total: Duration| {
+            if node_total.is_zero() {
+                0.0
+            } else {
+                100.0 * total.as_secs_f64() / node_total.as_secs_f64()
+            }
+        };
+        let op_width = self
+            .ops
+            .iter()
+            .map(|op| op.op.len())
+            .chain(Some(2))
+            .max()
+            .unwrap_or(0);
+        writeln!(
+            f,
+            "{} runs, {:.3} ms per run",
+            self.runs,
+            millis(mean(self.total, self.runs))
+        )?;
+        writeln!(
+            f,
+            "{:<w$}  {:>5}  {:>7}  {:>12}  {:>12}  {:>6}",
+            "op",
+            "nodes",
+            "count",
+            "total ms",
+            "mean ms",
+            "%",
+            w = op_width
+        )?;
+        for op in &self.ops {
+            writeln!(
+                f,
+                "{:<w$}  {:>5}  {:>7}  {:>12.3}  {:>12.3}  {:>6.2}",
+                op.op,
+                op.nodes,
+                op.count,
+                millis(op.total),
+                millis(op.mean()),
+                share(op.total),
+                w = op_width
+            )?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "{:>8}  {:>5}  {:<w$}  {:>7}  {:>12}  {:>12}  {:>12}  {:>6}",
+            "subgraph",
+            "node",
+            "op",
+            "count",
+            "mean ms",
+            "min ms",
+            "max ms",
+            "%",
+            w = op_width
+        )?;
+        for node in &self.nodes {
+            write!(
+                f,
+                "\n{:>8}  {:>5}  {:<w$}  {:>7}  {:>12.3}  {:>12.3}  {:>12.3}  {:>6.2}",
+                node.subgraph,
+                node.node,
+                node.op,
+                node.count,
+                millis(node.mean()),
+                millis(node.min),
+                millis(node.max),
+                share(node.total),
+                w = op_width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn mean(total: Duration, count: usize) -> Duration {
+    if count == 0 {
+        Duration::ZERO
+    } else {
+        total / count as u32
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e3
+}
+
+/// An event of the Chrome trace.
+#[derive(Debug, Clone)]
+struct TraceEvent {
+    name: String,
+    /// The index of the invocation the event belongs to.
+    run: usize,
+    /// The subgraph and node of the event, or [`None`] for an invocation.
+    node: Option<(usize, usize)>,
+    /// The start of the event relative to the creation of the profiler.
+    start: Duration,
+    duration: Duration,
+}
+
+/// An operator event which began but has not ended yet.
+#[derive(Debug)]
+struct PendingEvent {
+    op: String,
+    subgraph: usize,
+    node: usize,
+    start: Instant,
+}
+
+#[derive(Debug)]
+struct ProfilerState {
+    epoch: Instant,
+    runs: usize,
+    total: Duration,
+    run_start: Option<Instant>,
+    next_handle: u32,
+    pending: HashMap<u32, PendingEvent>,
+    nodes: BTreeMap<(usize, usize), NodeProfile>,
+    trace: Vec<TraceEvent>,
+}
+
+impl ProfilerState {
+    fn record(&mut self, op: String, subgraph: usize, node: usize, start: Instant, end: Instant) {
+        let duration = end.saturating_duration_since(start);
+        if self.trace.len() < MAX_TRACE_EVENTS {
+            self.trace.push(TraceEvent {
+                name: op.clone(),
+                run: self.runs.saturating_sub(1),
+                node: Some((subgraph, node)),
+                start: start.saturating_duration_since(self.epoch),
+                duration,
+            });
+        }
+        let profile = self
+            .nodes
+            .entry((subgraph, node))
+            .or_insert_with(|| NodeProfile {
+                subgraph,
+                node,
+                op,
+                count: 0,
+                total: Duration::ZERO,
+                min: Duration::MAX,
+                max: Duration::ZERO,
+            });
+        profile.count += 1;
+        profile.total += duration;
+        profile.min = profile.min.min(duration);
+        profile.max = profile.max.max(duration);
+    }
+}
+
+/// Records the time spent in every node of the graph, for each invocation of an
+/// [`Interpreter`][crate::interpreter::Interpreter].
+///
+/// The profiler is enabled with
+/// [`InterpreterBuilder::profiling()`][crate::interpreter::InterpreterBuilder::profiling()],
+/// and it is attached to the interpreter through the TensorFlow Lite telemetry profiler.
+/// Nodes claimed by a delegate are reported as a single node running the delegate kernel.
+pub struct Profiler {
+    /// The recorded events, which are boxed since their address is the profiler user data.
+    state: Box<Mutex<ProfilerState>>,
+
+    /// The profiler passed to TensorFlow Lite C, which is boxed to keep its address.
+    telemetry: Box<TfLiteTelemetryProfilerStruct>,
+}
+
+unsafe impl Send for Profiler {}
+unsafe impl Sync for Profiler {}
+
+impl Debug for Profiler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Profiler").finish()
+    }
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Profiler {
+        let state = Box::new(Mutex::new(ProfilerState {
+            epoch: Instant::now(),
+            runs: 0,
+            total: Duration::ZERO,
+            run_start: None,
+            next_handle: 0,
+            pending: HashMap::new(),
+            nodes: BTreeMap::new(),
+            trace: Vec::new(),
+        }));
+        let telemetry = Box::new(TfLiteTelemetryProfilerStruct {
+            data: state.as_ref() as *const Mutex<ProfilerState> as *mut c_void,
+            report_telemetry_event: Some(report_telemetry_event),
+            report_telemetry_op_event: Some(report_telemetry_op_event),
+            report_settings: Some(report_settings),
+            report_begin_op_invoke_event: Some(report_begin_op_invoke_event),
+            report_end_op_invoke_event: Some(report_end_op_invoke_event),
+            report_op_invoke_event: Some(report_op_invoke_event),
+        });
+        Profiler { state, telemetry }
+    }
+
+    /// Returns the underlying [`TfLiteTelemetryProfilerStruct`] C pointer.
+    pub(crate) fn as_ptr(&self) -> *mut TfLiteTelemetryProfilerStruct {
+        self.telemetry.as_ref() as *const TfLiteTelemetryProfilerStruct as *mut _
+    }
+
+    /// Marks the start of an invocation.
+    pub(crate) fn begin_run(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.runs += 1;
+        state.run_start = Some(Instant::now());
+    }
+
+    /// Marks the end of the invocation started last.
+    pub(crate) fn end_run(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(start) = state.run_start.take() {
+            let duration = start.elapsed();
+            state.total += duration;
+            if state.trace.len() < MAX_TRACE_EVENTS {
+                let event = TraceEvent {
+                    name: "Invoke".to_string(),
+                    run: state.runs - 1,
+                    node: None,
+                    start: start.saturating_duration_since(state.epoch),
+                    duration,
+                };
+                state.trace.push(event);
+            }
+        }
+    }
+
+    /// Returns the timings aggregated by node and by operator.
+    pub fn report(&self) -> ProfileReport {
+        let state = self.state.lock().unwrap();
+        let nodes = state.nodes.values().cloned().collect::<Vec<NodeProfile>>();
+        let mut ops = BTreeMap::<&str, OpProfile>::new();
+        for node in &nodes {
+            let op = ops.entry(&node.op).or_insert_with(|| OpProfile {
+                op: node.op.clone(),
+                nodes: 0,
+                count: 0,
+                total: Duration::ZERO,
+            });
+            op.nodes += 1;
+            op.count += node.count;
+            op.total += node.total;
+        }
+        let mut ops = ops.into_values().collect::<Vec<OpProfile>>();
+        ops.sort_by_key(|op| std::cmp::Reverse(op.total));
+        ProfileReport {
+            runs: state.runs,
+            total: state.total,
+            nodes,
+            ops,
+        }
+    }
+
+    /// Returns the recorded events in the Chrome trace event format, which can be
+    /// opened in `chrome://tracing` or Perfetto.
+    ///
+    /// Events of every node and invocation are included, up to [`MAX_TRACE_EVENTS`].
+    /// Nodes of subgraph `n` are on thread `n + 1`, below the invocations on thread 0.
+    pub fn chrome_trace(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut json = String::from("{\"traceEvents\":[");
+        for (index, event) in state.trace.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let (tid, args) = match event.node {
+                Some((subgraph, node)) => (
+                    subgraph + 1,
+                    format!(
+                        "{{\"run\":{},\"subgraph\":{},\"node\":{}}}",
+                        event.run, subgraph, node
+                    ),
+                ),
+                None => (0, format!("{{\"run\":{}}}", event.run)),
+            };
+            let _ = write!(
+                json,
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                 \"pid\":0,\"tid\":{},\"args\":{}}}",
+                escape_json(&event.name),
+                if event.node.is_some() { "op" } else { "invoke" },
+                event.start.as_secs_f64() * 1e6,
+                event.duration.as_secs_f64() * 1e6,
+                tid,
+                args
+            );
+        }
+        json.push_str("],\"displayTimeUnit\":\"ms\"}");
+        json
+    }
+
+    /// Forgets all recorded invocations.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.runs = 0;
+        state.total = Duration::ZERO;
+        state.nodes.clear();
+        state.trace.clear();
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+unsafe fn profiler_state<'a>(
+    profiler: *mut TfLiteTelemetryProfilerStruct,
+) -> Option<std::sync::MutexGuard<'a, ProfilerState>> {
+    let state = &*((*profiler).data as *const Mutex<ProfilerState>);
+    state.lock().ok()
+}
+
+unsafe fn op_name(op_name: *const c_char) -> String {
+    if op_name.is_null() {
+        "UNKNOWN".to_string()
+    } else {
+        CStr::from_ptr(op_name).to_string_lossy().into_owned()
+    }
+}
+
+unsafe extern "C" fn report_telemetry_event(
+    _profiler: *mut TfLiteTelemetryProfilerStruct,
+    _event_name: *const c_char,
+    _status: u64,
+) {
+}
+
+unsafe extern "C" fn report_telemetry_op_event(
+    _profiler: *mut TfLiteTelemetryProfilerStruct,
+    _event_name: *const c_char,
+    _op_idx: i64,
+    _subgraph_idx: i64,
+    _status: u64,
+) {
+}
+
+unsafe extern "C" fn report_settings(
+    _profiler: *mut TfLiteTelemetryProfilerStruct,
+    _setting_name: *const c_char,
+    _settings: *const TfLiteTelemetrySettings,
+) {
+}
+
+unsafe extern "C" fn report_begin_op_invoke_event(
+    profiler: *mut TfLiteTelemetryProfilerStruct,
+    op_name_ptr: *const c_char,
+    op_idx: i64,
+    subgraph_idx: i64,
+) -> u32 {
+    let start = Instant::now();
+    let mut state = match profiler_state(profiler) {
+        Some(state) => state,
+        None => return TFLITE_TELEMETRY_INVALID_EVENT_HANDLE,
+    };
+    let handle = state.next_handle;
+    state.next_handle = match handle.wrapping_add(1) {
+        TFLITE_TELEMETRY_INVALID_EVENT_HANDLE => 0,
+        next => next,
+    };
+    let event = PendingEvent {
+        op: op_name(op_name_ptr),
+        subgraph: subgraph_idx.max(0) as usize,
+        node: op_idx.max(0) as usize,
+        start,
+    };
+    state.pending.insert(handle, event);
+    handle
+}
+
+unsafe extern "C" fn report_end_op_invoke_event(
+    profiler: *mut TfLiteTelemetryProfilerStruct,
+    event_handle: u32,
+) {
+    let end = Instant::now();
+    if let Some(mut state) = profiler_state(profiler) {
+        if let Some(event) = state.pending.remove(&event_handle) {
+            state.record(event.op, event.subgraph, event.node, event.start, end);
+        }
+    }
+}
+
+/// Records an event measured by TensorFlow Lite, with `elapsed_time` in microseconds.
+unsafe extern "C" fn report_op_invoke_event(
+    profiler: *mut TfLiteTelemetryProfilerStruct,
+    op_name_ptr: *const c_char,
+    elapsed_time: u64,
+    op_idx: i64,
+    subgraph_idx: i64,
+) {
+    let end = Instant::now();
+    let start = end
+        .checked_sub(Duration::from_micros(elapsed_time))
+        .unwrap_or(end);
+    if let Some(mut state) = profiler_state(profiler) {
+        let op = op_name(op_name_ptr);
+        let (subgraph, node) = (subgraph_idx.max(0) as usize, op_idx.max(0) as usize);
+        state.record(op, subgraph, node, start, end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::interpreter::InterpreterBuilder;
+    use crate::model::Model;
+    use crate::profiler::Profiler;
+
+    #[test]
+    fn test_profiler_events() {
+        let profiler = Profiler::new();
+        let telemetry = profiler.as_ptr();
+        let begin = unsafe { (*telemetry).report_begin_op_invoke_event.unwrap() };
+        let end = unsafe { (*telemetry).report_end_op_invoke_event.unwrap() };
+        let add = b"ADD\0".as_ptr() as *const std::os::raw::c_char;
+        let quoted = b"MY\"OP\0".as_ptr() as *const std::os::raw::c_char;
+        for _ in 0..2 {
+            profiler.begin_run();
+            unsafe {
+                let first = begin(telemetry, add, 0, 0);
+                end(telemetry, first);
+                let second = begin(telemetry, add, 1, 0);
+                let inner = begin(telemetry, quoted, 0, 1);
+                end(telemetry, inner);
+                end(telemetry, second);
+            }
+            profiler.end_run();
+        }
+
+        let report = profiler.report();
+        assert_eq!(2, report.runs);
+        let nodes = report
+            .nodes
+            .iter()
+            .map(|node| (node.subgraph, node.node, node.op.as_str(), node.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![(0, 0, "ADD", 2), (0, 1, "ADD", 2), (1, 0, "MY\"OP", 2)],
+            nodes
+        );
+        let add_op = report.ops.iter().find(|op| op.op == "ADD").unwrap();
+        assert_eq!((2, 4), (add_op.nodes, add_op.count));
+        assert!(report.to_string().starts_with("2 runs"));
+
+        let trace = profiler.chrome_trace();
+        assert!(trace.starts_with("{\"traceEvents\":[{"));
+        assert_eq!(8, trace.matches("\"ph\":\"X\"").count());
+        assert_eq!(2, trace.matches("\"name\":\"Invoke\"").count());
+        assert!(trace.contains("\"name\":\"MY\\\"OP\""));
+
+        profiler.reset();
+        assert_eq!(0, profiler.report().runs);
+        assert!(profiler.report().nodes.is_empty());
+    }
+
+    #[test]
+    fn test_interpreter_profiling() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let interpreter = InterpreterBuilder::new(&model)
+            .build()
+            .expect("Cannot create interpreter!");
+        assert!(interpreter.profiler().is_none());
+
+        let interpreter = InterpreterBuilder::new(&model)
+            .profiling(true)
+            .build()
+            .expect("Cannot create interpreter!");
+        interpreter
+            .allocate_tensors()
+            .expect("Cannot allocate tensors");
+        for _ in 0..3 {
+            assert!(interpreter.invoke().is_ok());
+        }
+        let report = interpreter.profiler().unwrap().report();
+        assert_eq!(3, report.runs);
+        assert_eq!(2, report.nodes.len());
+        assert!(report.nodes.iter().all(|node| node.count == 3));
+        assert_eq!("ADD", report.ops[0].op);
+        assert_eq!(6, report.ops[0].count);
+    }
+}
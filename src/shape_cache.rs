@@ -0,0 +1,280 @@
+//! A [`ShapeCache`] keeping one allocated interpreter per recently used set of input shapes.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::model::Model;
+//! use tflitec::shape_cache::ShapeCacheBuilder;
+//! use tflitec::tensor::Shape;
+//! let model = Model::new("tests/add.bin")?;
+//! let mut cache = ShapeCacheBuilder::new(&model)
+//!     .capacity(2)
+//!     .configure(|builder| builder.thread_count(1))
+//!     .build()?;
+//! for batch_size in [1usize, 2, 1, 2].iter() {
+//!     let data = vec![1.0f32; batch_size * 192];
+//!     let interpreter = cache.run(&[(Shape::new(vec![*batch_size, 8, 8, 3]), &data[..])])?;
+//!     println!("{:?}", interpreter.output(0)?.data::<f32>());
+//! }
+//! assert_eq!(2, cache.statistics().misses);
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::fmt::{Debug, Formatter};
+
+use crate::interpreter::{Configuration, Interpreter, InterpreterBuilder};
+use crate::model::Model;
+use crate::tensor::{Shape, TensorElement};
+use crate::{Error, ErrorKind, Result};
+
+/// A builder of a [`ShapeCache`].
+pub struct ShapeCacheBuilder<'a> {
+    model: &'a Model<'a>,
+    capacity: usize,
+    configuration: Box<Configuration<'a>>,
+}
+
+impl Debug for ShapeCacheBuilder<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShapeCacheBuilder")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<'a> ShapeCacheBuilder<'a> {
+    /// Creates a builder of a cache of interpreters of `model`.
+    ///
+    /// The cache keeps the interpreters of at most 4 sets of input shapes by default.
+    pub fn new(model: &'a Model<'a>) -> ShapeCacheBuilder<'a> {
+        ShapeCacheBuilder {
+            model,
+            capacity: 4,
+            configuration: Box::new(|builder| builder),
+        }
+    }
+
+    /// Sets the largest number of interpreters kept by the cache.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the configuration of the [`InterpreterBuilder`] of every interpreter.
+    pub fn configure<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(InterpreterBuilder<'a>) -> InterpreterBuilder<'a> + 'a,
+    {
+        self.configuration = Box::new(configure);
+        self
+    }
+
+    /// Builds an empty cache.
+    ///
+    /// Interpreters are only built when a set of input shapes is first used.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidOptions`] if the capacity is zero.
+    pub fn build(self) -> Result<ShapeCache<'a>> {
+        if self.capacity == 0 {
+            return Err(Error::new(ErrorKind::InvalidOptions(
+                "shape cache capacity must be positive",
+            )));
+        }
+        Ok(ShapeCache {
+            model: self.model,
+            capacity: self.capacity,
+            configuration: self.configuration,
+            entries: Vec::with_capacity(self.capacity),
+            statistics: ShapeCacheStatistics::default(),
+        })
+    }
+}
+
+/// The counters of a [`ShapeCache`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct ShapeCacheStatistics {
+    /// The number of times an interpreter allocated for the input shapes was reused.
+    pub hits: u64,
+    /// The number of times an interpreter was built and allocated for the input shapes.
+    pub misses: u64,
+    /// The number of interpreters dropped to keep the cache within its capacity.
+    pub evictions: u64,
+}
+
+/// An interpreter allocated for a set of input shapes.
+struct CacheEntry<'a> {
+    shapes: Vec<Shape>,
+    interpreter: Interpreter<'a>,
+}
+
+/// Interpreters of one [`Model`] allocated for the recently used sets of input shapes.
+///
+/// Switching an [`Interpreter`] between input shapes requires
+/// [`Interpreter::resize_input()`] and [`Interpreter::allocate_tensors()`] on every switch.
+/// The cache instead keeps an interpreter per set of input shapes and selects the one
+/// matching the shapes of each run, so alternating between a few shapes, e.g. variable
+/// length audio windows, only allocates once per shape. When the cache is full, the least
+/// recently used interpreter is dropped.
+pub struct ShapeCache<'a> {
+    model: &'a Model<'a>,
+    capacity: usize,
+    configuration: Box<Configuration<'a>>,
+    /// The entries, from the least to the most recently used.
+    entries: Vec<CacheEntry<'a>>,
+    statistics: ShapeCacheStatistics,
+}
+
+impl Debug for ShapeCache<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShapeCache")
+            .field("capacity", &self.capacity)
+            .field("shapes", &self.shapes())
+            .field("statistics", &self.statistics)
+            .finish()
+    }
+}
+
+impl<'a> ShapeCache<'a> {
+    /// Returns the interpreter allocated for the given input `shapes`, building it and
+    /// allocating its tensors if it is not cached.
+    ///
+    /// The returned interpreter becomes the most recently used one.
+    ///
+    /// # Arguments
+    ///
+    /// * `shapes`: The shape of every input tensor, in the order of the inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InputCountMismatch`] if the number of shapes does not match
+    /// the number of inputs. Returns error if the interpreter cannot be built, resized or
+    /// allocated.
+    pub fn prepare(&mut self, shapes: &[Shape]) -> Result<&Interpreter<'a>> {
+        match self.entries.iter().position(|entry| entry.shapes == shapes) {
+            Some(position) => {
+                let entry = self.entries.remove(position);
+                self.entries.push(entry);
+                self.statistics.hits += 1;
+            }
+            None => {
+                let interpreter =
+                    (self.configuration)(InterpreterBuilder::new(self.model)).build()?;
+                let input_count = interpreter.input_tensor_count();
+                if shapes.len() != input_count {
+                    return Err(Error::new(ErrorKind::InputCountMismatch(
+                        shapes.len(),
+                        input_count,
+                    )));
+                }
+                for (index, shape) in shapes.iter().enumerate() {
+                    interpreter.resize_input(index, shape.clone())?;
+                }
+                interpreter.allocate_tensors()?;
+                if self.entries.len() == self.capacity {
+                    self.entries.remove(0);
+                    self.statistics.evictions += 1;
+                }
+                self.entries.push(CacheEntry {
+                    shapes: shapes.to_vec(),
+                    interpreter,
+                });
+                self.statistics.misses += 1;
+            }
+        }
+        Ok(&self.entries[self.entries.len() - 1].interpreter)
+    }
+
+    /// Copies the data of every input to the interpreter allocated for their shapes and
+    /// invokes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs`: The shape and data of every input tensor, in the order of the inputs.
+    ///
+    /// returns: The interpreter holding the outputs of the invocation.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the interpreter cannot be prepared (see [`ShapeCache::prepare()`]),
+    /// byte count of any data does not match the buffer size of its input tensor
+    /// or the invocation fails.
+    pub fn run<T: TensorElement>(&mut self, inputs: &[(Shape, &[T])]) -> Result<&Interpreter<'a>> {
+        let shapes = inputs
+            .iter()
+            .map(|(shape, _)| shape.clone())
+            .collect::<Vec<Shape>>();
+        let interpreter = self.prepare(&shapes)?;
+        for (index, (_, data)) in inputs.iter().enumerate() {
+            interpreter.copy(data, index)?;
+        }
+        interpreter.invoke()?;
+        Ok(interpreter)
+    }
+
+    /// Returns the sets of input shapes of the cached interpreters, from the least to
+    /// the most recently used.
+    pub fn shapes(&self) -> Vec<&[Shape]> {
+        self.entries.iter().map(|entry| &entry.shapes[..]).collect()
+    }
+
+    /// Returns the largest number of interpreters kept by the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the counters of the cache.
+    pub fn statistics(&self) -> ShapeCacheStatistics {
+        self.statistics
+    }
+
+    /// Drops every cached interpreter.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::MODEL_PATH;
+    use crate::model::Model;
+    use crate::shape_cache::{ShapeCacheBuilder, ShapeCacheStatistics};
+    use crate::tensor::Shape;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_shape_cache() {
+        let model = Model::new(MODEL_PATH).expect("Cannot load model from file!");
+        let mut cache = ShapeCacheBuilder::new(&model)
+            .capacity(2)
+            .build()
+            .expect("Cannot build cache!");
+
+        for batch_size in [1usize, 2, 1, 2, 3, 1].iter() {
+            let data = (0..batch_size * 192)
+                .map(|x| x as f32)
+                .collect::<Vec<f32>>();
+            let shape = Shape::new(vec![*batch_size, 8, 8, 3]);
+            let interpreter = cache.run(&[(shape, &data[..])]).expect("Cannot run");
+            let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+            let output = interpreter.output(0).unwrap();
+            assert_eq!(&vec![*batch_size, 8, 8, 3], output.shape().dimensions());
+            assert_eq!(&expected[..], output.data::<f32>());
+        }
+        assert_eq!(
+            ShapeCacheStatistics {
+                hits: 2,
+                misses: 4,
+                evictions: 2,
+            },
+            cache.statistics()
+        );
+        let shapes = cache.shapes();
+        assert_eq!(2, shapes.len());
+        assert_eq!(&vec![1, 8, 8, 3], shapes[1][0].dimensions());
+
+        let err = cache.prepare(&[]).err().unwrap();
+        assert_eq!(ErrorKind::InputCountMismatch(0, 1), err.kind());
+        assert!(ShapeCacheBuilder::new(&model).capacity(0).build().is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! A [`StreamingSession`] running models which pass recurrent state through explicit
+//! input and output tensors.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tflitec::interpreter::Interpreter;
+//! use tflitec::model::Model;
+//! use tflitec::streaming::StreamingSession;
+//! let model = Model::new("stateful_model.tflite")?;
+//! let interpreter = Interpreter::new(&model, None)?;
+//! let session = StreamingSession::new(interpreter, &[("state_in", "state_out")])?;
+//! for frame in [[0.0f32; 4], [1.0; 4], [2.0; 4]].iter() {
+//!     session.copy(&frame[..], 0)?;
+//!     session.invoke()?;
+//!     println!("{:?}", session.output(0)?.data::<f32>());
+//! }
+//! session.reset()?;
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use std::fmt::{Debug, Formatter};
+
+use crate::interpreter::{InputData, Interpreter};
+use crate::tensor::Tensor;
+use crate::{Error, ErrorKind, Result};
+
+/// An input and output tensor pair holding the recurrent state of a model.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct StatePair {
+    /// The index of the state input among the inputs of the interpreter.
+    input: usize,
+    /// The index of the state output among the outputs of the interpreter.
+    output: usize,
+}
+
+/// An [`Interpreter`] which feeds the state outputs of every invocation back into
+/// the state inputs of the next one.
+///
+/// Only the inputs and outputs which are not part of a state pair are exposed, and they
+/// are indexed in the order of the interpreter without the state tensors. State inputs
+/// start as zeros and are set back to zeros by [`StreamingSession::reset()`].
+pub struct StreamingSession<'a> {
+    interpreter: Interpreter<'a>,
+    states: Vec<StatePair>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+impl Debug for StreamingSession<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingSession")
+            .field("interpreter", &self.interpreter)
+            .field("states", &self.states)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .finish()
+    }
+}
+
+impl<'a> StreamingSession<'a> {
+    /// Creates a session, allocating the tensors of `interpreter` and resetting the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `interpreter`: The interpreter, whose inputs must already have their final shapes.
+    /// * `states`: The names of the state input and state output tensors of every pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidStatePair`] if a name is not an input or output of
+    /// `interpreter`, a tensor is used by two pairs or the tensors of a pair differ in
+    /// data type or shape. Returns error if allocating the tensors fails.
+    pub fn new(interpreter: Interpreter<'a>, states: &[(&str, &str)]) -> Result<Self> {
+        interpreter.allocate_tensors()?;
+        let input_names = (0..interpreter.input_tensor_count())
+            .map(|index| Ok(interpreter.input(index)?.name().to_owned()))
+            .collect::<Result<Vec<String>>>()?;
+        let output_names = (0..interpreter.output_tensor_count())
+            .map(|index| Ok(interpreter.output(index)?.name().to_owned()))
+            .collect::<Result<Vec<String>>>()?;
+
+        let mut pairs: Vec<StatePair> = Vec::with_capacity(states.len());
+        for (input_name, output_name) in states {
+            let input = input_names
+                .iter()
+                .position(|name| name == input_name)
+                .ok_or_else(|| invalid_state_pair("state input is not an input of the model"))?;
+            let output = output_names
+                .iter()
+                .position(|name| name == output_name)
+                .ok_or_else(|| invalid_state_pair("state output is not an output of the model"))?;
+            if pairs
+                .iter()
+                .any(|pair| pair.input == input || pair.output == output)
+            {
+                return Err(invalid_state_pair("tensor is used by two state pairs"));
+            }
+            let input_tensor = interpreter.input(input)?;
+            let output_tensor = interpreter.output(output)?;
+            if input_tensor.data_type() != output_tensor.data_type()
+                || input_tensor.shape() != output_tensor.shape()
+            {
+                return Err(invalid_state_pair(
+                    "state input and output differ in data type or shape",
+                ));
+            }
+            pairs.push(StatePair { input, output });
+        }
+
+        let inputs = (0..input_names.len())
+            .filter(|index| pairs.iter().all(|pair| pair.input != *index))
+            .collect();
+        let outputs = (0..output_names.len())
+            .filter(|index| pairs.iter().all(|pair| pair.output != *index))
+            .collect();
+        let session = StreamingSession {
+            interpreter,
+            states: pairs,
+            inputs,
+            outputs,
+        };
+        session.reset()?;
+        Ok(session)
+    }
+
+    /// Returns the number of inputs which are not state inputs.
+    pub fn input_tensor_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Returns the number of outputs which are not state outputs.
+    pub fn output_tensor_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Returns the input [`Tensor`] at the given `index` among the inputs which are not
+    /// state inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if given index is not in [0, [`StreamingSession::input_tensor_count()`]).
+    pub fn input(&self, index: usize) -> Result<Tensor<'_>> {
+        self.interpreter.input(map_index(&self.inputs, index)?)
+    }
+
+    /// Returns the output [`Tensor`] at the given `index` among the outputs which are not
+    /// state outputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if given index is not in [0, [`StreamingSession::output_tensor_count()`]).
+    pub fn output(&self, index: usize) -> Result<Tensor<'_>> {
+        self.interpreter.output(map_index(&self.outputs, index)?)
+    }
+
+    /// Copies the given `data` to the input [`Tensor`] at the given `index` among
+    /// the inputs which are not state inputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if given index is not in [0, [`StreamingSession::input_tensor_count()`])
+    /// or byte count of the data does not match the buffer size of the input tensor.
+    pub fn copy<D: InputData + ?Sized>(&self, data: &D, index: usize) -> Result<()> {
+        self.interpreter.copy(data, map_index(&self.inputs, index)?)
+    }
+
+    /// Invokes the interpreter and copies the state outputs into the state inputs
+    /// for the next invocation.
+    ///
+    /// Every state output is read before any state input is written, so the state is left
+    /// unchanged if the invocation or reading a state output fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the invocation fails, or TensorFlow Lite C fails to read a state
+    /// output or to copy into a state input.
+    pub fn invoke(&self) -> Result<()> {
+        self.interpreter.invoke()?;
+        let outputs = self
+            .states
+            .iter()
+            .map(|pair| Ok(self.interpreter.output(pair.output)?.data::<u8>().to_vec()))
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+        for (pair, output) in self.states.iter().zip(&outputs) {
+            self.interpreter.copy(&output[..], pair.input)?;
+        }
+        Ok(())
+    }
+
+    /// Sets all state inputs to zeros, so that the next invocation starts a new stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if TensorFlow Lite C fails to copy into a state input.
+    pub fn reset(&self) -> Result<()> {
+        for pair in &self.states {
+            let byte_size = self.interpreter.input(pair.input)?.data::<u8>().len();
+            self.interpreter
+                .copy(&vec![0u8; byte_size][..], pair.input)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the underlying [`Interpreter`].
+    pub fn interpreter(&self) -> &Interpreter<'a> {
+        &self.interpreter
+    }
+
+    /// Consumes the session and returns the underlying [`Interpreter`].
+    pub fn into_interpreter(self) -> Interpreter<'a> {
+        self.interpreter
+    }
+}
+
+/// Maps an index among the exposed tensors to an index of the interpreter.
+fn map_index(indices: &[usize], index: usize) -> Result<usize> {
+    indices.get(index).copied().ok_or_else(|| {
+        Error::new(ErrorKind::InvalidTensorIndex(
+            index,
+            indices.len().saturating_sub(1),
+        ))
+    })
+}
+
+fn invalid_state_pair(reason: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidStatePair(reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::model::Model;
+    use crate::streaming::StreamingSession;
+    use crate::test_models::explicit_state_model;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_streaming_session() {
+        let model_data = explicit_state_model();
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+        let session = StreamingSession::new(interpreter, &[("state_in", "state_out")])
+            .expect("Cannot create session!");
+        assert_eq!(1, session.input_tensor_count());
+        assert_eq!(1, session.output_tensor_count());
+        assert_eq!("input", session.input(0).unwrap().name());
+        assert_eq!("output", session.output(0).unwrap().name());
+        assert!(session.input(1).is_err());
+
+        let frame = [1.0f32, 2.0, 3.0, 4.0];
+        for step in 2..5 {
+            session.copy(&frame[..], 0).expect("Cannot copy input");
+            session.invoke().expect("Cannot invoke");
+            let expected: Vec<f32> = frame.iter().map(|x| x * step as f32).collect();
+            assert_eq!(&expected[..], session.output(0).unwrap().data::<f32>());
+        }
+
+        session.reset().expect("Cannot reset");
+        session.invoke().expect("Cannot invoke");
+        let expected: Vec<f32> = frame.iter().map(|x| x * 2.0).collect();
+        assert_eq!(&expected[..], session.output(0).unwrap().data::<f32>());
+    }
+
+    #[test]
+    fn test_streaming_session_validation() {
+        let model_data = explicit_state_model();
+        let model = Model::from_bytes(&model_data).expect("Cannot load model from bytes!");
+        let invalid_states: [&[(&str, &str)]; 3] = [
+            &[("missing", "state_out")],
+            &[("state_in", "missing")],
+            &[("state_in", "state_out"), ("state_in", "output")],
+        ];
+        for states in invalid_states.iter() {
+            let interpreter = Interpreter::new(&model, None).expect("Cannot create interpreter!");
+            let err = StreamingSession::new(interpreter, states).err().unwrap();
+            assert!(matches!(err.kind(), ErrorKind::InvalidStatePair(_)));
+        }
+    }
+}
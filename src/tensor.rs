@@ -1,10 +1,19 @@
 //! TensorFlow Lite input or output [`Tensor`] associated with an interpreter.
+use std::alloc::Layout;
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::ffi::{c_void, CStr};
 
 use crate::minimal_bindings::*;
 use crate::{Error, ErrorKind, Result};
-use std::fmt::{Debug, Formatter};
+#[cfg(feature = "half")]
+#[cfg_attr(docsrs, doc(cfg(feature = "half")))]
+pub use half::{bf16, f16};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
 use std::marker::PhantomData;
+use std::os::raw::c_int;
+use std::ptr::NonNull;
 
 /// Parameters that determine the mapping of quantized values to real values.
 ///
@@ -21,8 +30,34 @@ pub struct QuantizationParameters {
     pub zero_point: i32,
 }
 
+/// A plain-old-data element type of tensor data, i.e. a number type without padding whose
+/// every bit pattern is a valid value, so its values can be copied to tensor buffers as
+/// bytes.
+///
+/// The trait is sealed. It is implemented for the integer and floating point types, and
+/// for `f16` and `bf16` with the `half` feature.
+pub trait TensorElement: sealed::Sealed + Copy {}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_tensor_element {
+    ($($element:ty),*) => {
+        $(
+            impl sealed::Sealed for $element {}
+            impl TensorElement for $element {}
+        )*
+    };
+}
+
+impl_tensor_element!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+#[cfg(feature = "half")]
+impl_tensor_element!(f16, bf16);
+
 /// The supported [`Tensor`] data types.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+#[non_exhaustive]
 pub enum DataType {
     /// A boolean.
     Bool,
@@ -42,20 +77,47 @@ pub enum DataType {
     Float32,
     /// A 64-bit double precision floating point.
     Float64,
+    /// A 16-bit brain floating point, which has the exponent range of `f32`.
+    BFloat16,
+    /// A variable length byte string, see [`Tensor::strings()`].
+    String,
+    /// A 16-bit unsigned integer.
+    Uint16,
+    /// A 32-bit unsigned integer.
+    Uint32,
+    /// A 64-bit unsigned integer.
+    Uint64,
+    /// A complex number of two 32-bit single precision floating points.
+    Complex64,
+    /// A complex number of two 64-bit double precision floating points.
+    Complex128,
+    /// A 4-bit signed integer, see [`Tensor::int4_values()`].
+    Int4,
+    /// A handle to a resource, e.g. a hash table.
+    Resource,
+    /// A handle to a runtime-specific variant object.
+    Variant,
+    /// A type added by a newer TensorFlow Lite runtime, with its raw `TfLiteType` value.
+    Unknown(i32),
 }
 
 impl DataType {
-    /// Creates a new instance from the given [`Option<TfLiteType>`].
+    /// Creates a new instance from the given raw [`TfLiteType`] value.
     ///
     /// # Arguments
     ///
-    /// * `tflite_type`: A data type for a tensor.
+    /// * `tflite_type`: A raw data type for a tensor.
     ///
-    /// returns: [`None`] if the data type is unsupported or could not
-    /// be determined because there was an error, otherwise returns
-    /// [`Some`] corresponding enum variant.
-    pub(crate) fn new(tflite_type: TfLiteType) -> Option<DataType> {
+    /// returns: [`None`] if the data type could not be determined because there
+    /// was an error, otherwise returns [`Some`] corresponding enum variant, which is
+    /// [`DataType::Unknown`] for types newer than the bindings.
+    pub(crate) fn new(tflite_type: c_int) -> Option<DataType> {
+        let tflite_type = match TfLiteType::from_raw(tflite_type) {
+            Some(tflite_type) => tflite_type,
+            None => return Some(DataType::Unknown(tflite_type)),
+        };
         match tflite_type {
+            TfLiteType::kTfLiteNoType => None,
             TfLiteType::kTfLiteBool => Some(DataType::Bool),
             TfLiteType::kTfLiteUInt8 => Some(DataType::Uint8),
             TfLiteType::kTfLiteInt8 => Some(DataType::Int8),
@@ -65,9 +127,72 @@ impl DataType {
             TfLiteType::kTfLiteFloat16 => Some(DataType::Float16),
             TfLiteType::kTfLiteFloat32 => Some(DataType::Float32),
             TfLiteType::kTfLiteFloat64 => Some(DataType::Float64),
-            _ => None,
+            TfLiteType::kTfLiteBFloat16 => Some(DataType::BFloat16),
+            TfLiteType::kTfLiteString => Some(DataType::String),
+            TfLiteType::kTfLiteUInt16 => Some(DataType::Uint16),
+            TfLiteType::kTfLiteUInt32 => Some(DataType::Uint32),
+            TfLiteType::kTfLiteUInt64 => Some(DataType::Uint64),
+            TfLiteType::kTfLiteComplex64 => Some(DataType::Complex64),
+            TfLiteType::kTfLiteComplex128 => Some(DataType::Complex128),
+            TfLiteType::kTfLiteInt4 => Some(DataType::Int4),
+            TfLiteType::kTfLiteResource => Some(DataType::Resource),
+            TfLiteType::kTfLiteVariant => Some(DataType::Variant),
+        }
+    }
+
+    /// Returns the number of bits of a single element of the data type, e.g. 4 for
+    /// [`DataType::Int4`].
+    ///
+    /// returns: [`None`] if elements do not have a fixed size, i.e. for [`DataType::String`],
+    /// handles and [`DataType::Unknown`].
+    pub fn bits(&self) -> Option<usize> {
+        match self {
+            DataType::Int4 => Some(4),
+            DataType::Bool | DataType::Uint8 | DataType::Int8 => Some(8),
+            DataType::Int16 | DataType::Uint16 | DataType::Float16 | DataType::BFloat16 => Some(16),
+            DataType::Int32 | DataType::Uint32 | DataType::Float32 => Some(32),
+            DataType::Int64 | DataType::Uint64 | DataType::Float64 | DataType::Complex64 => {
+                Some(64)
+            }
+            DataType::Complex128 => Some(128),
+            DataType::String | DataType::Resource | DataType::Variant | DataType::Unknown(_) => {
+                None
+            }
         }
     }
+
+    /// Returns the number of bytes of a single element of the data type.
+    ///
+    /// returns: [`None`] if elements do not have a fixed size (see [`DataType::bits()`]) or
+    /// take less than a byte, i.e. for [`DataType::Int4`].
+    pub fn size(&self) -> Option<usize> {
+        self.bits().filter(|bits| *bits >= 8).map(|bits| bits / 8)
+    }
+
+    /// Returns the NumPy type descriptor of the data type, e.g. `<f4`.
+    ///
+    /// returns: [`None`] if the data type has no NumPy equivalent with the same layout.
+    fn numpy_descriptor(&self) -> Option<String> {
+        let kind = match self {
+            DataType::Bool => 'b',
+            DataType::Uint8 | DataType::Uint16 | DataType::Uint32 | DataType::Uint64 => 'u',
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => 'i',
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => 'f',
+            DataType::Complex64 | DataType::Complex128 => 'c',
+            // NumPy has no bfloat16, so the raw bits are written as opaque 2-byte values.
+            DataType::BFloat16 => 'V',
+            _ => return None,
+        };
+        let size = self.size()?;
+        let byte_order = if size == 1 || kind == 'V' {
+            '|'
+        } else if cfg!(target_endian = "little") {
+            '<'
+        } else {
+            '>'
+        };
+        Some(format!("{}{}{}", byte_order, kind, size))
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -113,11 +238,239 @@ impl Shape {
     pub fn rank(&self) -> usize {
         self.rank
     }
+
+    /// Returns the number of elements of the [`Tensor`], which is 1 for a scalar.
+    ///
+    /// # Panics
+    ///
+    /// * If the number of elements overflows `usize` (see [`Shape::checked_num_elements()`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::Shape;
+    /// assert_eq!(Shape::from([10, 8, 8, 3]).num_elements(), 1920);
+    /// assert_eq!(Shape::from([]).num_elements(), 1);
+    /// ```
+    pub fn num_elements(&self) -> usize {
+        self.checked_num_elements()
+            .unwrap_or_else(|| panic!("number of elements of shape {} overflows", self))
+    }
+
+    /// Returns the number of elements of the [`Tensor`], or [`None`] if it overflows `usize`.
+    pub fn checked_num_elements(&self) -> Option<usize> {
+        self.dimensions
+            .iter()
+            .try_fold(1usize, |count, size| count.checked_mul(*size))
+    }
+
+    /// Returns the row-major strides of the [`Tensor`] in elements, i.e. the number of
+    /// elements between consecutive indices of every dimension.
+    ///
+    /// # Panics
+    ///
+    /// * If a stride overflows `usize`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::Shape;
+    /// assert_eq!(Shape::from([10, 8, 8, 3]).strides(), vec![192, 24, 3, 1]);
+    /// ```
+    pub fn strides(&self) -> Vec<usize> {
+        let mut strides = vec![1usize; self.rank];
+        for dimension in (0..self.rank.saturating_sub(1)).rev() {
+            strides[dimension] = strides[dimension + 1]
+                .checked_mul(self.dimensions[dimension + 1])
+                .unwrap_or_else(|| panic!("strides of shape {} overflow", self));
+        }
+        strides
+    }
+
+    /// Returns the offset in elements of the element at `index` in row-major order.
+    ///
+    /// returns: [`None`] if the rank of `index` differs or any index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::Shape;
+    /// let shape = Shape::from([2, 3]);
+    /// assert_eq!(shape.offset(&[1, 2]), Some(5));
+    /// assert_eq!(shape.offset(&[2, 0]), None);
+    /// ```
+    pub fn offset(&self, index: &[usize]) -> Option<usize> {
+        if index.len() != self.rank {
+            return None;
+        }
+        index
+            .iter()
+            .zip(&self.dimensions)
+            .try_fold(0usize, |offset, (index, size)| {
+                if index < size {
+                    offset.checked_mul(*size)?.checked_add(*index)
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Returns the index of the element at `offset` in row-major order, which is the inverse
+    /// of [`Shape::offset()`].
+    ///
+    /// returns: [`None`] if `offset` is not less than the number of elements.
+    pub fn index(&self, offset: usize) -> Option<Vec<usize>> {
+        if offset >= self.checked_num_elements()? {
+            return None;
+        }
+        let mut index = vec![0; self.rank];
+        let mut remainder = offset;
+        for (dimension, size) in self.dimensions.iter().enumerate().rev() {
+            index[dimension] = remainder % size;
+            remainder /= size;
+        }
+        Some(index)
+    }
+
+    /// Returns the shape both shapes broadcast to with the NumPy broadcasting rules, where
+    /// dimensions are aligned from the last one and each pair must be equal or contain 1.
+    ///
+    /// returns: [`None`] if the shapes are not compatible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::Shape;
+    /// let shape = Shape::from([8, 1, 3]);
+    /// assert_eq!(shape.broadcast(&Shape::from([4, 3])), Some(Shape::from([8, 4, 3])));
+    /// assert_eq!(shape.broadcast(&Shape::from([4, 2])), None);
+    /// ```
+    pub fn broadcast(&self, other: &Shape) -> Option<Shape> {
+        let rank = self.rank.max(other.rank);
+        let size_of = |shape: &Shape, dimension: usize| {
+            (dimension + shape.rank)
+                .checked_sub(rank)
+                .map_or(1, |dimension| shape.dimensions[dimension])
+        };
+        (0..rank)
+            .map(
+                |dimension| match (size_of(self, dimension), size_of(other, dimension)) {
+                    (left, right) if left == right || right == 1 => Some(left),
+                    (1, right) => Some(right),
+                    _ => None,
+                },
+            )
+            .collect::<Option<Vec<usize>>>()
+            .map(Shape::new)
+    }
+
+    /// Returns whether the shape can be broadcast to `target` without changing `target`,
+    /// e.g. to apply a per-channel bias.
+    pub fn is_broadcastable_to(&self, target: &Shape) -> bool {
+        self.broadcast(target).as_ref() == Some(target)
+    }
+}
+
+impl Display for Shape {
+    /// Formats the dimensions as a list, e.g. `[1, 8, 8, 3]`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.dimensions)
+    }
+}
+
+impl From<Vec<usize>> for Shape {
+    fn from(dimensions: Vec<usize>) -> Shape {
+        Shape::new(dimensions)
+    }
+}
+
+impl From<&[usize]> for Shape {
+    fn from(dimensions: &[usize]) -> Shape {
+        Shape::new(dimensions.to_vec())
+    }
+}
+
+impl<const N: usize> From<[usize; N]> for Shape {
+    fn from(dimensions: [usize; N]) -> Shape {
+        Shape::new(dimensions.to_vec())
+    }
+}
+
+/// A dimension of a [`ShapeSignature`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Dimension {
+    /// A dimension with the given size fixed by the model.
+    Fixed(usize),
+    /// A dimension which can be resized, exported as `-1` by the converter.
+    Dynamic,
 }
 
-pub(crate) struct TensorData {
-    data_ptr: *mut u8,
-    data_length: usize,
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+/// The shape of a [`Tensor`] as exported in the model, e.g. `[Dynamic, Fixed(16000)]`
+/// for an input exported as `[-1, 16000]`.
+pub struct ShapeSignature {
+    /// An array of dimensions for the [`Tensor`]
+    dimensions: Vec<Dimension>,
+}
+
+impl ShapeSignature {
+    /// Creates a new instance with the given `dimensions`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::{Dimension, Shape, ShapeSignature};
+    /// let signature = ShapeSignature::new(vec![Dimension::Dynamic, Dimension::Fixed(16000)]);
+    /// assert!(signature.is_dynamic());
+    /// assert!(signature.accepts(&Shape::new(vec![4, 16000])));
+    /// assert!(!signature.accepts(&Shape::new(vec![4, 8000])));
+    /// ```
+    pub fn new(dimensions: Vec<Dimension>) -> ShapeSignature {
+        ShapeSignature { dimensions }
+    }
+
+    /// Reads the shape signature of `tensor_ptr`, which must point to a valid tensor.
+    ///
+    /// Unlike [`Tensor::from_raw()`], it does not need the tensor data to be allocated.
+    pub(crate) unsafe fn from_raw(tensor_ptr: *const TfLiteTensor) -> ShapeSignature {
+        let tensor_ptr = tensor_ptr as *const TfLiteOpaqueTensor;
+        let rank = TfLiteOpaqueTensorGetNumDimsSignature(tensor_ptr);
+        let dimensions = (0..rank)
+            .map(|i| match TfLiteOpaqueTensorGetDimSignature(tensor_ptr, i) {
+                -1 => Dimension::Dynamic,
+                size => Dimension::Fixed(size as usize),
+            })
+            .collect();
+        ShapeSignature { dimensions }
+    }
+
+    /// Returns dimensions of the [`Tensor`].
+    pub fn dimensions(&self) -> &Vec<Dimension> {
+        &self.dimensions
+    }
+
+    /// Returns rank(number of dimensions) of the [`Tensor`].
+    pub fn rank(&self) -> usize {
+        self.dimensions.len()
+    }
+
+    /// Returns whether any dimension is [`Dimension::Dynamic`].
+    pub fn is_dynamic(&self) -> bool {
+        self.dimensions.contains(&Dimension::Dynamic)
+    }
+
+    /// Returns whether `shape` has the same rank and matches every fixed dimension.
+    pub fn accepts(&self, shape: &Shape) -> bool {
+        shape.rank() == self.rank()
+            && self
+                .dimensions
+                .iter()
+                .zip(shape.dimensions())
+                .all(|(dimension, size)| match dimension {
+                    Dimension::Fixed(fixed) => fixed == size,
+                    Dimension::Dynamic => true,
+                })
+    }
 }
 
 /// An input or output tensor in a TensorFlow Lite graph.
@@ -131,9 +484,6 @@ pub struct Tensor<'a> {
     /// The shape of the `Tensor`.
     shape: Shape,
 
-    /// The data in the input or output `Tensor`.
-    data: TensorData,
-
     /// The quantization parameters for the `Tensor` if using a quantized model.
     quantization_parameters: Option<QuantizationParameters>,
 
@@ -166,41 +516,31 @@ impl<'a> Tensor<'a> {
             if name_ptr.is_null() {
                 return Err(Error::new(ErrorKind::ReadTensorError));
             }
-            let data_ptr = TfLiteTensorData(tensor_ptr) as *mut u8;
-            if data_ptr.is_null() {
+            let data_type = DataType::new(TfLiteTensorType(tensor_ptr))
+                .ok_or_else(|| Error::new(ErrorKind::InvalidTensorDataType))?;
+            // String tensors are allocated dynamically, so they have no buffer until
+            // strings are written to them.
+            if TfLiteTensorData(tensor_ptr).is_null() && data_type != DataType::String {
                 return Err(Error::new(ErrorKind::ReadTensorError));
             }
             let name = CStr::from_ptr(name_ptr).to_str().unwrap().to_owned();
 
-            let data_length = TfLiteTensorByteSize(tensor_ptr);
-            let data_type = DataType::new(TfLiteTensorType(tensor_ptr))
-                .ok_or_else(|| Error::new(ErrorKind::InvalidTensorDataType))?;
-
             let rank = TfLiteTensorNumDims(tensor_ptr);
             let dimensions = (0..rank)
                 .map(|i| TfLiteTensorDim(tensor_ptr, i) as usize)
                 .collect();
             let shape = Shape::new(dimensions);
-            let data = TensorData {
-                data_ptr,
-                data_length,
+            let TfLiteQuantizationParams { scale, zero_point } =
+                TfLiteTensorQuantizationParams(tensor_ptr);
+            let quantization_parameters = if scale == 0.0 {
+                None
+            } else {
+                Some(QuantizationParameters { scale, zero_point })
             };
-            // Quantization not implemented in minimal bindings - return default
-            let scale = 1.0f32;
-            let quantization_parameters =
-                if scale == 0.0 || (data_type != DataType::Uint8 && data_type != DataType::Int8) {
-                    None
-                } else {
-                    Some(QuantizationParameters {
-                        scale: 1.0,
-                        zero_point: 0,
-                    })
-                };
             Ok(Tensor {
                 name,
                 data_type,
                 shape,
-                data,
                 quantization_parameters,
                 tensor_ptr,
                 phantom: PhantomData,
@@ -208,11 +548,46 @@ impl<'a> Tensor<'a> {
         }
     }
 
+    /// Returns [`ShapeSignature`] of the tensor as exported in the model.
+    ///
+    /// Models exported without shape signatures report their current shape, with every
+    /// dimension fixed.
+    pub fn shape_signature(&self) -> ShapeSignature {
+        unsafe { ShapeSignature::from_raw(self.tensor_ptr) }
+    }
+
     /// Returns [`Shape`] of the tensor
     pub fn shape(&self) -> &Shape {
         &self.shape
     }
 
+    /// Copies the tensor into an [`OwnedTensor`], which outlives the interpreter.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tflitec::interpreter::Interpreter;
+    /// use tflitec::model::Model;
+    /// let model = Model::new("tests/add.bin")?;
+    /// let interpreter = Interpreter::new(&model, None)?;
+    /// interpreter.allocate_tensors()?;
+    /// interpreter.copy(&[1.0f32; 192], 0)?;
+    /// interpreter.invoke()?;
+    /// let output = interpreter.output(0)?.to_owned();
+    /// drop(interpreter);
+    /// std::thread::spawn(move || println!("{:?}", output.data::<f32>()));
+    /// # Ok::<(), tflitec::Error>(())
+    /// ```
+    pub fn to_owned(&self) -> OwnedTensor {
+        OwnedTensor::from_parts(
+            &self.name,
+            self.data_type,
+            self.shape.clone(),
+            self.quantization_parameters,
+            self.data::<u8>(),
+        )
+    }
+
     /// Returns data of the tensor as a slice of given type `T`.
     ///
     /// # Panics
@@ -221,17 +596,282 @@ impl<'a> Tensor<'a> {
     /// multiple of byte count of a single `T` (see [`std::mem::size_of`])
     pub fn data<T>(&self) -> &[T] {
         let element_size = std::mem::size_of::<T>();
-        if self.data.data_length % element_size != 0 {
+        let (data_ptr, data_length) = self.buffer();
+        if data_length % element_size != 0 {
             panic!(
                 "data length {} should be divisible by size of type {}",
-                self.data.data_length, element_size
+                data_length, element_size
             )
         }
+        unsafe { std::slice::from_raw_parts(data_ptr as *const T, data_length / element_size) }
+    }
+
+    /// Returns the pointer to the buffer of the tensor and its byte count.
+    ///
+    /// They are read on every access, because allocating the tensors of the interpreter or
+    /// writing strings through another [`Tensor`] may move the buffer.
+    fn buffer(&self) -> (*mut u8, usize) {
         unsafe {
-            std::slice::from_raw_parts(
-                self.data.data_ptr as *const T,
-                self.data.data_length / element_size,
-            )
+            match TfLiteTensorData(self.tensor_ptr) as *mut u8 {
+                data_ptr if !data_ptr.is_null() => {
+                    (data_ptr, TfLiteTensorByteSize(self.tensor_ptr))
+                }
+                // String tensors have no buffer until strings are written to them. The empty
+                // buffer is still aligned for every element type, like an empty `AlignedBuffer`.
+                _ => (TFLITE_DEFAULT_TENSOR_ALIGNMENT as *mut u8, 0),
+            }
+        }
+    }
+
+    /// Returns data of the tensor as an [`ndarray::ArrayViewD`] of given type `T` with
+    /// the shape of the tensor.
+    ///
+    /// # Panics
+    ///
+    /// * If number of elements of type `T` in buffer of the [`Tensor`] does not match
+    ///   [`Shape::num_elements()`], e.g. because `T` differs from the data type
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tflitec::interpreter::Interpreter;
+    /// use tflitec::model::Model;
+    /// let model = Model::new("tests/add.bin")?;
+    /// let interpreter = Interpreter::new(&model, None)?;
+    /// interpreter.allocate_tensors()?;
+    /// interpreter.invoke()?;
+    /// let output = interpreter.output(0)?;
+    /// let array = output.as_array::<f32>();
+    /// println!("{}", array.sum_axis(ndarray::Axis(3)));
+    /// # Ok::<(), tflitec::Error>(())
+    /// ```
+    #[cfg(feature = "ndarray")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn as_array<T>(&self) -> ndarray::ArrayViewD<'_, T> {
+        ndarray::ArrayViewD::from_shape(self.array_shape(), self.data::<T>())
+            .unwrap_or_else(|_| panic!("data should have shape {}", self.shape))
+    }
+
+    /// Returns data of the tensor as a mutable [`ndarray::ArrayViewMutD`] of given type `T`
+    /// with the shape of the tensor, e.g. to write an input in place.
+    ///
+    /// # Panics
+    ///
+    /// * If number of elements of type `T` in buffer of the [`Tensor`] does not match
+    ///   [`Shape::num_elements()`], e.g. because `T` differs from the data type
+    ///
+    /// # Safety
+    ///
+    /// Other [`Tensor`]s of the same tensor, e.g. another one returned by
+    /// [`Interpreter::input()`][crate::interpreter::Interpreter::input], can read its data.
+    /// While the returned view is alive, the caller must not access the data of the tensor
+    /// through any of them, nor invoke, resize or allocate the tensors of the interpreter.
+    #[cfg(feature = "ndarray")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub unsafe fn as_array_mut<T>(&mut self) -> ndarray::ArrayViewMutD<'_, T> {
+        let length = self.data::<T>().len();
+        let data = std::slice::from_raw_parts_mut(self.buffer().0 as *mut T, length);
+        ndarray::ArrayViewMutD::from_shape(self.array_shape(), data)
+            .unwrap_or_else(|_| panic!("data should have shape {}", self.shape))
+    }
+
+    #[cfg(feature = "ndarray")]
+    fn array_shape(&self) -> ndarray::IxDyn {
+        ndarray::IxDyn(self.shape.dimensions())
+    }
+
+    /// Returns data of a float16, bfloat16 or float32 tensor converted to `f32`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor has another data type.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use tflitec::interpreter::Interpreter;
+    /// use tflitec::model::Model;
+    /// let model = Model::new("fp16_model.tflite")?;
+    /// let interpreter = Interpreter::new(&model, None)?;
+    /// interpreter.allocate_tensors()?;
+    /// interpreter.input(0)?.set_f32_data(&[0.5f32; 16])?;
+    /// interpreter.invoke()?;
+    /// println!("{:?}", interpreter.output(0)?.to_f32_vec()?);
+    /// // Or read the half precision values directly
+    /// println!("{:?}", interpreter.output(0)?.data::<tflitec::tensor::f16>());
+    /// # Ok::<(), tflitec::Error>(())
+    /// ```
+    #[cfg(feature = "half")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "half")))]
+    pub fn to_f32_vec(&self) -> Result<Vec<f32>> {
+        use half::slice::HalfFloatSliceExt;
+        match self.data_type {
+            DataType::Float16 => Ok(self.data::<f16>().to_f32_vec()),
+            DataType::BFloat16 => Ok(self.data::<bf16>().to_f32_vec()),
+            DataType::Float32 => Ok(self.data::<f32>().to_vec()),
+            _ => Err(Error::new(ErrorKind::InvalidTensorDataType)),
+        }
+    }
+
+    /// Sets data of a float16, bfloat16 or float32 tensor by converting given `f32` data
+    /// to its data type, rounding to the nearest representable value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor has another data type.
+    /// Returns error if element count of the data does not match the tensor or TensorFlow
+    /// Lite C fails internally.
+    #[cfg(feature = "half")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "half")))]
+    pub fn set_f32_data(&mut self, data: &[f32]) -> Result<()> {
+        use half::slice::HalfFloatSliceExt;
+        match self.data_type {
+            DataType::Float16 => {
+                let mut converted = vec![f16::ZERO; data.len()];
+                converted.convert_from_f32_slice(data);
+                self.set_data(&converted[..])
+            }
+            DataType::BFloat16 => {
+                let mut converted = vec![bf16::ZERO; data.len()];
+                converted.convert_from_f32_slice(data);
+                self.set_data(&converted[..])
+            }
+            DataType::Float32 => self.set_data(data),
+            _ => Err(Error::new(ErrorKind::InvalidTensorDataType)),
+        }
+    }
+
+    /// Returns the strings of a [`DataType::String`] tensor in row-major order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not a string tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer is not a valid string buffer or
+    /// a string is not valid UTF-8 (see [`Tensor::byte_strings()`]).
+    pub fn strings(&self) -> Result<Vec<&str>> {
+        self.byte_strings()?
+            .into_iter()
+            .map(|bytes| {
+                std::str::from_utf8(bytes).map_err(|_| Error::new(ErrorKind::ReadTensorError))
+            })
+            .collect()
+    }
+
+    /// Returns the strings of a [`DataType::String`] tensor in row-major order as bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not a string tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer is not a valid string buffer.
+    pub fn byte_strings(&self) -> Result<Vec<&[u8]>> {
+        if self.data_type != DataType::String {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        decode_strings(self.data::<u8>()).ok_or_else(|| Error::new(ErrorKind::ReadTensorError))
+    }
+
+    /// Sets the strings of a [`DataType::String`] tensor, resizing its buffer to fit them.
+    ///
+    /// The strings are encoded in the TensorFlow Lite string buffer layout: the number of
+    /// strings, the offset of every string and the end offset as 32-bit integers, followed by
+    /// the bytes of the strings. To change the number of strings, resize the input with
+    /// [`Interpreter::resize_input()`][crate::interpreter::Interpreter::resize_input] first.
+    ///
+    /// # Arguments
+    ///
+    /// * `strings`: The strings in row-major order, e.g. `&["first", "second"]`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not a string tensor.
+    /// Returns [`ErrorKind::InvalidTensorDataCount`] if the number of strings does not match
+    /// [`Shape::num_elements()`]. Returns error if the buffer is too large for the layout or
+    /// TensorFlow Lite C fails internally.
+    ///
+    /// # Safety
+    ///
+    /// Strings of another encoded size reallocate the buffer of the tensor, which frees the
+    /// previous one. Other [`Tensor`]s of the same tensor, e.g. another one returned by
+    /// [`Interpreter::input()`][crate::interpreter::Interpreter::input], may have borrowed
+    /// it. The caller must not use data borrowed from any of them before this call, e.g. by
+    /// [`Tensor::data()`] or [`Tensor::strings()`], after it.
+    /// [`Interpreter::copy_strings()`][crate::interpreter::Interpreter::copy_strings] is a
+    /// safe alternative.
+    pub unsafe fn set_strings<S: AsRef<[u8]>>(&mut self, strings: &[S]) -> Result<()> {
+        if self.data_type != DataType::String {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        let count = self.shape.num_elements();
+        if strings.len() != count {
+            return Err(Error::new(ErrorKind::InvalidTensorDataCount(
+                strings.len(),
+                count,
+            )));
+        }
+        let buffer = encode_strings(strings)
+            .ok_or_else(|| Error::new(ErrorKind::FailedToCopyDataToInputTensor))?;
+        unsafe {
+            if TfLiteTensorByteSize(self.tensor_ptr) != buffer.len()
+                && TfLiteTensorRealloc(buffer.len(), self.tensor_ptr) != TfLiteStatus::kTfLiteOk
+            {
+                return Err(Error::new(ErrorKind::FailedToCopyDataToInputTensor));
+            }
+            let status = TfLiteTensorCopyFromBuffer(
+                self.tensor_ptr,
+                buffer.as_ptr() as *const c_void,
+                buffer.len(),
+            );
+            if status != TfLiteStatus::kTfLiteOk {
+                return Err(Error::new(ErrorKind::FailedToCopyDataToInputTensor));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the values of a [`DataType::Int4`] tensor, one per element.
+    ///
+    /// Buffers packing two values per byte are unpacked with [`unpack_int4()`], while
+    /// buffers holding a value per byte are read from the lower 4 bits of every byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not an int4 tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer fits neither layout.
+    pub fn int4_values(&self) -> Result<Vec<i8>> {
+        if self.data_type != DataType::Int4 {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        decode_int4(self.data::<u8>(), self.shape.num_elements())
+            .ok_or_else(|| Error::new(ErrorKind::ReadTensorError))
+    }
+
+    /// Sets the values of a [`DataType::Int4`] tensor in the layout of its buffer
+    /// (see [`Tensor::int4_values()`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `values`: The values in [-8, 7], whose upper 4 bits are discarded when packed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not an int4 tensor.
+    /// Returns [`ErrorKind::InvalidTensorDataCount`] if the number of values does not match
+    /// [`Shape::num_elements()`]. Returns error if TensorFlow Lite C fails internally.
+    pub fn set_int4_values(&mut self, values: &[i8]) -> Result<()> {
+        if self.data_type != DataType::Int4 {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        let count = self.shape.num_elements();
+        if values.len() != count {
+            return Err(Error::new(ErrorKind::InvalidTensorDataCount(
+                values.len(),
+                count,
+            )));
+        }
+        if self.buffer().1 == count {
+            self.set_data(values)
+        } else {
+            self.set_data(&pack_int4(values)[..])
         }
     }
 
@@ -243,15 +883,22 @@ impl<'a> Tensor<'a> {
     ///
     /// # Errors
     ///
-    /// Returns error if byte count of the data does not match the buffer size of the
-    /// input tensor or TensorFlow Lite C fails internally.
-    pub fn set_data<T>(&self, data: &[T]) -> Result<()> {
+    /// Returns [`ErrorKind::InvalidTensorDataCount`] with the number of elements of `data`
+    /// and the number of elements of type `T` filling the tensor if the byte count of the
+    /// data does not match the buffer size of the tensor. Returns error if TensorFlow Lite
+    /// C fails internally.
+    pub fn set_data<T: TensorElement>(&mut self, data: &[T]) -> Result<()> {
         let element_size = std::mem::size_of::<T>();
         let input_byte_count = element_size * data.len();
-        if self.data.data_length != input_byte_count {
+        let byte_count = self
+            .shape
+            .checked_num_elements()
+            .and_then(|count| count.checked_mul(self.data_type.size()?))
+            .unwrap_or_else(|| self.buffer().1);
+        if byte_count != input_byte_count {
             return Err(Error::new(ErrorKind::InvalidTensorDataCount(
                 data.len(),
-                input_byte_count,
+                byte_count / element_size.max(1),
             )));
         }
         let status = unsafe {
@@ -282,4 +929,443 @@ impl<'a> Tensor<'a> {
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
+
+    /// Writes the data of the [`Tensor`] in the NumPy `.npy` format, which can be
+    /// loaded with `numpy.load()` for offline comparison.
+    ///
+    /// Strings are written as fixed width byte strings padded with zeros, [`DataType::Int4`]
+    /// values unpacked to one `int8` per element, and data types without a NumPy
+    /// equivalent as their raw bytes in a one-dimensional `uint8` array.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if writing to `writer` fails or a string tensor has an invalid buffer.
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let invalid_data = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut dimensions = self.shape.dimensions().clone();
+        let (descriptor, data): (String, Cow<[u8]>) = match self.data_type {
+            DataType::String => {
+                let strings = self
+                    .byte_strings()
+                    .map_err(|_| invalid_data("invalid string buffer"))?;
+                let size = strings.iter().map(|s| s.len()).max().unwrap_or(0).max(1);
+                let mut data = Vec::with_capacity(size * strings.len());
+                for string in strings {
+                    data.extend_from_slice(string);
+                    data.resize(data.len() + size - string.len(), 0);
+                }
+                (format!("|S{}", size), Cow::Owned(data))
+            }
+            DataType::Int4 => {
+                let values = self
+                    .int4_values()
+                    .map_err(|_| invalid_data("invalid int4 buffer"))?;
+                let data = values.iter().map(|value| *value as u8).collect();
+                ("|i1".to_string(), Cow::Owned(data))
+            }
+            data_type => match data_type.numpy_descriptor() {
+                Some(descriptor) => (descriptor, Cow::Borrowed(self.data::<u8>())),
+                None => {
+                    dimensions = vec![self.buffer().1];
+                    ("|u1".to_string(), Cow::Borrowed(self.data::<u8>()))
+                }
+            },
+        };
+        let shape = match dimensions.len() {
+            1 => format!("({},)", dimensions[0]),
+            _ => format!(
+                "({})",
+                dimensions
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        };
+        let mut header = format!(
+            "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+            descriptor, shape
+        );
+        // The magic string, version and header length take 10 bytes, and the header
+        // ends with a newline so that the data is aligned to 64 bytes.
+        let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+        header.push_str(&" ".repeat(padding));
+        header.push('\n');
+        writer.write_all(b"\x93NUMPY\x01\x00")?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        writer.write_all(&data)?;
+        writer.flush()
+    }
+}
+
+/// Returns the `count` values of a [`DataType::Int4`] buffer, which either packs two
+/// values per byte or holds a value per byte.
+///
+/// Values held a byte each are in [-8, 7], so sign-extending the lower 4 bits of every
+/// byte reads them as they are. It also reads a single packed value, so the layouts need
+/// not be told apart when they have the same length.
+///
+/// returns: [`None`] if the length of `data` fits neither layout.
+fn decode_int4(data: &[u8], count: usize) -> Option<Vec<i8>> {
+    if data.len() == count {
+        Some(data.iter().map(|byte| (byte << 4) as i8 >> 4).collect())
+    } else if data.len() == count.div_ceil(2) {
+        Some(unpack_int4(data, count))
+    } else {
+        None
+    }
+}
+
+/// Packs 4-bit signed `values` two per byte, the first value of each pair in the lower
+/// 4 bits, as TensorFlow Lite stores [`DataType::Int4`] data.
+///
+/// The upper 4 bits of every value are discarded, and an odd number of values leaves
+/// the upper 4 bits of the last byte zero.
+///
+/// # Examples
+///
+/// ```
+/// use tflitec::tensor::{pack_int4, unpack_int4};
+/// let packed = pack_int4(&[1, -2, 7]);
+/// assert_eq!(packed, vec![0xe1, 0x07]);
+/// assert_eq!(unpack_int4(&packed, 3), vec![1, -2, 7]);
+/// ```
+pub fn pack_int4(values: &[i8]) -> Vec<u8> {
+    values
+        .chunks(2)
+        .map(|pair| {
+            let low = pair[0] as u8 & 0x0f;
+            let high = pair.get(1).map_or(0, |value| *value as u8 & 0x0f);
+            low | high << 4
+        })
+        .collect()
+}
+
+/// Unpacks `count` 4-bit signed values packed two per byte by [`pack_int4()`],
+/// sign-extending each of them to `i8`.
+///
+/// # Panics
+///
+/// * If `packed` has fewer than `(count + 1) / 2` bytes
+pub fn unpack_int4(packed: &[u8], count: usize) -> Vec<i8> {
+    (0..count)
+        .map(|index| {
+            let byte = packed[index / 2];
+            let nibble = if index % 2 == 0 {
+                byte << 4
+            } else {
+                byte & 0xf0
+            };
+            nibble as i8 >> 4
+        })
+        .collect()
+}
+
+/// Encodes `strings` in the TensorFlow Lite string buffer layout.
+///
+/// returns: [`None`] if the buffer would not fit the 32-bit offsets of the layout.
+fn encode_strings<S: AsRef<[u8]>>(strings: &[S]) -> Option<Vec<u8>> {
+    let header_size = 4 * (strings.len() + 2);
+    let payload_size = strings.iter().try_fold(0usize, |size, string| {
+        size.checked_add(string.as_ref().len())
+    })?;
+    let buffer_size = header_size.checked_add(payload_size)?;
+    i32::try_from(buffer_size).ok()?;
+
+    let mut buffer = Vec::with_capacity(buffer_size);
+    buffer.extend_from_slice(&(strings.len() as i32).to_ne_bytes());
+    let mut offset = header_size;
+    for string in strings {
+        buffer.extend_from_slice(&(offset as i32).to_ne_bytes());
+        offset += string.as_ref().len();
+    }
+    buffer.extend_from_slice(&(offset as i32).to_ne_bytes());
+    for string in strings {
+        buffer.extend_from_slice(string.as_ref());
+    }
+    Some(buffer)
+}
+
+/// Decodes the strings of a buffer in the TensorFlow Lite string buffer layout.
+///
+/// An empty buffer, which string tensors have before strings are written, has no strings.
+///
+/// returns: [`None`] if the count or the offsets are out of bounds of `buffer`.
+fn decode_strings(buffer: &[u8]) -> Option<Vec<&[u8]>> {
+    if buffer.is_empty() {
+        return Some(Vec::new());
+    }
+    let read = |position: usize| -> Option<usize> {
+        let bytes = buffer.get(position..position.checked_add(4)?)?;
+        usize::try_from(i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok()
+    };
+    let count = read(0)?;
+    (0..count)
+        .map(|index| {
+            let start = read(4 * (index + 1))?;
+            let end = read(4 * (index + 2))?;
+            buffer.get(start..end)
+        })
+        .collect()
+}
+
+/// A zeroed buffer aligned as TensorFlow Lite requires for tensor data.
+pub(crate) struct AlignedBuffer {
+    pub(crate) data: NonNull<u8>,
+    pub(crate) layout: Layout,
+}
+
+// The buffer is only written through `&mut self`.
+unsafe impl Send for AlignedBuffer {}
+unsafe impl Sync for AlignedBuffer {}
+
+impl AlignedBuffer {
+    pub(crate) fn new(bytes: usize) -> Option<AlignedBuffer> {
+        let layout = Layout::from_size_align(bytes, TFLITE_DEFAULT_TENSOR_ALIGNMENT).ok()?;
+        let data = match bytes {
+            // An empty buffer is not allocated, but its pointer is still aligned.
+            0 => NonNull::new(layout.align() as *mut u8)?,
+            _ => NonNull::new(unsafe { std::alloc::alloc_zeroed(layout) })?,
+        };
+        Some(AlignedBuffer { data, layout })
+    }
+
+    /// Returns a buffer holding a copy of `bytes`.
+    pub(crate) fn copy_of(bytes: &[u8]) -> AlignedBuffer {
+        let mut buffer = AlignedBuffer::new(bytes.len()).unwrap_or_else(|| {
+            std::alloc::handle_alloc_error(
+                Layout::from_size_align(bytes.len(), TFLITE_DEFAULT_TENSOR_ALIGNMENT)
+                    .expect("tensor buffer layout should be valid"),
+            )
+        });
+        buffer.as_bytes_mut().copy_from_slice(bytes);
+        buffer
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), self.layout.size()) }
+    }
+
+    pub(crate) fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl Clone for AlignedBuffer {
+    fn clone(&self) -> Self {
+        AlignedBuffer::copy_of(self.as_bytes())
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { std::alloc::dealloc(self.data.as_ptr(), self.layout) }
+        }
+    }
+}
+
+/// A copy of a [`Tensor`] which owns its data, so it outlives the interpreter it was
+/// copied from and can be sent to other threads.
+///
+/// Its data is aligned like tensor data of TensorFlow Lite, so it can be read as any
+/// element type with [`OwnedTensor::data()`], and copied back into an input with
+/// [`Interpreter::copy()`][crate::interpreter::Interpreter::copy].
+#[derive(Clone)]
+pub struct OwnedTensor {
+    name: String,
+    data_type: DataType,
+    shape: Shape,
+    quantization_parameters: Option<QuantizationParameters>,
+    data: AlignedBuffer,
+}
+
+impl Debug for OwnedTensor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedTensor")
+            .field("name", &self.name)
+            .field("shape", &self.shape)
+            .field("data_type", &self.data_type)
+            .field("quantization_parameters", &self.quantization_parameters)
+            .finish()
+    }
+}
+
+impl PartialEq for OwnedTensor {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.data_type == other.data_type
+            && self.shape == other.shape
+            && self.quantization_parameters == other.quantization_parameters
+            && self.data.as_bytes() == other.data.as_bytes()
+    }
+}
+
+impl OwnedTensor {
+    /// Creates a new instance holding a copy of `data`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the tensor.
+    /// * `data_type`: The data type of the elements of `data`.
+    /// * `shape`: The shape of the tensor.
+    /// * `data`: The data of the tensor, e.g. a `&[f32]` slice for [`DataType::Float32`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataCount`] if `data_type` has a fixed size and
+    /// byte count of the data does not match the number of elements of `shape`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tflitec::tensor::{DataType, OwnedTensor, Shape};
+    /// let tensor = OwnedTensor::new("input", DataType::Float32, Shape::from([2, 2]), &[1.0f32; 4])?;
+    /// assert_eq!(tensor.data::<f32>(), &[1.0; 4]);
+    /// # Ok::<(), tflitec::Error>(())
+    /// ```
+    pub fn new<T: TensorElement>(
+        name: &str,
+        data_type: DataType,
+        shape: Shape,
+        data: &[T],
+    ) -> Result<OwnedTensor> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
+        };
+        if let Some(byte_count) = data_type
+            .size()
+            .and_then(|size| shape.checked_num_elements()?.checked_mul(size))
+        {
+            if byte_count != bytes.len() {
+                return Err(Error::new(ErrorKind::InvalidTensorDataCount(
+                    bytes.len(),
+                    byte_count,
+                )));
+            }
+        }
+        Ok(OwnedTensor::from_parts(name, data_type, shape, None, bytes))
+    }
+
+    pub(crate) fn from_parts(
+        name: &str,
+        data_type: DataType,
+        shape: Shape,
+        quantization_parameters: Option<QuantizationParameters>,
+        data: &[u8],
+    ) -> OwnedTensor {
+        OwnedTensor {
+            name: name.to_owned(),
+            data_type,
+            shape,
+            quantization_parameters,
+            data: AlignedBuffer::copy_of(data),
+        }
+    }
+
+    /// Sets the [`QuantizationParameters`] of the tensor.
+    pub fn with_quantization_parameters(
+        mut self,
+        quantization_parameters: Option<QuantizationParameters>,
+    ) -> Self {
+        self.quantization_parameters = quantization_parameters;
+        self
+    }
+
+    /// Returns name of the tensor.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Returns [`DataType`] of the tensor.
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Returns [`Shape`] of the tensor.
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// Returns optional [`QuantizationParameters`] of the tensor.
+    pub fn quantization_parameters(&self) -> Option<QuantizationParameters> {
+        self.quantization_parameters
+    }
+
+    /// Returns data of the tensor as a slice of given type `T`.
+    ///
+    /// # Panics
+    ///
+    /// * If number of bytes of the data is not integer multiple of byte count of a single `T`
+    ///   (see [`std::mem::size_of`])
+    pub fn data<T>(&self) -> &[T] {
+        let bytes = self.data.as_bytes();
+        let element_size = std::mem::size_of::<T>();
+        if !bytes.chunks_exact(element_size).remainder().is_empty() {
+            panic!(
+                "data length {} should be divisible by size of type {}",
+                bytes.len(),
+                element_size
+            )
+        }
+        unsafe {
+            std::slice::from_raw_parts(bytes.as_ptr() as *const T, bytes.len() / element_size)
+        }
+    }
+
+    /// Returns data of the tensor as a mutable slice of given type `T`.
+    ///
+    /// # Panics
+    ///
+    /// * If number of bytes of the data is not integer multiple of byte count of a single `T`
+    ///   (see [`std::mem::size_of`])
+    pub fn data_mut<T: TensorElement>(&mut self) -> &mut [T] {
+        let length = self.data::<T>().len();
+        unsafe { std::slice::from_raw_parts_mut(self.data.data.as_ptr() as *mut T, length) }
+    }
+
+    /// Returns the strings of a [`DataType::String`] tensor in row-major order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not a string tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer is not a valid string buffer or
+    /// a string is not valid UTF-8 (see [`OwnedTensor::byte_strings()`]).
+    pub fn strings(&self) -> Result<Vec<&str>> {
+        self.byte_strings()?
+            .into_iter()
+            .map(|bytes| {
+                std::str::from_utf8(bytes).map_err(|_| Error::new(ErrorKind::ReadTensorError))
+            })
+            .collect()
+    }
+
+    /// Returns the strings of a [`DataType::String`] tensor in row-major order as bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not a string tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer is not a valid string buffer.
+    pub fn byte_strings(&self) -> Result<Vec<&[u8]>> {
+        if self.data_type != DataType::String {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        decode_strings(self.data.as_bytes()).ok_or_else(|| Error::new(ErrorKind::ReadTensorError))
+    }
+
+    /// Returns the values of a [`DataType::Int4`] tensor, one per element.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidTensorDataType`] if the tensor is not an int4 tensor.
+    /// Returns [`ErrorKind::ReadTensorError`] if the buffer fits neither layout
+    /// (see [`Tensor::int4_values()`]).
+    pub fn int4_values(&self) -> Result<Vec<i8>> {
+        if self.data_type != DataType::Int4 {
+            return Err(Error::new(ErrorKind::InvalidTensorDataType));
+        }
+        decode_int4(self.data.as_bytes(), self.shape.num_elements())
+            .ok_or_else(|| Error::new(ErrorKind::ReadTensorError))
+    }
 }
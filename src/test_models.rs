@@ -0,0 +1,339 @@
+//! Tiny TensorFlow Lite models written by the tests.
+//!
+//! Models are serialized with a minimal FlatBuffers writer which places every object
+//! after the object referring to it, so all offsets point forward as FlatBuffers requires.
+use crate::minimal_bindings::TFLITE_BUILTIN_CUSTOM;
+
+/// A field of a FlatBuffers [`Table`].
+enum Field {
+    U8(u8),
+    I32(i32),
+    U32(u32),
+    Str(&'static str),
+    Bytes(Vec<u8>),
+    Ints(Vec<i32>),
+    Floats(Vec<f32>),
+    Longs(Vec<i64>),
+    Tables(Vec<Table>),
+}
+
+/// A FlatBuffers table, with its fields in the order of the schema.
+struct Table(Vec<Option<Field>>);
+
+impl Table {
+    /// Writes the table, preceded by its vtable, and returns the position of the table.
+    fn write(&self, buffer: &mut Vec<u8>) -> usize {
+        // Every field takes a 4-byte slot, which keeps the layout trivial.
+        align(buffer);
+        let vtable_position = buffer.len();
+        push_u16(buffer, 4 + 2 * self.0.len());
+        push_u16(buffer, 4 + 4 * self.0.len());
+        for (index, field) in self.0.iter().enumerate() {
+            push_u16(buffer, field.as_ref().map(|_| 4 + 4 * index).unwrap_or(0));
+        }
+        align(buffer);
+        let table_position = buffer.len();
+        push_u32(buffer, (table_position - vtable_position) as u32);
+
+        let mut children = Vec::new();
+        for field in &self.0 {
+            match field {
+                Some(Field::U8(value)) => buffer.extend_from_slice(&[*value, 0, 0, 0]),
+                Some(Field::I32(value)) => buffer.extend_from_slice(&value.to_le_bytes()),
+                Some(Field::U32(value)) => push_u32(buffer, *value),
+                Some(child) => {
+                    children.push((buffer.len(), child));
+                    push_u32(buffer, 0);
+                }
+                None => push_u32(buffer, 0),
+            }
+        }
+        for (slot, child) in children {
+            let position = child.write(buffer);
+            patch_offset(buffer, slot, position);
+        }
+        table_position
+    }
+}
+
+impl Field {
+    /// Writes the object referred to by an offset field and returns its position.
+    fn write(&self, buffer: &mut Vec<u8>) -> usize {
+        align(buffer);
+        if let Field::Longs(_) = self {
+            // The elements following the length must be 8-byte aligned.
+            let padding = (8 - (buffer.len() + 4) % 8) % 8;
+            buffer.resize(buffer.len() + padding, 0);
+        }
+        let position = buffer.len();
+        match self {
+            Field::Str(value) => {
+                push_u32(buffer, value.len() as u32);
+                buffer.extend_from_slice(value.as_bytes());
+                buffer.push(0);
+            }
+            Field::Bytes(values) => {
+                push_u32(buffer, values.len() as u32);
+                buffer.extend_from_slice(values);
+            }
+            Field::Ints(values) => {
+                push_u32(buffer, values.len() as u32);
+                values
+                    .iter()
+                    .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+            }
+            Field::Floats(values) => {
+                push_u32(buffer, values.len() as u32);
+                values
+                    .iter()
+                    .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+            }
+            Field::Longs(values) => {
+                push_u32(buffer, values.len() as u32);
+                values
+                    .iter()
+                    .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+            }
+            Field::Tables(tables) => {
+                push_u32(buffer, tables.len() as u32);
+                let slots = (0..tables.len())
+                    .map(|_| {
+                        push_u32(buffer, 0);
+                        buffer.len() - 4
+                    })
+                    .collect::<Vec<usize>>();
+                for (slot, table) in slots.into_iter().zip(tables) {
+                    let table_position = table.write(buffer);
+                    patch_offset(buffer, slot, table_position);
+                }
+            }
+            Field::U8(_) | Field::I32(_) | Field::U32(_) => unreachable!("scalars are inline"),
+        }
+        position
+    }
+}
+
+fn align(buffer: &mut Vec<u8>) {
+    let padding = (4 - buffer.len() % 4) % 4;
+    buffer.resize(buffer.len() + padding, 0);
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: usize) {
+    buffer.extend_from_slice(&(value as u16).to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn patch_offset(buffer: &mut [u8], slot: usize, position: usize) {
+    buffer[slot..slot + 4].copy_from_slice(&((position - slot) as u32).to_le_bytes());
+}
+
+/// Returns a tensor of the given `TensorType` of the schema with the given shape and
+/// no constant data.
+fn typed_tensor(name: &'static str, tensor_type: u8, shape: &[i32]) -> Table {
+    Table(vec![
+        Some(Field::Ints(shape.to_vec())),
+        Some(Field::U8(tensor_type)),
+        Some(Field::U32(0)),
+        Some(Field::Str(name)),
+    ])
+}
+
+/// Returns a float32 tensor with the given shape and no constant data.
+fn float_tensor(name: &'static str, shape: &[i32]) -> Table {
+    typed_tensor(name, 0, shape)
+}
+
+/// Returns an int8 tensor with the given shape, quantized with `scale` and `zero_point`.
+fn quantized_tensor(name: &'static str, shape: &[i32], scale: f32, zero_point: i64) -> Table {
+    const INT8: u8 = 9;
+    let quantization = Table(vec![
+        None,
+        None,
+        Some(Field::Floats(vec![scale])),
+        Some(Field::Longs(vec![zero_point])),
+    ]);
+    let mut tensor = typed_tensor(name, INT8, shape);
+    tensor.0.push(Some(Field::Tables(vec![quantization])));
+    tensor
+}
+
+/// Returns a float32 variable tensor with the given shape, which holds state across
+/// invocations.
+fn variable_tensor(name: &'static str, shape: &[i32]) -> Table {
+    let mut tensor = float_tensor(name, shape);
+    tensor.0.extend(vec![None, Some(Field::U8(1))]);
+    tensor
+}
+
+/// Returns a float32 tensor with the given shape signature, where `-1` marks a dynamic
+/// dimension which has size 1 in the shape.
+fn dynamic_tensor(name: &'static str, shape_signature: &[i32]) -> Table {
+    let shape = shape_signature
+        .iter()
+        .map(|size| (*size).max(1))
+        .collect::<Vec<i32>>();
+    let mut tensor = float_tensor(name, &shape);
+    tensor.0.extend(vec![
+        None,
+        None,
+        None,
+        Some(Field::Ints(shape_signature.to_vec())),
+    ]);
+    tensor
+}
+
+/// Returns a model with a single subgraph running `operators`, which all use the
+/// operator code at index 0.
+fn graph_model(
+    operator_code: Table,
+    tensors: Vec<Table>,
+    inputs: Vec<i32>,
+    outputs: Vec<i32>,
+    operators: Vec<Table>,
+) -> Vec<u8> {
+    let subgraph = Table(vec![
+        Some(Field::Tables(tensors)),
+        Some(Field::Ints(inputs)),
+        Some(Field::Ints(outputs)),
+        Some(Field::Tables(operators)),
+        Some(Field::Str("main")),
+    ]);
+    let model = Table(vec![
+        Some(Field::U32(3)),
+        Some(Field::Tables(vec![operator_code])),
+        Some(Field::Tables(vec![subgraph])),
+        Some(Field::Str("tflitec test model")),
+        Some(Field::Tables(vec![Table(Vec::new())])),
+    ]);
+
+    let mut buffer = vec![0; 4];
+    buffer.extend_from_slice(b"TFL3");
+    let root_position = model.write(&mut buffer);
+    patch_offset(&mut buffer, 0, root_position);
+    buffer
+}
+
+/// Returns a model computing `output = op(input)` with a single custom operator.
+///
+/// Both tensors are float32 with shape `[1, 4]`, and `options` are the custom options
+/// of the node.
+pub(crate) fn custom_op_model(op_name: &'static str, options: &[u8]) -> Vec<u8> {
+    let tensors = vec![
+        float_tensor("input", &[1, 4]),
+        float_tensor("output", &[1, 4]),
+    ];
+    custom_op_graph(op_name, options, tensors)
+}
+
+/// Returns a model computing `output = op(input)` with a single custom operator and
+/// no custom options.
+///
+/// Both tensors are int8 with shape `[1, 4]`, quantized with `scale` and `zero_point`.
+pub(crate) fn quantized_custom_op_model(
+    op_name: &'static str,
+    scale: f32,
+    zero_point: i64,
+) -> Vec<u8> {
+    let tensors = vec![
+        quantized_tensor("input", &[1, 4], scale, zero_point),
+        quantized_tensor("output", &[1, 4], scale, zero_point),
+    ];
+    custom_op_graph(op_name, &[], tensors)
+}
+
+/// Returns a model running a single custom operator from tensor 0 to tensor 1 of `tensors`.
+fn custom_op_graph(op_name: &'static str, options: &[u8], tensors: Vec<Table>) -> Vec<u8> {
+    let operator_code = Table(vec![
+        Some(Field::U8(TFLITE_BUILTIN_CUSTOM as u8)),
+        Some(Field::Str(op_name)),
+        Some(Field::I32(1)),
+        Some(Field::I32(TFLITE_BUILTIN_CUSTOM)),
+    ]);
+    let operator = Table(vec![
+        Some(Field::U32(0)),
+        Some(Field::Ints(vec![0])),
+        Some(Field::Ints(vec![1])),
+        None,
+        None,
+        Some(Field::Bytes(options.to_vec())),
+        Some(Field::U8(0)),
+    ]);
+    graph_model(operator_code, tensors, vec![0], vec![1], vec![operator])
+}
+
+/// Returns a model computing `output = input + state`, where `state` is a variable tensor.
+///
+/// All tensors are float32 with shape `[1, 4]`.
+pub(crate) fn variable_add_model() -> Vec<u8> {
+    let tensors = vec![
+        float_tensor("input", &[1, 4]),
+        variable_tensor("state", &[1, 4]),
+        float_tensor("output", &[1, 4]),
+    ];
+    let operators = vec![add_operator(0, 1, 2)];
+    graph_model(add_operator_code(), tensors, vec![0], vec![2], operators)
+}
+
+/// Returns a model with state as explicit inputs and outputs, computing
+/// `state_out = input + state_in` and `output = state_out + input`.
+///
+/// All tensors are float32 with shape `[1, 4]`. The inputs are `input` and `state_in`,
+/// and the outputs are `output` and `state_out`.
+pub(crate) fn explicit_state_model() -> Vec<u8> {
+    let tensors = vec![
+        float_tensor("input", &[1, 4]),
+        float_tensor("state_in", &[1, 4]),
+        float_tensor("state_out", &[1, 4]),
+        float_tensor("output", &[1, 4]),
+    ];
+    let operators = vec![add_operator(0, 1, 2), add_operator(2, 0, 3)];
+    graph_model(
+        add_operator_code(),
+        tensors,
+        vec![0, 1],
+        vec![3, 2],
+        operators,
+    )
+}
+
+/// Returns a model computing `output = input + input`.
+///
+/// Both tensors are float32 with shape signature `[-1, 4]` and shape `[1, 4]`.
+pub(crate) fn dynamic_batch_model() -> Vec<u8> {
+    let tensors = vec![
+        dynamic_tensor("input", &[-1, 4]),
+        dynamic_tensor("output", &[-1, 4]),
+    ];
+    let operators = vec![add_operator(0, 0, 1)];
+    graph_model(add_operator_code(), tensors, vec![0], vec![1], operators)
+}
+
+/// Returns a model without operators, whose only tensor `value` of the given `TensorType`
+/// of the schema and shape `[1, 4]` is both the input and the output.
+pub(crate) fn identity_model(tensor_type: u8) -> Vec<u8> {
+    let tensors = vec![typed_tensor("value", tensor_type, &[1, 4])];
+    graph_model(add_operator_code(), tensors, vec![0], vec![0], Vec::new())
+}
+
+/// Returns the operator code of the builtin `ADD` operator.
+fn add_operator_code() -> Table {
+    const BUILTIN_ADD: u8 = 0;
+    Table(vec![
+        Some(Field::U8(BUILTIN_ADD)),
+        None,
+        Some(Field::I32(1)),
+        Some(Field::I32(BUILTIN_ADD as i32)),
+    ])
+}
+
+/// Returns an `ADD` operator computing the tensor `output` from tensors `x` and `y`.
+fn add_operator(x: i32, y: i32, output: i32) -> Table {
+    Table(vec![
+        Some(Field::U32(0)),
+        Some(Field::Ints(vec![x, y])),
+        Some(Field::Ints(vec![output])),
+    ])
+}
@@ -0,0 +1,757 @@
+//! Configuration of the XNNPACK delegate used by the [`Interpreter`][crate::interpreter::Interpreter].
+//!
+//! # Examples
+//!
+//! ```
+//! use tflitec::interpreter::{Interpreter, Options};
+//! use tflitec::model::Model;
+//! use tflitec::xnnpack::XnnpackOptions;
+//! let options = Options {
+//!     is_xnnpack_enabled: true,
+//!     xnnpack: XnnpackOptions {
+//!         force_fp16: true,
+//!         thread_count: Some(2),
+//!         ..XnnpackOptions::default()
+//!     },
+//!     ..Options::default()
+//! };
+//! let model = Model::new("tests/add.bin")?;
+//! let interpreter = Interpreter::new(&model, Some(options))?;
+//! # Ok::<(), tflitec::Error>(())
+//! ```
+use crate::delegate::Delegate;
+use crate::interpreter::Options;
+use crate::minimal_bindings::*;
+use crate::model::Model;
+use crate::{Error, ErrorKind, Result};
+use std::ffi::CString;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The file extension of XNNPACK weight cache files managed by the crate.
+const WEIGHT_CACHE_EXTENSION: &str = "xnnpack_cache";
+
+/// The version in the header of weight cache files written by XNNPACK.
+///
+/// XNNPACK writes an invalid version of 0 until a cache file is complete.
+const WEIGHT_CACHE_VERSION: u64 = 1;
+
+/// The size of the header of weight cache files: the version, a 32-byte build identifier,
+/// and the offset and size of the buffer list.
+const WEIGHT_CACHE_HEADER_SIZE: usize = 56;
+
+/// A directory of persistent XNNPACK weight cache files.
+///
+/// XNNPACK packs the weights of a model when the delegate is applied, which dominates the
+/// startup time of large models. With a weight cache file, packed weights are written once
+/// and loaded on subsequent startups.
+///
+/// The crate names the cache file of a model after its [fingerprint][Model::fingerprint()],
+/// so a changed model never loads weights packed for a previous version. Cache files of the
+/// same [name][WeightCacheFile::name] with a different fingerprint are stale, and they are
+/// removed when a new interpreter is created.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct WeightCacheFile {
+    /// The directory containing the cache files. It is created if it does not exist.
+    pub directory: PathBuf,
+
+    /// The name identifying the model among the cache files of the directory.
+    ///
+    /// If [`None`], the file stem of [`Model::filepath()`] is used. Models created from
+    /// bytes have no default name, and their stale cache files are not detected.
+    pub name: Option<String>,
+}
+
+impl WeightCacheFile {
+    /// Creates a new instance with the given `directory` and the default name.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> WeightCacheFile {
+        WeightCacheFile {
+            directory: directory.into(),
+            name: None,
+        }
+    }
+
+    /// Prepares the cache file of the given `model`, removing stale files of the same name.
+    pub(crate) fn prepare(&self, model: &Model) -> Result<WeightCacheReport> {
+        let failed = |_| Error::new(ErrorKind::FailedToPrepareWeightCache);
+        let fingerprint = model.fingerprint()?;
+        let name = self.name.clone().or_else(|| {
+            model
+                .filepath()
+                .and_then(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy().into_owned())
+        });
+        let file_name = match name.as_ref() {
+            Some(name) => format!("{}.{:016x}.{}", name, fingerprint, WEIGHT_CACHE_EXTENSION),
+            None => format!("{:016x}.{}", fingerprint, WEIGHT_CACHE_EXTENSION),
+        };
+        std::fs::create_dir_all(&self.directory).map_err(failed)?;
+        let path = self.directory.join(&file_name);
+
+        let mut has_stale_files = false;
+        if let Some(name) = name.as_ref() {
+            let prefix = format!("{}.", name);
+            let suffix = format!(".{}", WEIGHT_CACHE_EXTENSION);
+            for entry in std::fs::read_dir(&self.directory).map_err(failed)? {
+                let entry = entry.map_err(failed)?;
+                let entry_name = entry.file_name().to_string_lossy().into_owned();
+                let is_same_model = entry_name
+                    .strip_prefix(&prefix)
+                    .and_then(|rest| rest.strip_suffix(&suffix))
+                    .map(|hex| hex.len() == 16 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .unwrap_or(false);
+                if is_same_model && entry_name != file_name {
+                    std::fs::remove_file(entry.path()).map_err(failed)?;
+                    has_stale_files = true;
+                }
+            }
+        }
+
+        let status = if !path.exists() {
+            if has_stale_files {
+                WeightCacheStatus::Stale
+            } else {
+                WeightCacheStatus::Miss
+            }
+        } else if is_valid_cache_file(&path) {
+            WeightCacheStatus::Hit
+        } else {
+            // XNNPACK would fail to load the file, so it is packed again from scratch.
+            std::fs::remove_file(&path).map_err(failed)?;
+            WeightCacheStatus::Invalid
+        };
+        Ok(WeightCacheReport { path, status })
+    }
+}
+
+/// Checks the header of the weight cache file at `path`, so that incomplete, truncated
+/// and incompatible files are not reported as hits.
+fn is_valid_cache_file(path: &Path) -> bool {
+    let mut header = [0u8; WEIGHT_CACHE_HEADER_SIZE];
+    let length = match File::open(path).and_then(|mut file| {
+        file.read_exact(&mut header)?;
+        file.metadata()
+    }) {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return false,
+    };
+    let read_u64 = |offset: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&header[offset..offset + 8]);
+        u64::from_ne_bytes(bytes)
+    };
+    let (version, buffer_list_offset, buffer_list_size) = (read_u64(0), read_u64(40), read_u64(48));
+    version == WEIGHT_CACHE_VERSION
+        && buffer_list_offset >= WEIGHT_CACHE_HEADER_SIZE as u64
+        && buffer_list_offset
+            .checked_add(buffer_list_size)
+            .map(|end| end <= length)
+            .unwrap_or(false)
+}
+
+/// Converts `path` to the C string passed to TensorFlow Lite C, keeping non-UTF-8 paths
+/// intact on Unix.
+fn path_to_c_string(path: &Path) -> Result<CString> {
+    let failed = || Error::new(ErrorKind::FailedToPrepareWeightCache);
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_str().ok_or_else(failed)?.as_bytes();
+    CString::new(bytes).map_err(|_| failed())
+}
+
+/// The state of the weight cache file of a model when the interpreter was created.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum WeightCacheStatus {
+    /// A cache file of the model existed, and packed weights are loaded from it.
+    Hit,
+    /// No cache file of the model existed, so weights are packed and written to a new file.
+    Miss,
+    /// Only cache files of a previous version of the model existed. They are removed,
+    /// and weights are packed and written to a new file.
+    Stale,
+    /// The cache file of the model was incomplete, truncated or written by an incompatible
+    /// version of XNNPACK. It is removed, and weights are packed and written to a new file.
+    Invalid,
+}
+
+/// Describes the weight cache file used by an [`Interpreter`][crate::interpreter::Interpreter].
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct WeightCacheReport {
+    /// The path of the cache file.
+    pub path: PathBuf,
+    /// The state of the cache file when the interpreter was created.
+    pub status: WeightCacheStatus,
+}
+
+impl WeightCacheReport {
+    /// Returns the path of the cache file as a C string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::FailedToPrepareWeightCache`] if the path contains a nul byte,
+    /// or it is not valid Unicode on platforms other than Unix.
+    pub(crate) fn c_path(&self) -> Result<CString> {
+        path_to_c_string(&self.path)
+    }
+}
+
+/// The way a [`WeightsCache`] is finalized.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum WeightsCacheFinalization {
+    /// Leaves extra space in the cache, so that interpreters created after finalization
+    /// can still use it as long as their weights are already packed. Use this if
+    /// interpreters are created dynamically.
+    Soft,
+    /// Freezes the cache and shrinks it to the smallest possible size. Use this if all
+    /// interpreters are created up front.
+    Hard,
+}
+
+struct WeightsCacheInner {
+    /// The underlying [`TfLiteXNNPackDelegateWeightsCache`] C pointer.
+    ///
+    /// The mutex serializes packing weights into the cache and finalizing it,
+    /// since the cache is not thread-safe while it is being built.
+    cache_ptr: Mutex<*mut TfLiteXNNPackDelegateWeightsCache>,
+
+    /// The finalization of the cache, if finalized.
+    finalization: Mutex<Option<WeightsCacheFinalization>>,
+}
+
+unsafe impl Send for WeightsCacheInner {}
+unsafe impl Sync for WeightsCacheInner {}
+
+impl Drop for WeightsCacheInner {
+    fn drop(&mut self) {
+        let cache_ptr = *self.cache_ptr.get_mut().unwrap_or_else(|e| e.into_inner());
+        unsafe { TfLiteXNNPackDelegateWeightsCacheDelete(cache_ptr) }
+    }
+}
+
+/// An XNNPACK weights cache shared by the interpreters of one [`Model`].
+///
+/// Each XNNPACK delegate packs its own copy of the model weights by default, so `N`
+/// interpreters of a model use `N` times the memory. Interpreters attached to the same
+/// cache through [`InterpreterBuilder::xnnpack_weights_cache()`][crate::interpreter::InterpreterBuilder::xnnpack_weights_cache()]
+/// share a single copy instead.
+///
+/// The cache is reference counted: cloning it is cheap, and every interpreter keeps it alive
+/// until its delegate is deleted. Create and [allocate][crate::interpreter::Interpreter::allocate_tensors()]
+/// the interpreters first, then [finalize][WeightsCache::finalize()] the cache before invoking them.
+///
+/// # Examples
+///
+/// ```
+/// use tflitec::interpreter::InterpreterBuilder;
+/// use tflitec::model::Model;
+/// use tflitec::xnnpack::{WeightsCache, WeightsCacheFinalization};
+/// let model = Model::new("tests/add.bin")?;
+/// let weights_cache = WeightsCache::new()?;
+/// let build = || {
+///     InterpreterBuilder::new(&model)
+///         .add_xnnpack_delegate()
+///         .xnnpack_weights_cache(weights_cache.clone())
+///         .build()
+/// };
+/// let first = build()?;
+/// let second = build()?;
+/// first.allocate_tensors()?;
+/// second.allocate_tensors()?;
+/// weights_cache.finalize(WeightsCacheFinalization::Hard)?;
+/// # Ok::<(), tflitec::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct WeightsCache {
+    inner: Arc<WeightsCacheInner>,
+}
+
+impl WeightsCache {
+    /// Creates a new empty cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if TensorFlow Lite C fails to create the cache.
+    pub fn new() -> Result<WeightsCache> {
+        Self::from_raw(unsafe { TfLiteXNNPackDelegateWeightsCacheCreate() })
+    }
+
+    /// Creates a new empty cache which reserves `size` bytes up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if TensorFlow Lite C fails to create the cache.
+    pub fn with_size(size: usize) -> Result<WeightsCache> {
+        Self::from_raw(unsafe { TfLiteXNNPackDelegateWeightsCacheCreateWithSize(size) })
+    }
+
+    fn from_raw(cache_ptr: *mut TfLiteXNNPackDelegateWeightsCache) -> Result<WeightsCache> {
+        if cache_ptr.is_null() {
+            return Err(Error::new(ErrorKind::FailedToCreateWeightsCache));
+        }
+        Ok(WeightsCache {
+            inner: Arc::new(WeightsCacheInner {
+                cache_ptr: Mutex::new(cache_ptr),
+                finalization: Mutex::new(None),
+            }),
+        })
+    }
+
+    /// Finalizes the cache after the weights of all interpreters have been packed.
+    ///
+    /// # Arguments
+    ///
+    /// * `finalization`: The way the cache is finalized.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the cache has already been finalized or
+    /// TensorFlow Lite C fails to finalize it.
+    pub fn finalize(&self, finalization: WeightsCacheFinalization) -> Result<()> {
+        let cache_ptr = self.lock();
+        let mut current = self.inner.finalization.lock().unwrap();
+        if current.is_some() {
+            return Err(Error::new(ErrorKind::FailedToFinalizeWeightsCache));
+        }
+        let is_finalized = unsafe {
+            match finalization {
+                WeightsCacheFinalization::Soft => {
+                    TfLiteXNNPackDelegateWeightsCacheFinalizeSoft(*cache_ptr)
+                }
+                WeightsCacheFinalization::Hard => {
+                    TfLiteXNNPackDelegateWeightsCacheFinalizeHard(*cache_ptr)
+                }
+            }
+        };
+        if !is_finalized {
+            return Err(Error::new(ErrorKind::FailedToFinalizeWeightsCache));
+        }
+        *current = Some(finalization);
+        Ok(())
+    }
+
+    /// Returns the finalization of the cache, or [`None`] if it is not finalized yet.
+    pub fn finalization(&self) -> Option<WeightsCacheFinalization> {
+        *self.inner.finalization.lock().unwrap()
+    }
+
+    /// Returns the number of interpreters and handles sharing the cache.
+    pub fn reference_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+
+    /// Locks the cache while weights are packed into it.
+    pub(crate) fn lock(&self) -> MutexGuard<'_, *mut TfLiteXNNPackDelegateWeightsCache> {
+        self.inner.cache_ptr.lock().unwrap()
+    }
+}
+
+impl Debug for WeightsCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightsCache")
+            .field("finalization", &self.finalization())
+            .finish()
+    }
+}
+
+impl PartialEq for WeightsCache {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for WeightsCache {}
+
+impl Hash for WeightsCache {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.inner) as usize).hash(state)
+    }
+}
+
+/// Options of the XNNPACK delegate.
+///
+/// Every boolean field maps to a `TFLITE_XNNPACK_DELEGATE_FLAG_*` flag of the delegate.
+/// Enabled flags are added to the default flags of TensorFlow Lite, and a flag is only
+/// cleared if a field which is `true` by default is set to `false`. See [official README](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/lite/delegates/xnnpack/README.md)
+/// for details of each flag.
+///
+/// Weight caches are set on the
+/// [`InterpreterBuilder`][crate::interpreter::InterpreterBuilder], see
+/// [`WeightCacheFile`] and [`WeightsCache`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Ord, PartialOrd)]
+pub struct XnnpackOptions {
+    /// Enables acceleration of signed quantized 8-bit inference. The default is `true` if
+    /// the `xnnpack_qs8` feature is enabled.
+    pub qs8: bool,
+
+    /// Enables acceleration of unsigned quantized 8-bit inference. The default is `true` if
+    /// the `xnnpack_qu8` feature is enabled.
+    pub qu8: bool,
+
+    /// Forces FP16 inference for FP32 operators.
+    ///
+    /// This trades accuracy for speed on hardware with native FP16 arithmetic.
+    pub force_fp16: bool,
+
+    /// Enables acceleration of `FULLY_CONNECTED` operators with dynamic weights.
+    pub dynamic_fully_connected: bool,
+
+    /// Enables acceleration of `VAR_HANDLE`, `READ_VARIABLE` and `ASSIGN_VARIABLE` operators.
+    pub variable_operators: bool,
+
+    /// Enables transient indirection buffers to reduce memory usage of selected operators.
+    pub transient_indirection_buffer: bool,
+
+    /// Enables the latest XNNPACK operators and features. The default is `true`.
+    pub latest_operators: bool,
+
+    /// Enables subgraph reshaping for dynamic tensors. The default is `true`.
+    pub subgraph_reshaping: bool,
+
+    /// Indicates whether the delegate handles variable tensors itself.
+    pub handle_variable_ops: bool,
+
+    /// The number of threads of the delegate.
+    ///
+    /// The default is [`None`], which uses
+    /// [`Options::thread_count`][crate::interpreter::Options::thread_count].
+    pub thread_count: Option<i32>,
+}
+
+impl Default for XnnpackOptions {
+    fn default() -> Self {
+        Self {
+            qs8: cfg!(feature = "xnnpack_qs8"),
+            qu8: cfg!(feature = "xnnpack_qu8"),
+            force_fp16: false,
+            dynamic_fully_connected: false,
+            variable_operators: false,
+            transient_indirection_buffer: false,
+            latest_operators: true,
+            subgraph_reshaping: true,
+            handle_variable_ops: false,
+            thread_count: None,
+        }
+    }
+}
+
+impl XnnpackOptions {
+    /// Returns the `TFLITE_XNNPACK_DELEGATE_FLAG_*` bit mask of the enabled flags.
+    pub fn flags(&self) -> u32 {
+        self.flag_fields()
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .fold(0, |flags, (_, flag)| flags | flag)
+    }
+
+    /// Returns the bit mask of the flags which are enabled by default but disabled
+    /// in the options.
+    pub fn disabled_flags(&self) -> u32 {
+        let default = XnnpackOptions::default().flags();
+        default & !self.flags()
+    }
+
+    /// Returns `default_flags` with the enabled flags added and the disabled flags cleared.
+    pub fn apply_flags(&self, default_flags: u32) -> u32 {
+        (default_flags | self.flags()) & !self.disabled_flags()
+    }
+
+    fn flag_fields(&self) -> [(bool, u32); 8] {
+        [
+            (self.qs8, TFLITE_XNNPACK_DELEGATE_FLAG_QS8),
+            (self.qu8, TFLITE_XNNPACK_DELEGATE_FLAG_QU8),
+            (self.force_fp16, TFLITE_XNNPACK_DELEGATE_FLAG_FORCE_FP16),
+            (
+                self.dynamic_fully_connected,
+                TFLITE_XNNPACK_DELEGATE_FLAG_DYNAMIC_FULLY_CONNECTED,
+            ),
+            (
+                self.variable_operators,
+                TFLITE_XNNPACK_DELEGATE_FLAG_VARIABLE_OPERATORS,
+            ),
+            (
+                self.transient_indirection_buffer,
+                TFLITE_XNNPACK_DELEGATE_FLAG_TRANSIENT_INDIRECTION_BUFFER,
+            ),
+            (
+                self.latest_operators,
+                TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS,
+            ),
+            (
+                self.subgraph_reshaping,
+                TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_SUBGRAPH_RESHAPING,
+            ),
+        ]
+    }
+}
+
+/// The XNNPACK delegate of an [`Interpreter`][crate::interpreter::Interpreter].
+///
+/// It keeps the weight cache file path and the shared weights cache alive until the
+/// delegate is deleted.
+pub(crate) struct XnnpackDelegate {
+    /// The underlying [`TfLiteDelegate`] C pointer.
+    delegate_ptr: NonNull<TfLiteDelegate>,
+
+    /// The path of the weight cache file passed to the delegate.
+    #[allow(dead_code)]
+    weight_cache_path: Option<CString>,
+
+    /// The weights cache shared with other interpreters.
+    #[allow(dead_code)]
+    weights_cache: Option<WeightsCache>,
+}
+
+unsafe impl Send for XnnpackDelegate {}
+
+impl XnnpackDelegate {
+    /// Creates the delegate from the given interpreter `options`.
+    ///
+    /// `weights_cache_ptr` must be the locked pointer of `weights_cache`, if any.
+    ///
+    /// returns: [`None`] if TensorFlow Lite C fails to create the delegate.
+    pub(crate) fn create(
+        options: &Options,
+        weight_cache_path: Option<CString>,
+        weights_cache: Option<WeightsCache>,
+        weights_cache_ptr: *mut TfLiteXNNPackDelegateWeightsCache,
+    ) -> Option<XnnpackDelegate> {
+        // Use the library's default XNNPACK options and override them with ours
+        let mut xnnpack_options = unsafe { TfLiteXNNPackDelegateOptionsDefault() };
+        xnnpack_options.num_threads =
+            options.xnnpack.thread_count.unwrap_or(options.thread_count) as c_int;
+        xnnpack_options.flags = options.xnnpack.apply_flags(xnnpack_options.flags);
+        xnnpack_options.handle_variable_ops = options.xnnpack.handle_variable_ops;
+        if let Some(path) = weight_cache_path.as_ref() {
+            xnnpack_options.weight_cache_file_path = path.as_ptr();
+        }
+        xnnpack_options.weights_cache = weights_cache_ptr;
+
+        log::debug!(
+            "Creating XNNPACK delegate: threads={}, flags={:#x}, weights_cache={:?}, handle_variable_ops={}, weight_cache_file_path={:?}",
+            xnnpack_options.num_threads,
+            xnnpack_options.flags,
+            xnnpack_options.weights_cache,
+            xnnpack_options.handle_variable_ops,
+            xnnpack_options.weight_cache_file_path
+        );
+
+        let delegate_ptr = unsafe { TfLiteXNNPackDelegateCreate(&xnnpack_options) };
+        NonNull::new(delegate_ptr).map(|delegate_ptr| XnnpackDelegate {
+            delegate_ptr,
+            weight_cache_path,
+            weights_cache,
+        })
+    }
+}
+
+unsafe impl Delegate for XnnpackDelegate {
+    fn as_ptr(&self) -> *mut TfLiteDelegate {
+        self.delegate_ptr.as_ptr()
+    }
+
+    fn name(&self) -> &str {
+        "XNNPACK"
+    }
+}
+
+impl Drop for XnnpackDelegate {
+    fn drop(&mut self) {
+        unsafe { TfLiteXNNPackDelegateDelete(self.delegate_ptr.as_ptr()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::minimal_bindings::*;
+    use crate::model::Model;
+    use crate::xnnpack::{
+        WeightCacheFile, WeightCacheStatus, WeightsCache, WeightsCacheFinalization, XnnpackOptions,
+        WEIGHT_CACHE_HEADER_SIZE, WEIGHT_CACHE_VERSION,
+    };
+
+    #[test]
+    fn test_xnnpack_options_flags() {
+        let options = XnnpackOptions {
+            qs8: false,
+            qu8: false,
+            latest_operators: false,
+            subgraph_reshaping: false,
+            ..XnnpackOptions::default()
+        };
+        assert_eq!(options.flags(), 0);
+
+        let options = XnnpackOptions {
+            qs8: true,
+            force_fp16: true,
+            transient_indirection_buffer: true,
+            latest_operators: false,
+            subgraph_reshaping: false,
+            ..XnnpackOptions::default()
+        };
+        assert_eq!(
+            options.flags(),
+            TFLITE_XNNPACK_DELEGATE_FLAG_QS8
+                | TFLITE_XNNPACK_DELEGATE_FLAG_FORCE_FP16
+                | TFLITE_XNNPACK_DELEGATE_FLAG_TRANSIENT_INDIRECTION_BUFFER
+        );
+    }
+
+    #[test]
+    fn test_xnnpack_options_default_flags() {
+        let options = XnnpackOptions::default();
+        let mut flags = TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS
+            | TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_SUBGRAPH_RESHAPING;
+        if cfg!(feature = "xnnpack_qs8") {
+            flags |= TFLITE_XNNPACK_DELEGATE_FLAG_QS8;
+        }
+        if cfg!(feature = "xnnpack_qu8") {
+            flags |= TFLITE_XNNPACK_DELEGATE_FLAG_QU8;
+        }
+        assert_eq!(options.flags(), flags);
+        assert_eq!(options.disabled_flags(), 0);
+        // Flags enabled by TensorFlow Lite are kept.
+        assert_eq!(
+            options.apply_flags(
+                TFLITE_XNNPACK_DELEGATE_FLAG_FORCE_FP16 | TFLITE_XNNPACK_DELEGATE_FLAG_QU8
+            ),
+            flags | TFLITE_XNNPACK_DELEGATE_FLAG_FORCE_FP16 | TFLITE_XNNPACK_DELEGATE_FLAG_QU8
+        );
+
+        let options = XnnpackOptions {
+            latest_operators: false,
+            force_fp16: true,
+            ..XnnpackOptions::default()
+        };
+        assert_eq!(
+            options.disabled_flags(),
+            TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS
+        );
+        assert_eq!(
+            options.apply_flags(TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS),
+            (flags & !TFLITE_XNNPACK_DELEGATE_FLAG_ENABLE_LATEST_OPERATORS)
+                | TFLITE_XNNPACK_DELEGATE_FLAG_FORCE_FP16
+        );
+    }
+
+    #[test]
+    fn test_weight_cache_file_status() {
+        let model = Model::new("tests/add.bin").expect("Cannot load model from file");
+        let directory =
+            std::env::temp_dir().join(format!("tflitec_weight_cache_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let cache_file = WeightCacheFile::new(&directory);
+
+        let report = cache_file.prepare(&model).unwrap();
+        assert_eq!(report.status, WeightCacheStatus::Miss);
+        assert_eq!(report.path.parent(), Some(directory.as_path()));
+
+        // A file with a complete header and buffer list is loaded.
+        let mut packed = Vec::new();
+        packed.extend_from_slice(&WEIGHT_CACHE_VERSION.to_ne_bytes());
+        packed.extend_from_slice(&[0u8; 32]);
+        packed.extend_from_slice(&(WEIGHT_CACHE_HEADER_SIZE as u64).to_ne_bytes());
+        packed.extend_from_slice(&8u64.to_ne_bytes());
+        packed.extend_from_slice(&[0u8; 8]);
+        std::fs::write(&report.path, &packed).unwrap();
+        assert_eq!(
+            cache_file.prepare(&model).unwrap().status,
+            WeightCacheStatus::Hit
+        );
+
+        // Truncated and incomplete files are removed.
+        std::fs::write(&report.path, &packed[..packed.len() - 1]).unwrap();
+        assert_eq!(
+            cache_file.prepare(&model).unwrap().status,
+            WeightCacheStatus::Invalid
+        );
+        assert!(!report.path.exists());
+        packed[..8].copy_from_slice(&0u64.to_ne_bytes());
+        std::fs::write(&report.path, &packed).unwrap();
+        assert_eq!(
+            cache_file.prepare(&model).unwrap().status,
+            WeightCacheStatus::Invalid
+        );
+        std::fs::write(&report.path, b"packed").unwrap();
+        assert_eq!(
+            cache_file.prepare(&model).unwrap().status,
+            WeightCacheStatus::Invalid
+        );
+
+        // A cache of a previous model version is removed.
+        let stale_path = directory.join("add.0123456789abcdef.xnnpack_cache");
+        std::fs::write(&stale_path, b"packed").unwrap();
+        assert_eq!(
+            cache_file.prepare(&model).unwrap().status,
+            WeightCacheStatus::Stale
+        );
+        assert!(!stale_path.exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_weights_cache_shared_by_interpreters() {
+        use crate::interpreter::InterpreterBuilder;
+        use crate::ErrorKind;
+
+        let model = Model::new("tests/add.bin").expect("Cannot load model from file");
+        let weights_cache = WeightsCache::new().expect("Cannot create weights cache");
+        assert_eq!(weights_cache.reference_count(), 1);
+        let build = || {
+            InterpreterBuilder::new(&model)
+                .add_xnnpack_delegate()
+                .xnnpack_weights_cache(weights_cache.clone())
+                .build()
+                .unwrap()
+        };
+        let mut interpreters = vec![build()];
+        // Every interpreter holds the same number of references until it is dropped.
+        let references_per_interpreter = weights_cache.reference_count() - 1;
+        assert!(references_per_interpreter > 0);
+        interpreters.push(build());
+        assert_eq!(
+            weights_cache.reference_count(),
+            1 + 2 * references_per_interpreter
+        );
+        for interpreter in interpreters.iter() {
+            interpreter.allocate_tensors().unwrap();
+        }
+        weights_cache
+            .finalize(WeightsCacheFinalization::Hard)
+            .expect("Cannot finalize weights cache");
+        let err = weights_cache
+            .finalize(WeightsCacheFinalization::Soft)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::FailedToFinalizeWeightsCache);
+
+        let data = (0..192).map(|x| x as f32).collect::<Vec<f32>>();
+        let expected: Vec<f32> = data.iter().map(|e| e * 3.0).collect();
+        for interpreter in interpreters.iter() {
+            interpreter.copy(&data[..], 0).unwrap();
+            interpreter.invoke().unwrap();
+            assert_eq!(expected, interpreter.output(0).unwrap().data::<f32>());
+        }
+        drop(interpreters);
+        assert_eq!(weights_cache.reference_count(), 1);
+
+        let err = InterpreterBuilder::new(&model)
+            .add_xnnpack_delegate()
+            .xnnpack_weights_cache(weights_cache)
+            .xnnpack_weight_cache_file(WeightCacheFile::new(std::env::temp_dir()))
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidOptions(_)));
+    }
+}
@@ -0,0 +1,72 @@
+// A stub delegate implementing the TensorFlow Lite external delegate interface.
+//
+// It claims no nodes, so the graph runs on the builtin kernels. Counters exported
+// below let the tests check the lifecycle of the delegates created by the library.
+//
+// Options:
+//   fail=true     tflite_plugin_create_delegate returns NULL.
+//   prepare=error Prepare fails, so the delegate cannot be applied to a graph.
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef struct TfLiteDelegate {
+  void* data_;
+  int (*Prepare)(void* context, struct TfLiteDelegate* delegate);
+  void* CopyFromBufferHandle;
+  void* CopyToBufferHandle;
+  void* FreeBufferHandle;
+  int64_t flags;
+  void* opaque_delegate_builder;
+} TfLiteDelegate;
+
+static int live_count = 0;
+static int option_count = 0;
+static int prepare_count = 0;
+
+static int StubPrepare(void* context, TfLiteDelegate* delegate) {
+  (void)context;
+  prepare_count++;
+  // kTfLiteDelegateError if the delegate was created with prepare=error.
+  return delegate->data_ != NULL ? 2 : 0;
+}
+
+TfLiteDelegate* tflite_plugin_create_delegate(char** options_keys,
+                                              char** options_values,
+                                              size_t num_options,
+                                              void (*report_error)(const char*)) {
+  for (size_t i = 0; i < num_options; i++) {
+    if (strcmp(options_keys[i], "fail") == 0 &&
+        strcmp(options_values[i], "true") == 0) {
+      if (report_error != NULL) {
+        report_error("stub delegate: failing on request");
+      }
+      return NULL;
+    }
+  }
+  TfLiteDelegate* delegate = calloc(1, sizeof(TfLiteDelegate));
+  if (delegate == NULL) {
+    return NULL;
+  }
+  for (size_t i = 0; i < num_options; i++) {
+    if (strcmp(options_keys[i], "prepare") == 0 &&
+        strcmp(options_values[i], "error") == 0) {
+      delegate->data_ = delegate;
+    }
+  }
+  delegate->Prepare = StubPrepare;
+  option_count = (int)num_options;
+  live_count++;
+  return delegate;
+}
+
+void tflite_plugin_destroy_delegate(TfLiteDelegate* delegate) {
+  free(delegate);
+  live_count--;
+}
+
+int stub_delegate_live_count(void) { return live_count; }
+
+int stub_delegate_option_count(void) { return option_count; }
+
+int stub_delegate_prepare_count(void) { return prepare_count; }